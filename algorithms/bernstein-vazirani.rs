@@ -43,12 +43,8 @@ fn main() {
     state.apply_all(h());
 
     // Apply the inner products oracle
-    for i in 0..num_qubits {
-        if a & (1 << i) != 0 {
-            state.z(i as i32);
-        }
-        // Otherwise should apply identity gate, but computationally this doens't change the state.
-    }
+    let qubits: Vec<i32> = (0..num_qubits as i32).collect();
+    state.apply_phase_oracle(&qubits, |x| (a & x).count_ones() % 2 == 1);
 
     // Apply hadamard gates before measuring
     state.apply_all(h());
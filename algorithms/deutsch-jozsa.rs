@@ -36,10 +36,7 @@ fn main() {
     balanced_state.apply_all(h());
 
     // Oracle U_f
-    balanced_state.h(2);
-    balanced_state.z(0);
-    balanced_state.cx(1, 2);
-    balanced_state.h(2);
+    balanced_state.apply_phase_oracle(&[0, 1, 2], |x| (x & 1) ^ ((x >> 1) & (x >> 2) & 1) == 1);
 
     balanced_state.apply_all(h());
 
@@ -7,7 +7,7 @@ extern crate qcgpu;
 
 use qcgpu::State;
 
-fn superdense(input: &str) -> i32 {
+fn superdense(input: &str) -> u64 {
     let mut state = State::new(2, 0);
     let input_str = String::from(input);
 
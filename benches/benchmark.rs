@@ -61,6 +61,36 @@ fn benchmarks(c: &mut Criterion) {
         ],
     );
 
+    /////////////////////////////////////////////////////////////////////////////////////
+    // TOFFOLI GATE APPLICATION
+    /////////////////////////////////////////////////////////////////////////////////////
+
+    c.bench_function_over_inputs(
+        "Toffoli Gate Application",
+        |b, &size| {
+            let mut state = State::new(size, 1);
+            b.iter(|| state.toffoli(0, 1, 2));
+        },
+        vec![
+            3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25
+        ],
+    );
+
+    /////////////////////////////////////////////////////////////////////////////////////
+    // SWAP
+    /////////////////////////////////////////////////////////////////////////////////////
+
+    c.bench_function_over_inputs(
+        "Swap",
+        |b, &size| {
+            let mut state = State::new(size, 1);
+            b.iter(|| state.swap(0, 1));
+        },
+        vec![
+            2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25
+        ],
+    );
+
     /////////////////////////////////////////////////////////////////////////////////////
     // SINGLE MEASUREMENT
     /////////////////////////////////////////////////////////////////////////////////////
@@ -0,0 +1,83 @@
+#[macro_use]
+extern crate criterion;
+extern crate qcgpu;
+
+use criterion::{Criterion, Fun};
+use qcgpu::State;
+use qcgpu::backends::Cpu;
+use std::time::Duration;
+
+// Criterion struct for really fast benchmarks
+fn fast_benchmark() -> Criterion {
+    Criterion::default().sample_size(10).nresamples(2).warm_up_time(Duration::new(0,5))
+}
+
+fn benchmarks(c: &mut Criterion) {
+    ///////////////////////////////////////////////////////////////////////////////////
+    // SINGLE GATE APPLICATION
+    ///////////////////////////////////////////////////////////////////////////////////
+
+    let single_threaded = Fun::new("Single Thread", |b, i| {
+        let mut state = State::with_backend(*i, Cpu::with_threads(1).unwrap());
+        b.iter(|| state.h(0))
+    });
+    let multi_threaded = Fun::new("All Threads", |b, i| {
+        let mut state = State::with_backend(*i, Cpu::new());
+        b.iter(|| state.h(0))
+    });
+
+    c.bench_functions("CPU Single Gate Application", vec![single_threaded, multi_threaded], 25);
+
+    /////////////////////////////////////////////////////////////////////////////////////
+    // CONTROLLED GATE APPLICATION
+    /////////////////////////////////////////////////////////////////////////////////////
+
+    let single_threaded = Fun::new("Single Thread", |b, i| {
+        let mut state = State::with_backend(*i, Cpu::with_threads(1).unwrap());
+        b.iter(|| state.cx(0, 1))
+    });
+    let multi_threaded = Fun::new("All Threads", |b, i| {
+        let mut state = State::with_backend(*i, Cpu::new());
+        b.iter(|| state.cx(0, 1))
+    });
+
+    c.bench_functions("CPU Controlled Gate Application", vec![single_threaded, multi_threaded], 25);
+
+    /////////////////////////////////////////////////////////////////////////////////////
+    // SWAP
+    /////////////////////////////////////////////////////////////////////////////////////
+
+    let single_threaded = Fun::new("Single Thread", |b, i| {
+        let mut state = State::with_backend(*i, Cpu::with_threads(1).unwrap());
+        b.iter(|| state.swap(0, 1))
+    });
+    let multi_threaded = Fun::new("All Threads", |b, i| {
+        let mut state = State::with_backend(*i, Cpu::new());
+        b.iter(|| state.swap(0, 1))
+    });
+
+    c.bench_functions("CPU Swap", vec![single_threaded, multi_threaded], 25);
+
+    /////////////////////////////////////////////////////////////////////////////////////
+    // THOUSAND MEASUREMENTS
+    /////////////////////////////////////////////////////////////////////////////////////
+
+    let single_threaded = Fun::new("Single Thread", |b, i| {
+        let mut state = State::with_backend(*i, Cpu::with_threads(1).unwrap());
+        b.iter(|| state.measure_many(1000))
+    });
+    let multi_threaded = Fun::new("All Threads", |b, i| {
+        let mut state = State::with_backend(*i, Cpu::new());
+        b.iter(|| state.measure_many(1000))
+    });
+
+    c.bench_functions("CPU Thousand Measurements", vec![single_threaded, multi_threaded], 25);
+}
+
+criterion_group!{
+    name = benches;
+    config = fast_benchmark();
+    targets = benchmarks
+}
+
+criterion_main!(benches);
@@ -0,0 +1,322 @@
+//! Grover's Algorithm
+//!
+//! Searches for an `n` bit integer `x` where a predicate `f(x)` is true, using
+//! O(sqrt(2^n)) evaluations of a phase oracle for `f` rather than the O(2^n)
+//! evaluations needed classically.
+//!
+//! Starting from an equal superposition of every `x`, each Grover iteration
+//! negates the amplitudes of the solutions with the oracle, then reflects the
+//! state about the equal superposition. This rotates the state towards the
+//! solutions, so after about pi/4 sqrt(2^n / m) iterations, where `m` is the
+//! number of solutions, a measurement gives a solution with high probability.
+//!
+//! ```rust
+//! # extern crate qcgpu;
+//! # extern crate rand;
+//! use qcgpu::algorithms::grover;
+//! use qcgpu::backends::Cpu;
+//! use rand::thread_rng;
+//!
+//! let search = grover::search(6, 1, |x| x == 42, || Ok(Cpu::new()), &mut thread_rng()).unwrap();
+//! assert_eq!(search.solution, Some(42));
+//! ```
+//!
+//! `search_unknown` finds a solution without knowing how many there are, and
+//! `amplify` runs amplitude amplification from any initial state.
+//!
+//! See <https://cs.uwaterloo.ca/~watrous/LectureNotes.html>
+
+use rand::Rng;
+use std::f64::consts::PI;
+
+use backends::Backend;
+use error::Result;
+use gates::h;
+use precision::Real;
+use state::State;
+
+/// The number of times `search` runs the circuit before giving up
+const SEARCH_ATTEMPTS: u32 = 4;
+
+/// The factor the maximum number of iterations grows by in `search_unknown`
+const UNKNOWN_SEARCH_GROWTH: f64 = 6.0 / 5.0;
+
+/// The number of iterations, in multiples of sqrt(2^n), after which
+/// `search_unknown` decides there is no solution
+const UNKNOWN_SEARCH_LIMIT: f64 = 18.0;
+
+/// The outcome of a search
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Search {
+    /// The solution found, or `None` if no measurement gave a solution
+    pub solution: Option<u64>,
+    /// The value of the last measurement
+    pub measured: u64,
+    /// The number of times the circuit was run and measured
+    pub measurements: u32,
+    /// The total number of Grover iterations, each of which applies the oracle once
+    pub iterations: u64,
+    /// The probability that the last measurement would give a solution
+    pub success_probability: Real,
+}
+
+/// The number of Grover iterations which maximises the probability of measuring
+/// one of `num_solutions` solutions in a register of `num_qubits` qubits.
+///
+/// Returns 0 if there are no solutions, or every value is a solution.
+///
+/// ```rust
+/// # extern crate qcgpu;
+/// use qcgpu::algorithms::grover::optimal_iterations;
+///
+/// assert_eq!(optimal_iterations(3, 1), 2);
+/// assert_eq!(optimal_iterations(10, 1), 25);
+/// assert_eq!(optimal_iterations(10, 4), 12);
+/// ```
+pub fn optimal_iterations(num_qubits: u32, num_solutions: u64) -> u32 {
+    let size = 2.0_f64.powi(num_qubits as i32);
+    if num_solutions == 0 || num_solutions as f64 >= size {
+        return 0;
+    }
+
+    // Each iteration rotates the state by 2 theta, starting theta from the
+    // states which aren't solutions
+    let theta = (num_solutions as f64 / size).sqrt().asin();
+    (PI / (4.0 * theta)).floor() as u32
+}
+
+/// Search a register of `num_qubits` qubits for a value where `oracle` is true,
+/// given the number of solutions.
+///
+/// The circuit is run with the optimal number of iterations up to `SEARCH_ATTEMPTS`
+/// times, until a measurement gives a solution. The registers are created on
+/// backends returned by `backend`, and seeded from `rng`.
+///
+/// Returns an error if a register can't be created.
+pub fn search<B, F, O, R>(
+    num_qubits: u32,
+    num_solutions: u64,
+    oracle: O,
+    mut backend: F,
+    rng: &mut R,
+) -> Result<Search>
+where
+    B: Backend + 'static,
+    F: FnMut() -> Result<B>,
+    O: Fn(u64) -> bool,
+    R: Rng,
+{
+    let iterations = optimal_iterations(num_qubits, num_solutions);
+    let mut total = Search {
+        solution: None,
+        measured: 0,
+        measurements: 0,
+        iterations: 0,
+        success_probability: 0.0,
+    };
+
+    for _ in 0..SEARCH_ATTEMPTS {
+        let search = amplify(
+            num_qubits,
+            hadamards,
+            hadamards,
+            &oracle,
+            iterations,
+            &mut backend,
+            rng,
+        )?;
+
+        total = accumulate(total, search);
+        if total.solution.is_some() {
+            break;
+        }
+    }
+
+    Ok(total)
+}
+
+/// Search a register of `num_qubits` qubits for one of the marked items.
+///
+/// Items which don't fit in the register are ignored. See `search`.
+pub fn search_items<B, F, R>(
+    num_qubits: u32,
+    items: &[u64],
+    backend: F,
+    rng: &mut R,
+) -> Result<Search>
+where
+    B: Backend + 'static,
+    F: FnMut() -> Result<B>,
+    R: Rng,
+{
+    let mut items: Vec<u64> = items
+        .iter()
+        .cloned()
+        .filter(|&item| num_qubits >= 64 || item >> num_qubits == 0)
+        .collect();
+    items.sort();
+    items.dedup();
+
+    search(
+        num_qubits,
+        items.len() as u64,
+        |x| items.binary_search(&x).is_ok(),
+        backend,
+        rng,
+    )
+}
+
+/// Search a register of `num_qubits` qubits for a value where `oracle` is true,
+/// without knowing the number of solutions.
+///
+/// This is the randomised schedule of Boyer, Brassard, Høyer and Tapp. The
+/// circuit is run with a random number of iterations below a bound, which grows
+/// by `UNKNOWN_SEARCH_GROWTH` after each failed measurement, up to sqrt(2^n).
+/// When there are `m` solutions, the expected number of iterations is O(sqrt(2^n / m)).
+/// Once `UNKNOWN_SEARCH_LIMIT` sqrt(2^n) iterations and measurements have been run
+/// without finding a solution, the search gives up, so the solution is `None`.
+///
+/// Returns an error if a register can't be created.
+pub fn search_unknown<B, F, O, R>(
+    num_qubits: u32,
+    oracle: O,
+    mut backend: F,
+    rng: &mut R,
+) -> Result<Search>
+where
+    B: Backend + 'static,
+    F: FnMut() -> Result<B>,
+    O: Fn(u64) -> bool,
+    R: Rng,
+{
+    let root = 2.0_f64.powi(num_qubits as i32).sqrt();
+    let limit = UNKNOWN_SEARCH_LIMIT * root;
+    let mut bound: f64 = 1.0;
+    let mut total = Search {
+        solution: None,
+        measured: 0,
+        measurements: 0,
+        iterations: 0,
+        success_probability: 0.0,
+    };
+
+    // Checking each measurement evaluates the oracle once more, so it counts
+    // towards the limit as well
+    while total.solution.is_none()
+        && ((total.iterations + u64::from(total.measurements)) as f64) < limit
+    {
+        let iterations = rng.gen_range(0, bound.ceil() as u32);
+        let search = amplify(
+            num_qubits,
+            hadamards,
+            hadamards,
+            &oracle,
+            iterations,
+            &mut backend,
+            rng,
+        )?;
+
+        total = accumulate(total, search);
+        bound = (bound * UNKNOWN_SEARCH_GROWTH).min(root);
+    }
+
+    Ok(total)
+}
+
+/// Run amplitude amplification on a register of `num_qubits` qubits, and measure it.
+///
+/// `prepare` is applied to the register, which starts in the state |0>. Each of
+/// the `iterations` then negates the amplitudes of the states where `oracle` is
+/// true, applies `unprepare`, which must undo `prepare`, negates the amplitude of
+/// |0>, and applies `prepare` again. This reflects the state about the prepared
+/// state, amplifying the amplitudes of the solutions. Grover's algorithm is the
+/// case where `prepare` is a Hadamard gate on every qubit.
+///
+/// The register is created on a backend returned by `backend`, and seeded from `rng`.
+///
+/// ```rust
+/// # extern crate qcgpu;
+/// # extern crate rand;
+/// use qcgpu::algorithms::grover;
+/// use qcgpu::backends::Cpu;
+/// use qcgpu::gates::{h, ry};
+/// use qcgpu::State;
+/// use rand::thread_rng;
+///
+/// // Start with qubit 0 mostly 0, and search for values with qubit 0 set
+/// let prepare = |state: &mut State| {
+///     state.try_apply_gate(0, ry(0.4))?;
+///     state.try_apply_gate(1, h())
+/// };
+/// let unprepare = |state: &mut State| {
+///     state.try_apply_gate(1, h())?;
+///     state.try_apply_gate(0, ry(-0.4))
+/// };
+///
+/// let search = grover::amplify(2, prepare, unprepare, |x| x & 1 == 1, 3, &mut || Ok(Cpu::new()),
+///                              &mut thread_rng()).unwrap();
+/// assert!(search.success_probability > 0.95);
+/// ```
+///
+/// Returns an error if the register can't be created, or `prepare` or `unprepare` fail.
+pub fn amplify<B, F, P, U, O, R>(
+    num_qubits: u32,
+    mut prepare: P,
+    mut unprepare: U,
+    oracle: O,
+    iterations: u32,
+    backend: &mut F,
+    rng: &mut R,
+) -> Result<Search>
+where
+    B: Backend + 'static,
+    F: FnMut() -> Result<B>,
+    P: FnMut(&mut State) -> Result<()>,
+    U: FnMut(&mut State) -> Result<()>,
+    O: Fn(u64) -> bool,
+    R: Rng,
+{
+    let qubits: Vec<i32> = (0..num_qubits as i32).collect();
+    let mut state = State::try_with_backend(num_qubits, backend()?)?.with_seed(rng.gen());
+
+    prepare(&mut state)?;
+    for _ in 0..iterations {
+        state.try_apply_phase_oracle(&qubits, &oracle)?;
+        unprepare(&mut state)?;
+        state.try_apply_phase_oracle(&qubits, |x| x == 0)?;
+        prepare(&mut state)?;
+    }
+
+    let success_probability = state
+        .try_get_probabilities()?
+        .iter()
+        .enumerate()
+        .filter(|&(x, _)| oracle(x as u64))
+        .fold(0.0, |sum, (_, probability)| sum + probability);
+
+    let measured = state.try_measure()?;
+
+    Ok(Search {
+        solution: if oracle(measured) { Some(measured) } else { None },
+        measured,
+        measurements: 1,
+        iterations: u64::from(iterations),
+        success_probability,
+    })
+}
+
+/// Put the register into an equal superposition, or undo it
+fn hadamards(state: &mut State) -> Result<()> {
+    state.try_apply_all(h())
+}
+
+/// Combine the statistics of a run with those of the previous runs
+fn accumulate(total: Search, run: Search) -> Search {
+    Search {
+        solution: run.solution,
+        measured: run.measured,
+        measurements: total.measurements + run.measurements,
+        iterations: total.iterations + run.iterations,
+        success_probability: run.success_probability,
+    }
+}
@@ -0,0 +1,9 @@
+//! Quantum Algorithms
+//!
+//! Implementations of quantum algorithms built on `State`. Each algorithm
+//! creates the registers it needs from a backend constructor, so it can run
+//! on any backend, and draws its random numbers from a caller supplied
+//! generator, so a run can be made reproducible by seeding it.
+
+pub mod grover;
+pub mod shor;
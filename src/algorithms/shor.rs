@@ -0,0 +1,248 @@
+//! Shor's Algorithm
+//!
+//! Finds the factors of a composite integer `n`, using a quantum register to
+//! find the order of a random element of the multiplicative group modulo `n`.
+//!
+//! Even numbers and prime powers are factored classically. For any other
+//! composite, a random `a` coprime to `n` is chosen and its order `r`, the
+//! smallest positive integer with a^r = 1 (mod n), is found. If `r` is even
+//! and a^(r/2) is not -1 (mod n), then gcd(a^(r/2) - 1, n) is a factor of `n`.
+//! This happens for at least half of the possible choices of `a`.
+//!
+//! ```rust
+//! # extern crate qcgpu;
+//! # extern crate rand;
+//! use qcgpu::algorithms::shor;
+//! use qcgpu::backends::Cpu;
+//! use rand::thread_rng;
+//!
+//! let factors = shor::factor(15, || Ok(Cpu::new()), &mut thread_rng()).unwrap();
+//! assert_eq!(factors, Some((3, 5)));
+//! ```
+//!
+//! See <https://cs.uwaterloo.ca/~watrous/LectureNotes.html>
+
+use rand::Rng;
+
+use arithmetic::{pow_mod, Arithmetic};
+use backends::Backend;
+use error::Result;
+use gates::h;
+use state::State;
+
+/// The number of times `find_order` runs the quantum circuit before giving up
+const ORDER_FINDING_ATTEMPTS: u32 = 8;
+
+/// Factor `n` into two integers greater than 1, the smaller first.
+///
+/// The registers used for order finding are created on backends returned by
+/// `backend`, and seeded from `rng`. Returns `None` if `n` is prime or less than 4.
+///
+/// Returns an error if a register can't be created, which happens when `n` is
+/// too large to simulate.
+pub fn factor<B, F, R>(n: u64, mut backend: F, rng: &mut R) -> Result<Option<(u64, u64)>>
+where
+    B: Backend + 'static,
+    F: FnMut() -> Result<B>,
+    R: Rng,
+{
+    if n < 4 || is_prime(n) {
+        return Ok(None);
+    }
+    if n & 1 == 0 {
+        return Ok(Some((2, n / 2)));
+    }
+    if let Some(root) = perfect_power_root(n) {
+        return Ok(Some((root, n / root)));
+    }
+
+    loop {
+        let a = rng.gen_range(2, n);
+
+        // A lucky choice of a shares a factor with n, and no quantum register is needed
+        let d = gcd(a, n);
+        if d > 1 {
+            return Ok(Some(ordered(d, n / d)));
+        }
+
+        if let Some(r) = find_order(a, n, &mut backend, rng)? {
+            if r & 1 == 0 {
+                // a^(r/2) is a square root of 1 other than 1, so if it isn't -1
+                // then n divides (x - 1)(x + 1) without dividing either
+                let x = pow_mod(a, r / 2, n);
+                if x != n - 1 {
+                    let d = gcd(x - 1, n);
+                    return Ok(Some(ordered(d, n / d)));
+                }
+            }
+        }
+    }
+}
+
+/// Find the order of `a` modulo `n`: the smallest positive integer `r` such
+/// that a^r = 1 (mod n). `a` and `n` must be coprime, and `n` at least 2.
+///
+/// The exponent a^x mod n is calculated with `Arithmetic::PowModXor` for a
+/// superposition of every `x` in a register of 2 log2(n) qubits. The inverse
+/// quantum Fourier transform of that register is then measured, giving a value
+/// close to a multiple of 2^(2 log2(n))/r, from which `r` is found with
+/// continued fractions.
+///
+/// The circuit is run up to `ORDER_FINDING_ATTEMPTS` times, on registers created
+/// on backends returned by `backend` and seeded from `rng`. Returns `None` if
+/// every attempt failed to find the order.
+///
+/// Returns an error if a register can't be created, which happens when `n` is
+/// too large to simulate.
+pub fn find_order<B, F, R>(a: u64, n: u64, backend: &mut F, rng: &mut R) -> Result<Option<u64>>
+where
+    B: Backend + 'static,
+    F: FnMut() -> Result<B>,
+    R: Rng,
+{
+    if n < 2 || gcd(a, n) != 1 {
+        return Ok(None);
+    }
+
+    // The output register holds values below n, and the input register has twice
+    // as many qubits so the measured fraction determines r
+    let output_width = 64 - (n - 1).leading_zeros();
+    let input_width = 2 * output_width;
+    let input: Vec<i32> = (output_width..output_width + input_width)
+        .map(|qubit| qubit as i32)
+        .collect();
+
+    for _ in 0..ORDER_FINDING_ATTEMPTS {
+        let mut state =
+            State::try_with_backend(input_width + output_width, backend()?)?.with_seed(rng.gen());
+
+        for &qubit in &input {
+            state.try_apply_gate(qubit, h())?;
+        }
+        state.try_apply_arithmetic(&Arithmetic::PowModXor {
+            input: input[0]..input[0] + input_width as i32,
+            output: 0..output_width as i32,
+            base: a % n,
+            modulus: n,
+        })?;
+        state.try_inverse_qft(input[0]..input[0] + input_width as i32)?;
+
+        let measured = state.try_measure_qubits(&input)?;
+
+        for denominator in convergent_denominators(measured, 1 << input_width, n) {
+            // The denominator divides r, so r is found among its multiples
+            let multiple = (1..n / denominator + 1)
+                .map(|k| k * denominator)
+                .find(|&r| pow_mod(a, r, n) == 1);
+
+            if let Some(multiple) = multiple {
+                return Ok(Some(reduce_order(a, n, multiple)));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// The denominators of the continued fraction convergents of
+/// `numerator / denominator`, up to `max`
+fn convergent_denominators(mut numerator: u64, mut denominator: u64, max: u64) -> Vec<u64> {
+    let mut denominators = Vec::new();
+    let (mut previous, mut current) = (0, 1);
+
+    while numerator != 0 {
+        let quotient = denominator / numerator;
+        let next = quotient * current + previous;
+        if next > max {
+            break;
+        }
+
+        denominators.push(next);
+        previous = current;
+        current = next;
+
+        let remainder = denominator % numerator;
+        denominator = numerator;
+        numerator = remainder;
+    }
+
+    denominators
+}
+
+/// The order of `a` modulo `n`, given a multiple of it
+fn reduce_order(a: u64, n: u64, mut multiple: u64) -> u64 {
+    let mut remaining = multiple;
+    let mut prime = 2;
+
+    // Divide out each prime factor of the multiple for as long as the result
+    // is still a multiple of the order
+    while remaining > 1 {
+        if remaining % prime == 0 {
+            while remaining % prime == 0 {
+                remaining /= prime;
+            }
+            while multiple % prime == 0 && pow_mod(a, multiple / prime, n) == 1 {
+                multiple /= prime;
+            }
+        }
+        prime += 1;
+    }
+
+    multiple
+}
+
+/// Calculate the greatest common divisor (Euclid's algorithm)
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let tmp = a;
+        a = b;
+        b = tmp % b;
+    }
+    a
+}
+
+/// A pair of factors, the smaller first
+fn ordered(a: u64, b: u64) -> (u64, u64) {
+    (a.min(b), a.max(b))
+}
+
+/// Whether `n` is prime, with a Miller-Rabin test that is exact for every
+/// 64 bit integer
+fn is_prime(n: u64) -> bool {
+    const WITNESSES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+    if n < 2 {
+        return false;
+    }
+    if let Some(&p) = WITNESSES.iter().find(|&&p| n % p == 0) {
+        return n == p;
+    }
+
+    // n - 1 = 2^s d, with d odd
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+
+    WITNESSES.iter().all(|&a| {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = pow_mod(x, 2, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+/// The smallest integer whose power is `n`, if `n` is a perfect power
+fn perfect_power_root(n: u64) -> Option<u64> {
+    (2..64 - n.leading_zeros()).rev().find_map(|k| {
+        // Rounding errors in the floating point root are at most one either way
+        let estimate = (n as f64).powf(1.0 / f64::from(k)).round() as u64;
+        (estimate.saturating_sub(1)..estimate + 2)
+            .find(|root| *root > 1 && root.checked_pow(k) == Some(n))
+    })
+}
@@ -0,0 +1,379 @@
+//! Reversible Arithmetic
+//!
+//! Arithmetic on integers stored in ranges of qubits, where the first qubit
+//! of a range is the least significant bit. Every operation maps each basis
+//! state to exactly one other, so it can be applied to a superposition of
+//! values at once, and undone.
+//!
+//! ```
+//!# extern crate qcgpu;
+//!# use qcgpu::State;
+//!# use qcgpu::arithmetic::Arithmetic;
+//!# use qcgpu::backends::Cpu;
+//! // |x = 5>|y = 3>, with x in qubits 0..3 and y in qubits 3..6
+//! let mut state = State::from_bit_string_with_backend("|011101>", Cpu::new());
+//!
+//! state.apply_arithmetic(&Arithmetic::AddRegister { source: 0..3, target: 3..6 });
+//! assert_eq!(state.measure(), 0b000101);
+//! ```
+//!
+//! Backends apply the operations as a `Permutation` of the basis states,
+//! which gives the state each amplitude is moved from.
+
+use std::ops::Range;
+
+use error::{Error, Result};
+use gates::Control;
+
+/// An arithmetic operation on the integers held in ranges of qubits
+///
+/// The ranges an operation acts on must not overlap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Arithmetic {
+    /// Add a constant to the target, modulo 2^width
+    AddConstant {
+        /// The register added to
+        target: Range<i32>,
+        /// The constant to add
+        constant: u64,
+    },
+    /// Subtract a constant from the target, modulo 2^width
+    SubtractConstant {
+        /// The register subtracted from
+        target: Range<i32>,
+        /// The constant to subtract
+        constant: u64,
+    },
+    /// Add the value of the source to the target, modulo 2^width of the target
+    AddRegister {
+        /// The register to add
+        source: Range<i32>,
+        /// The register added to
+        target: Range<i32>,
+    },
+    /// Subtract the value of the source from the target, modulo 2^width of the target
+    SubtractRegister {
+        /// The register to subtract
+        source: Range<i32>,
+        /// The register subtracted from
+        target: Range<i32>,
+    },
+    /// Multiply the target by `factor` modulo `modulus`. Values of the target
+    /// which are not less than the modulus are left unchanged. The factor must
+    /// be coprime to the modulus, for the multiplication to be reversible.
+    MultiplyMod {
+        /// The register multiplied
+        target: Range<i32>,
+        /// The factor to multiply by
+        factor: u64,
+        /// The modulus, which must fit in the target
+        modulus: u64,
+    },
+    /// Multiply the target by `base^x` modulo `modulus`, where `x` is the value
+    /// of the exponent. Values of the target which are not less than the modulus
+    /// are left unchanged. The base must be coprime to the modulus.
+    ///
+    /// Starting from a target of 1, this leaves `base^x mod modulus` in the target.
+    ExpMod {
+        /// The register holding the exponent
+        exponent: Range<i32>,
+        /// The register multiplied
+        target: Range<i32>,
+        /// The base of the exponent
+        base: u64,
+        /// The modulus, which must fit in the target
+        modulus: u64,
+    },
+    /// XOR `base^x mod modulus` into the output, where `x` is the value of the input.
+    ///
+    /// Starting from an output of 0, this leaves `base^x mod modulus` in the output,
+    /// whether or not the base is coprime to the modulus.
+    PowModXor {
+        /// The register holding the exponent
+        input: Range<i32>,
+        /// The register the result is XORed into
+        output: Range<i32>,
+        /// The base of the exponent
+        base: u64,
+        /// The modulus, which must fit in the output
+        modulus: u64,
+    },
+    /// Flip the result qubit if the value of `left` is less than the value of `right`
+    LessThan {
+        /// The left hand side of the comparison
+        left: Range<i32>,
+        /// The right hand side of the comparison
+        right: Range<i32>,
+        /// The qubit flipped when the comparison holds
+        result: i32,
+    },
+    /// Flip the result qubit if the value of the register is less than a constant
+    LessThanConstant {
+        /// The register compared
+        register: Range<i32>,
+        /// The constant it is compared with
+        constant: u64,
+        /// The qubit flipped when the comparison holds
+        result: i32,
+    },
+}
+
+impl Arithmetic {
+    /// Every qubit the operation acts on
+    pub fn qubits(&self) -> Vec<i32> {
+        let (first, second, result) = self.operands();
+        first.chain(second).chain(result).collect()
+    }
+
+    /// The registers the operation reads and writes, and the result qubit of a comparison
+    fn operands(&self) -> (Range<i32>, Range<i32>, Option<i32>) {
+        let none = 0..0;
+
+        match *self {
+            Arithmetic::AddConstant { ref target, .. }
+            | Arithmetic::SubtractConstant { ref target, .. }
+            | Arithmetic::MultiplyMod { ref target, .. } => (none, target.clone(), None),
+            Arithmetic::AddRegister {
+                ref source,
+                ref target,
+            }
+            | Arithmetic::SubtractRegister {
+                ref source,
+                ref target,
+            } => (source.clone(), target.clone(), None),
+            Arithmetic::ExpMod {
+                ref exponent,
+                ref target,
+                ..
+            } => (exponent.clone(), target.clone(), None),
+            Arithmetic::PowModXor {
+                ref input,
+                ref output,
+                ..
+            } => (input.clone(), output.clone(), None),
+            Arithmetic::LessThan {
+                ref left,
+                ref right,
+                result,
+            } => (left.clone(), right.clone(), Some(result)),
+            Arithmetic::LessThanConstant {
+                ref register,
+                result,
+                ..
+            } => (register.clone(), none, Some(result)),
+        }
+    }
+}
+
+/// The kind of a `Permutation`. The values must match the `OP_` constants
+/// in `src/cl/kernel.cl`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operation {
+    /// target += constants[0] * source + constants[1]
+    Add = 0,
+    /// target *= constants[0]^-1 mod constants[1]
+    MultiplyMod = 1,
+    /// target *= constants[0]^-x mod constants[1]
+    ExpMod = 2,
+    /// target ^= constants[0]^x mod constants[1]
+    PowModXor = 3,
+    /// Compares the registers, or the source and constants[0] if the target is empty
+    LessThan = 4,
+}
+
+/// A permutation of the basis states of a register, in the form backends
+/// apply arithmetic operations.
+///
+/// The permuted state vector is gathered from the original: the amplitude of
+/// each state is the amplitude of its `preimage` before the permutation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permutation {
+    pub(crate) operation: Operation,
+    pub(crate) control_mask: u64,
+    pub(crate) control_value: u64,
+    /// The first qubit and width of the source and target registers
+    pub(crate) registers: [(u32, u32); 2],
+    pub(crate) result: u32,
+    pub(crate) constants: [u64; 2],
+}
+
+impl Permutation {
+    /// The permutation which applies an operation, for the states where every
+    /// control qubit has its required value.
+    ///
+    /// Returns an error if a range of qubits is reversed, a qubit is outside of
+    /// a 64 qubit register, or the modulus or factor of a modular operation is
+    /// invalid. Qubits are not checked against the size of any particular register.
+    pub fn new(operation: &Arithmetic, controls: &[Control]) -> Result<Permutation> {
+        let (first, second, result) = operation.operands();
+        let registers = [register(&first)?, register(&second)?];
+        let result = match result {
+            Some(qubit) => register(&(qubit..qubit + 1))?.0,
+            None => 0,
+        };
+
+        let mut control_mask = 0;
+        let mut control_value = 0;
+        for control in controls {
+            let qubit = register(&(control.qubit()..control.qubit() + 1))?.0;
+            control_mask |= 1 << qubit;
+            if control.value() {
+                control_value |= 1 << qubit;
+            }
+        }
+
+        let width = registers[1].1;
+        let (operation, constants) = match *operation {
+            Arithmetic::AddConstant { constant, .. } => (Operation::Add, [0, constant]),
+            Arithmetic::SubtractConstant { constant, .. } => {
+                (Operation::Add, [0, constant.wrapping_neg()])
+            }
+            Arithmetic::AddRegister { .. } => (Operation::Add, [1, 0]),
+            Arithmetic::SubtractRegister { .. } => (Operation::Add, [mask(width), 0]),
+            Arithmetic::MultiplyMod {
+                factor, modulus, ..
+            } => (
+                Operation::MultiplyMod,
+                [inverse(factor, modulus, width)?, modulus],
+            ),
+            Arithmetic::ExpMod { base, modulus, .. } => (
+                Operation::ExpMod,
+                [inverse(base, modulus, width)?, modulus],
+            ),
+            Arithmetic::PowModXor { base, modulus, .. } => {
+                check_modulus(modulus, width)?;
+                (Operation::PowModXor, [base % modulus, modulus])
+            }
+            Arithmetic::LessThan { .. } => (Operation::LessThan, [0, 0]),
+            Arithmetic::LessThanConstant { constant, .. } => {
+                (Operation::LessThan, [constant, 0])
+            }
+        };
+
+        Ok(Permutation {
+            operation,
+            control_mask,
+            control_value,
+            registers,
+            result,
+            constants,
+        })
+    }
+
+    /// The state whose amplitude is moved to `state` by the permutation
+    pub fn preimage(&self, state: u64) -> u64 {
+        if state & self.control_mask != self.control_value {
+            return state;
+        }
+
+        let [(source_start, source_width), (target_start, target_width)] = self.registers;
+        let source = (state >> source_start) & mask(source_width);
+        let target = (state >> target_start) & mask(target_width);
+        let [c0, c1] = self.constants;
+
+        let target = match self.operation {
+            Operation::Add => {
+                target.wrapping_sub(c0.wrapping_mul(source)).wrapping_sub(c1) & mask(target_width)
+            }
+            Operation::MultiplyMod if target < c1 => mul_mod(target, c0, c1),
+            Operation::ExpMod if target < c1 => mul_mod(target, pow_mod(c0, source, c1), c1),
+            Operation::MultiplyMod | Operation::ExpMod => target,
+            Operation::PowModXor => target ^ (pow_mod(c0, source, c1) & mask(target_width)),
+            Operation::LessThan => {
+                let right = if target_width > 0 { target } else { c0 };
+                let flip = if source < right { 1 << self.result } else { 0 };
+                return state ^ flip;
+            }
+        };
+
+        (state & !(mask(target_width) << target_start)) | (target << target_start)
+    }
+}
+
+/// The first qubit and width of a range of qubits, which must lie
+/// within a 64 qubit register. A reversed range is an error, rather than
+/// being taken as an empty register.
+fn register(qubits: &Range<i32>) -> Result<(u32, u32)> {
+    if qubits.start > qubits.end {
+        return Err(Error::InvalidQubit {
+            qubit: qubits.end,
+            num_qubits: 64,
+        });
+    }
+    if qubits.start == qubits.end {
+        return Ok((0, 0));
+    }
+
+    for &qubit in &[qubits.start, qubits.end - 1] {
+        if !(0..64).contains(&qubit) {
+            return Err(Error::InvalidQubit {
+                qubit,
+                num_qubits: 64,
+            });
+        }
+    }
+
+    Ok((qubits.start as u32, (qubits.end - qubits.start) as u32))
+}
+
+/// A mask of the lowest `width` bits
+fn mask(width: u32) -> u64 {
+    if width >= 64 {
+        !0
+    } else {
+        (1 << width) - 1
+    }
+}
+
+/// Check that a modulus is at least 1, and that every value below it fits
+/// in a register of `width` qubits
+fn check_modulus(modulus: u64, width: u32) -> Result<()> {
+    if modulus == 0 || modulus - 1 > mask(width) {
+        return Err(Error::InvalidModulus { modulus, width });
+    }
+
+    Ok(())
+}
+
+/// The inverse of `value` modulo `modulus`, which is used to undo a multiplication
+fn inverse(value: u64, modulus: u64, width: u32) -> Result<u64> {
+    check_modulus(modulus, width)?;
+
+    // The extended Euclidean algorithm, keeping track of the coefficient of value
+    let (mut r0, mut r1) = (i128::from(modulus), i128::from(value % modulus));
+    let (mut t0, mut t1) = (0, 1);
+    while r1 != 0 {
+        let quotient = r0 / r1;
+        let (r, t) = (r0 - quotient * r1, t0 - quotient * t1);
+        r0 = r1;
+        r1 = r;
+        t0 = t1;
+        t1 = t;
+    }
+
+    if r0 != 1 {
+        return Err(Error::NotInvertible { value, modulus });
+    }
+
+    Ok(t0.rem_euclid(i128::from(modulus)) as u64)
+}
+
+/// Calculates a * b mod n
+pub(crate) fn mul_mod(a: u64, b: u64, n: u64) -> u64 {
+    (u128::from(a) * u128::from(b) % u128::from(n)) as u64
+}
+
+/// Calculates x^y mod n
+pub(crate) fn pow_mod(mut x: u64, mut y: u64, n: u64) -> u64 {
+    let mut r = 1 % n;
+    x %= n;
+    while y > 0 {
+        if y & 1 == 1 {
+            r = mul_mod(r, x, n);
+        }
+        y /= 2;
+        x = mul_mod(x, x, n);
+    }
+
+    r
+}
@@ -0,0 +1,526 @@
+//! Native CPU Backend
+//!
+//! A pure Rust implementation of the kernels in `src/cl/kernel.cl`.
+//! Useful on machines without an OpenCL driver, and as a reference
+//! implementation to test the OpenCL kernels against.
+//!
+//! Gates are applied in place. Each operation is split into blocks of
+//! amplitudes, which are processed in parallel on a thread pool.
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use rayon::prelude::*;
+use std::mem::{size_of, swap};
+use std::sync::Arc;
+
+use arithmetic::Permutation;
+use backends::Backend;
+use error::{Error, Result};
+use gates::{Gate, MatrixGate, TwoQubitGate};
+use oracle::TruthTable;
+use precision::{Complex, Real};
+use precision::consts::{FRAC_1_SQRT_2, PI};
+
+/// The number of amplitudes processed by each task
+const BLOCK_SIZE: usize = 1 << 12;
+
+/// A state vector stored in host memory
+#[derive(Debug, Clone)]
+pub struct Cpu {
+    amplitudes: Vec<Complex>,
+    /// The thread pool to run on. `None` uses the global rayon pool.
+    pool: Option<Arc<ThreadPool>>,
+}
+
+impl Cpu {
+    /// Create a new CPU backend, using one thread per logical core.
+    ///
+    /// The backend starts out holding a register with no qubits, use
+    /// `Backend::allocate` to create a register of the required size.
+    pub fn new() -> Cpu {
+        Cpu {
+            amplitudes: vec![Complex::new(1.0, 0.0)],
+            pool: None,
+        }
+    }
+
+    /// Create a new CPU backend which runs on its own pool of `num_threads` threads.
+    ///
+    /// ```rust
+    /// # extern crate qcgpu;
+    /// use qcgpu::State;
+    /// use qcgpu::backends::Cpu;
+    ///
+    /// let state = State::with_backend(10, Cpu::with_threads(2).unwrap());
+    /// ```
+    ///
+    /// Returns an error if the threads could not be started.
+    pub fn with_threads(num_threads: usize) -> Result<Cpu> {
+        let pool = ThreadPoolBuilder::new().num_threads(num_threads).build()?;
+
+        Ok(Cpu {
+            amplitudes: vec![Complex::new(1.0, 0.0)],
+            pool: Some(Arc::new(pool)),
+        })
+    }
+
+    /// The number of threads operations are split across
+    pub fn num_threads(&self) -> usize {
+        match self.pool {
+            Some(ref pool) => pool.current_num_threads(),
+            None => ::rayon::current_num_threads(),
+        }
+    }
+
+    /// Apply `op` to every pair of amplitudes which differ only in the target qubit,
+    /// in place. `op` is given the index of the state where the target is 0, followed
+    /// by the amplitudes where the target is 0 and 1.
+    ///
+    /// The state vector is split into blocks of pairs, which are processed in parallel.
+    fn for_each_pair<F>(&mut self, target: i32, op: F)
+    where
+        F: Fn(usize, &mut Complex, &mut Complex) + Sync,
+    {
+        let half = 1 << target;
+        // Group small chunks together, so each task gets roughly a block of pairs
+        let min_chunks = (BLOCK_SIZE / (2 * half)).max(1);
+        let Cpu {
+            ref mut amplitudes,
+            ref pool,
+        } = *self;
+
+        install(pool, || {
+            amplitudes
+                .par_chunks_mut(2 * half)
+                .with_min_len(min_chunks)
+                .enumerate()
+                .for_each(|(chunk, amps)| {
+                    let (zeros, ones) = amps.split_at_mut(half);
+
+                    zeros
+                        .par_chunks_mut(BLOCK_SIZE)
+                        .zip(ones.par_chunks_mut(BLOCK_SIZE))
+                        .enumerate()
+                        .for_each(|(block, (zeros, ones))| {
+                            let offset = chunk * 2 * half + block * BLOCK_SIZE;
+                            for (i, (zero, one)) in zeros.iter_mut().zip(ones).enumerate() {
+                                op(offset + i, zero, one);
+                            }
+                        })
+                })
+        });
+    }
+
+    /// Apply `op` to every group of four amplitudes which differ only in the
+    /// `low` and `high` qubits, in place. `op` is given the amplitudes ordered
+    /// by the basis states `|high low>`. The low qubit must be lower than the high qubit.
+    fn for_each_quad<F>(&mut self, low: i32, high: i32, op: F)
+    where
+        F: Fn([&mut Complex; 4]) + Sync,
+    {
+        let Cpu {
+            ref mut amplitudes,
+            ref pool,
+        } = *self;
+
+        install(pool, || {
+            amplitudes.par_chunks_mut(2 << high).for_each(|chunk| {
+                let (high_zeros, high_ones) = chunk.split_at_mut(1 << high);
+
+                high_zeros
+                    .par_chunks_mut(2 << low)
+                    .zip(high_ones.par_chunks_mut(2 << low))
+                    .with_min_len((BLOCK_SIZE >> (low + 2)).max(1))
+                    .for_each(|(high_zeros, high_ones)| {
+                        let (zeros, low_ones) = high_zeros.split_at_mut(1 << low);
+                        let (high_ones, ones) = high_ones.split_at_mut(1 << low);
+
+                        for (((zero, low_one), high_one), one) in zeros
+                            .iter_mut()
+                            .zip(low_ones)
+                            .zip(high_ones)
+                            .zip(ones)
+                        {
+                            op([zero, low_one, high_one, one]);
+                        }
+                    })
+            })
+        });
+    }
+
+    /// Apply `gate` to every amplitude pair of the target qubit for which
+    /// `condition` holds on the state index.
+    fn apply_conditional_gate<F>(&mut self, target: i32, gate: Gate, condition: F) -> Result<()>
+    where
+        F: Fn(usize) -> bool + Sync,
+    {
+        self.for_each_pair(target, |zero_state, zero, one| {
+            if condition(zero_state) {
+                let zero_amp = *zero;
+                let one_amp = *one;
+
+                *zero = gate.a * zero_amp + gate.b * one_amp;
+                *one = gate.c * zero_amp + gate.d * one_amp;
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// Run `op` on the given thread pool, or the global pool if there is none
+fn install<OP, R>(pool: &Option<Arc<ThreadPool>>, op: OP) -> R
+where
+    OP: FnOnce() -> R + Send,
+    R: Send,
+{
+    match *pool {
+        Some(ref pool) => pool.install(op),
+        None => op(),
+    }
+}
+
+impl Default for Cpu {
+    fn default() -> Cpu {
+        Cpu::new()
+    }
+}
+
+/// Look up the value of the qubits of a state in a truth table,
+/// where the first qubit is the lowest bit of the value
+fn lookup(state: usize, qubits: &[i32], table: &TruthTable) -> bool {
+    let x = qubits
+        .iter()
+        .enumerate()
+        .fold(0, |x, (bit, &qubit)| x | (((state >> qubit) & 1) << bit));
+
+    table.get(x as u64)
+}
+
+/// Allocate a state vector of `len` zero amplitudes, reporting
+/// allocation failure as an error rather than aborting.
+fn zeroed(len: usize) -> Result<Vec<Complex>> {
+    let mut amplitudes = Vec::new();
+    amplitudes.try_reserve_exact(len).map_err(|_| Error::OutOfMemory {
+        required: (len * size_of::<Complex>()) as u64,
+        available: None,
+    })?;
+    amplitudes.resize(len, Complex::new(0.0, 0.0));
+
+    Ok(amplitudes)
+}
+
+impl Backend for Cpu {
+    fn allocate(&mut self, num_qubits: u32, initial: u64) -> Result<()> {
+        let mut amplitudes = zeroed(1 << num_qubits)?;
+        amplitudes[initial as usize] = Complex::new(1.0, 0.0);
+
+        self.amplitudes = amplitudes;
+
+        Ok(())
+    }
+
+    fn load(&mut self, amplitudes: &[Complex]) -> Result<()> {
+        let mut new_amplitudes = zeroed(amplitudes.len())?;
+        new_amplitudes.copy_from_slice(amplitudes);
+
+        self.amplitudes = new_amplitudes;
+
+        Ok(())
+    }
+
+    fn apply_gate(&mut self, target: i32, gate: Gate) -> Result<()> {
+        self.apply_conditional_gate(target, gate, |_| true)
+    }
+
+    fn apply_multi_controlled_gate(
+        &mut self,
+        control_mask: u64,
+        control_value: u64,
+        target: i32,
+        gate: Gate,
+    ) -> Result<()> {
+        self.apply_conditional_gate(target, gate, |state| {
+            state as u64 & control_mask == control_value
+        })
+    }
+
+    fn apply_two_qubit_gate(&mut self, qubit0: i32, qubit1: i32, gate: TwoQubitGate) -> Result<()> {
+        let matrix = if qubit0 < qubit1 {
+            gate.matrix
+        } else {
+            gate.reversed().matrix
+        };
+
+        self.for_each_quad(qubit0.min(qubit1), qubit0.max(qubit1), |mut amps| {
+            let old = [*amps[0], *amps[1], *amps[2], *amps[3]];
+
+            for (amp, row) in amps.iter_mut().zip(matrix.iter()) {
+                **amp = row[0] * old[0] + row[1] * old[1] + row[2] * old[2] + row[3] * old[3];
+            }
+        });
+
+        Ok(())
+    }
+
+    fn apply_matrix(&mut self, qubits: &[i32], gate: &MatrixGate) -> Result<()> {
+        let dim = gate.dimension();
+        let matrix = gate.matrix();
+        let highest = *qubits.iter().max().unwrap_or(&0);
+
+        // The offset of each basis state of the gate from the state where all
+        // of its qubits are 0
+        let offsets: Vec<usize> = (0..dim)
+            .map(|i| {
+                qubits
+                    .iter()
+                    .enumerate()
+                    .filter(|&(bit, _)| i & (1 << bit) != 0)
+                    .fold(0, |offset, (_, &qubit)| offset | (1 << qubit))
+            })
+            .collect();
+
+        let Cpu {
+            ref mut amplitudes,
+            ref pool,
+        } = *self;
+
+        // Every group of amplitudes the gate acts on lies within one chunk
+        // of 2^(highest + 1) states, so the chunks are processed in parallel
+        install(pool, || {
+            amplitudes
+                .par_chunks_mut(2 << highest)
+                .with_min_len((BLOCK_SIZE >> (highest + 1)).max(1))
+                .for_each(|chunk| {
+                    let mut amps = vec![Complex::new(0.0, 0.0); dim];
+
+                    for base in 0..chunk.len() {
+                        if base & offsets[dim - 1] != 0 {
+                            continue;
+                        }
+
+                        for (amp, offset) in amps.iter_mut().zip(&offsets) {
+                            *amp = chunk[base | offset];
+                        }
+
+                        for (row, offset) in offsets.iter().enumerate() {
+                            chunk[base | offset] = matrix[row * dim..(row + 1) * dim]
+                                .iter()
+                                .zip(&amps)
+                                .fold(Complex::new(0.0, 0.0), |sum, (m, amp)| sum + m * amp);
+                        }
+                    }
+                })
+        });
+
+        Ok(())
+    }
+
+    fn apply_qft_layer(&mut self, target: i32, low: i32, inverse: bool) -> Result<()> {
+        // The rotations combine into one phase, proportional to the value of
+        // the qubits from low up to the target
+        let mask = (1 << (target - low)) - 1;
+        let step = PI / (1_u64 << (target - low)) as Real;
+        let step = if inverse { -step } else { step };
+
+        self.for_each_pair(target, |zero_state, zero, one| {
+            let phase = Complex::from_polar(&1.0, &(step * ((zero_state >> low) & mask) as Real));
+            let zero_amp = *zero;
+            let one_amp = if inverse { *one * phase } else { *one };
+
+            *zero = (zero_amp + one_amp) * FRAC_1_SQRT_2;
+            *one = (zero_amp - one_amp) * FRAC_1_SQRT_2;
+
+            if !inverse {
+                *one *= phase;
+            }
+        });
+
+        Ok(())
+    }
+
+    fn swap(&mut self, first_qubit: i32, second_qubit: i32) -> Result<()> {
+        if first_qubit == second_qubit {
+            return Ok(());
+        }
+
+        let low = first_qubit.min(second_qubit);
+        let high = first_qubit.max(second_qubit);
+        let Cpu {
+            ref mut amplitudes,
+            ref pool,
+        } = *self;
+
+        // Only the states where the qubits differ change. Within each chunk, the
+        // states where the high qubit is 0 and the low qubit is 1 swap with those
+        // where the high qubit is 1 and the low qubit is 0.
+        install(pool, || {
+            amplitudes.par_chunks_mut(2 << high).for_each(|chunk| {
+                let (zeros, ones) = chunk.split_at_mut(1 << high);
+
+                zeros
+                    .par_chunks_mut(2 << low)
+                    .zip(ones.par_chunks_mut(2 << low))
+                    .with_min_len((BLOCK_SIZE >> (low + 1)).max(1))
+                    .for_each(|(zeros, ones)| {
+                        zeros[1 << low..].swap_with_slice(&mut ones[..1 << low]);
+                    })
+            })
+        });
+
+        Ok(())
+    }
+
+    fn apply_permutation(&mut self, permutation: &Permutation) -> Result<()> {
+        let mut permuted = zeroed(self.amplitudes.len())?;
+        let amplitudes = &self.amplitudes;
+
+        // Each amplitude is gathered from its preimage, so the permutation
+        // can be applied in parallel without any two writes colliding
+        install(&self.pool, || {
+            permuted
+                .par_iter_mut()
+                .with_min_len(BLOCK_SIZE)
+                .enumerate()
+                .for_each(|(state, amp)| {
+                    *amp = amplitudes[permutation.preimage(state as u64) as usize];
+                })
+        });
+
+        self.amplitudes = permuted;
+
+        Ok(())
+    }
+
+    fn apply_phase_oracle(&mut self, qubits: &[i32], table: &TruthTable) -> Result<()> {
+        table.check_inputs(qubits)?;
+
+        let Cpu {
+            ref mut amplitudes,
+            ref pool,
+        } = *self;
+
+        install(pool, || {
+            amplitudes
+                .par_iter_mut()
+                .with_min_len(BLOCK_SIZE)
+                .enumerate()
+                .for_each(|(state, amp)| {
+                    if lookup(state, qubits, table) {
+                        *amp = -*amp;
+                    }
+                })
+        });
+
+        Ok(())
+    }
+
+    fn apply_bit_oracle(&mut self, inputs: &[i32], output: i32, table: &TruthTable) -> Result<()> {
+        table.check_inputs(inputs)?;
+
+        self.for_each_pair(output, |zero_state, zero, one| {
+            if lookup(zero_state, inputs, table) {
+                swap(zero, one);
+            }
+        });
+
+        Ok(())
+    }
+
+    fn probabilities(&self) -> Result<Vec<Real>> {
+        let amplitudes = &self.amplitudes;
+
+        Ok(install(&self.pool, || {
+            amplitudes
+                .par_iter()
+                .with_min_len(BLOCK_SIZE)
+                .map(|amp| amp.norm_sqr())
+                .collect()
+        }))
+    }
+
+    fn cumulative_probabilities(&self) -> Result<Vec<Real>> {
+        let mut cumulative = self.probabilities()?;
+
+        install(&self.pool, || {
+            // Scan each block in parallel, then add the total of the preceding blocks
+            let totals: Vec<Real> = cumulative
+                .par_chunks_mut(BLOCK_SIZE)
+                .map(|block| {
+                    let mut total = 0.0;
+                    for probability in block.iter_mut() {
+                        total += *probability;
+                        *probability = total;
+                    }
+                    total
+                })
+                .collect();
+
+            let offsets: Vec<Real> = totals
+                .iter()
+                .scan(0.0, |sum, total| {
+                    let offset = *sum;
+                    *sum += total;
+                    Some(offset)
+                })
+                .collect();
+
+            cumulative
+                .par_chunks_mut(BLOCK_SIZE)
+                .zip(offsets.par_iter())
+                .for_each(|(block, offset)| {
+                    for probability in block {
+                        *probability += offset;
+                    }
+                });
+        });
+
+        Ok(cumulative)
+    }
+
+    fn qubit_probability(&self, qubit: i32) -> Result<Real> {
+        let half = 1 << qubit;
+        let min_chunks = (BLOCK_SIZE / (2 * half)).max(1);
+        let amplitudes = &self.amplitudes;
+
+        Ok(install(&self.pool, || {
+            amplitudes
+                .par_chunks(2 * half)
+                .with_min_len(min_chunks)
+                .map(|chunk| {
+                    chunk[half..]
+                        .par_iter()
+                        .with_min_len(BLOCK_SIZE)
+                        .map(|amp| amp.norm_sqr())
+                        .sum::<Real>()
+                })
+                .sum()
+        }))
+    }
+
+    fn collapse(&mut self, qubit: i32, outcome: bool, norm: Real) -> Result<()> {
+        let zero_amp = Complex::new(0.0, 0.0);
+
+        self.for_each_pair(qubit, |_, zero, one| {
+            if outcome {
+                *zero = zero_amp;
+                *one *= norm;
+            } else {
+                *zero *= norm;
+                *one = zero_amp;
+            }
+        });
+
+        Ok(())
+    }
+
+    fn amplitudes(&self) -> Result<Vec<Complex>> {
+        let mut amplitudes = zeroed(self.amplitudes.len())?;
+        amplitudes.copy_from_slice(&self.amplitudes);
+
+        Ok(amplitudes)
+    }
+
+    fn info(&self) -> String {
+        format!("Device type: Host CPU ({} threads)", self.num_threads())
+    }
+}
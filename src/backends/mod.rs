@@ -0,0 +1,125 @@
+//! Simulation Backends
+//!
+//! A backend owns the state vector of a register and implements the
+//! operations that act upon it. `State` forwards every gate application
+//! and measurement to the backend it was created with.
+//!
+//! Two backends are provided:
+//!
+//! * `OpenCL`, which runs the kernels in `src/cl/kernel.cl` on an OpenCL device
+//! * `Cpu`, a native Rust implementation that needs no OpenCL driver
+//!
+//! Backends can assume that the qubit indices they are given have already
+//! been validated against the size of the register.
+
+use std::fmt;
+
+use arithmetic::Permutation;
+use device::Device;
+use error::Result;
+use gates::{Gate, MatrixGate, TwoQubitGate};
+use oracle::TruthTable;
+use precision::{Complex, Real};
+
+mod cpu;
+mod opencl;
+
+pub use self::cpu::Cpu;
+pub use self::opencl::OpenCL;
+
+/// Operations a state vector implementation must provide.
+///
+/// Every method mirrors one of the OpenCL kernels, so a register behaves
+/// identically whichever backend it is stored on. Backends must be `Send`,
+/// so that a register can be moved to another thread.
+pub trait Backend: fmt::Debug + Send {
+    /// Allocate a state vector for `num_qubits` qubits, initialized to the
+    /// basis state `|initial>`. Any previous state vector is discarded.
+    ///
+    /// Returns `Error::OutOfMemory` without discarding the previous state vector
+    /// if the new one does not fit in the memory available.
+    fn allocate(&mut self, num_qubits: u32, initial: u64) -> Result<()>;
+
+    /// Replace the state vector with the given amplitudes.
+    /// The number of amplitudes must be a power of two.
+    ///
+    /// Returns `Error::OutOfMemory` if they do not fit in the memory available.
+    fn load(&mut self, amplitudes: &[Complex]) -> Result<()>;
+
+    /// Apply a single qubit gate to the target qubit
+    fn apply_gate(&mut self, target: i32, gate: Gate) -> Result<()>;
+
+    /// Apply a single qubit gate to the target qubit, for the states where the
+    /// qubits in `control_mask` have the values given by the same bits of `control_value`.
+    /// The target is not one of the controls.
+    fn apply_multi_controlled_gate(
+        &mut self,
+        control_mask: u64,
+        control_value: u64,
+        target: i32,
+        gate: Gate,
+    ) -> Result<()>;
+
+    /// Apply a two qubit gate to the qubits. The qubits are distinct, and
+    /// `qubit0` is the low bit of the gate's basis states.
+    fn apply_two_qubit_gate(&mut self, qubit0: i32, qubit1: i32, gate: TwoQubitGate) -> Result<()>;
+
+    /// Apply a gate on several qubits. The qubits are distinct, there is one
+    /// for each qubit the gate acts on, and the first is the lowest bit of
+    /// the gate's basis states.
+    fn apply_matrix(&mut self, qubits: &[i32], gate: &MatrixGate) -> Result<()>;
+
+    /// Apply one layer of the quantum Fourier transform: a Hadamard gate on the
+    /// target, then a phase rotation by pi/2^(target - k) of the states where both
+    /// the target and qubit `k` are 1, for each qubit `k` from `low` up to the target.
+    ///
+    /// The inverse layer applies the opposite rotations, followed by the Hadamard gate.
+    fn apply_qft_layer(&mut self, target: i32, low: i32, inverse: bool) -> Result<()>;
+
+    /// Swap the states of two qubits
+    fn swap(&mut self, first_qubit: i32, second_qubit: i32) -> Result<()>;
+
+    /// Permute the basis states, moving the amplitude of each state's
+    /// `permutation.preimage` to it. This is how arithmetic operations are applied.
+    ///
+    /// The permuted amplitudes are gathered into a second state vector, so this
+    /// needs memory for twice the register. Returns `Error::OutOfMemory` without
+    /// changing the state vector if that memory isn't available.
+    fn apply_permutation(&mut self, permutation: &Permutation) -> Result<()>;
+
+    /// Negate the amplitude of each state where the value of the qubits is true
+    /// in the table. The qubits are distinct, and the first is the lowest bit
+    /// of the value looked up.
+    fn apply_phase_oracle(&mut self, qubits: &[i32], table: &TruthTable) -> Result<()>;
+
+    /// Flip the output qubit of each state where the value of the input qubits
+    /// is true in the table. The output is not one of the inputs, which are
+    /// distinct, and the first input is the lowest bit of the value looked up.
+    fn apply_bit_oracle(&mut self, inputs: &[i32], output: i32, table: &TruthTable) -> Result<()>;
+
+    /// The probability of measuring each basis state
+    fn probabilities(&self) -> Result<Vec<Real>>;
+
+    /// The cumulative probabilities of the basis states, where the `i`th
+    /// element is the probability of measuring a state less than or equal to `i`
+    fn cumulative_probabilities(&self) -> Result<Vec<Real>>;
+
+    /// The probability of measuring the qubit as 1
+    fn qubit_probability(&self, qubit: i32) -> Result<Real>;
+
+    /// Project the state vector onto the states where the qubit has the value
+    /// `outcome`, setting every other amplitude to zero and multiplying the
+    /// remaining amplitudes by `norm`.
+    fn collapse(&mut self, qubit: i32, outcome: bool, norm: Real) -> Result<()>;
+
+    /// A copy of the state vector
+    fn amplitudes(&self) -> Result<Vec<Complex>>;
+
+    /// A human readable description of where the state vector is stored
+    fn info(&self) -> String;
+
+    /// The OpenCL device the state vector is stored on, if any
+    fn device(&self) -> Option<Device> {
+        None
+    }
+}
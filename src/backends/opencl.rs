@@ -0,0 +1,562 @@
+//! OpenCL Backend
+//!
+//! Stores the state vector in a device buffer and applies the kernels
+//! from `src/cl/kernel.cl`.
+
+use ocl::{self, Buffer, MemFlags, Platform, ProQue};
+use std::mem::size_of;
+
+use arithmetic::Permutation;
+use backends::Backend;
+use device::{ocl_error, platforms, Device};
+use error::{Error, Result};
+use gates::{Gate, MatrixGate, TwoQubitGate};
+use kernel::KERNEL;
+use oracle::TruthTable;
+use precision::{Complex, Real};
+use precision::consts::{FRAC_1_SQRT_2, PI};
+
+/// The number of work items in each group of the reduction kernels
+const WORK_GROUP_SIZE: usize = 64;
+
+/// A state vector stored on an OpenCL device
+#[derive(Debug)]
+pub struct OpenCL {
+    buffer: Buffer<Complex>,
+    pro_que: ProQue,
+    device: Device,
+}
+
+impl OpenCL {
+    /// Compile the kernels for the device with the given index on the
+    /// default OpenCL platform.
+    ///
+    /// The backend starts out holding a register with no qubits, use
+    /// `Backend::allocate` to create a register of the required size.
+    pub fn new(device: usize) -> Result<OpenCL> {
+        // The platform `Platform::default` would choose, without its panic
+        // when there are no platforms
+        let platform_index = ocl::core::default_platform_idx();
+        let platform = *platforms()?
+            .get(platform_index)
+            .ok_or_else(|| Error::DeviceNotFound(format!("platform index {}", platform_index)))?;
+
+        let ocl_device = *ocl::Device::list_all(platform)
+            .map_err(ocl_error)?
+            .get(device)
+            .ok_or_else(|| Error::DeviceNotFound(format!("device index {}", device)))?;
+
+        let info = Device::from_ocl(platform_index, device, &platform, &ocl_device)?;
+
+        OpenCL::build(platform, ocl_device, info)
+    }
+
+    /// Compile the kernels for a device found with `qcgpu::device::list_devices`
+    /// or a `qcgpu::device::Selection`.
+    pub fn with_device(device: &Device) -> Result<OpenCL> {
+        let (platform, ocl_device) = device.to_ocl()?;
+
+        OpenCL::build(platform, ocl_device, device.clone())
+    }
+
+    fn build(platform: Platform, ocl_device: ocl::Device, device: Device) -> Result<OpenCL> {
+        let ocl_pq = ProQue::builder()
+            .src(KERNEL)
+            .platform(platform)
+            .device(ocl_device)
+            .dims(1)
+            .build()?;
+
+        let buffer = Buffer::builder()
+            .queue(ocl_pq.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(1)
+            .fill_val(Complex::new(1.0, 0.0))
+            .build()?;
+
+        Ok(OpenCL {
+            buffer,
+            pro_que: ocl_pq,
+            device,
+        })
+    }
+
+    /// Check that `num_vectors` state vectors of `num_amps` amplitudes fit on
+    /// the device, along with the buffer the probabilities are calculated into.
+    fn check_memory(&self, num_amps: usize, num_vectors: u64) -> Result<()> {
+        let required = (num_amps * size_of::<Complex>()) as u64;
+        if required > self.device.max_allocation {
+            return Err(Error::OutOfMemory {
+                required,
+                available: Some(self.device.max_allocation),
+            });
+        }
+
+        let total = num_vectors * required + (num_amps * size_of::<Real>()) as u64;
+        if total > self.device.global_memory {
+            return Err(Error::OutOfMemory {
+                required: total,
+                available: Some(self.device.global_memory),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Copy the qubits an oracle looks up, and its truth table, to the device
+    fn upload_oracle(&self, qubits: &[i32], table: &TruthTable) -> Result<(Buffer<u32>, Buffer<u32>)> {
+        table.check_inputs(qubits)?;
+
+        let mut qubits: Vec<u32> = qubits.iter().map(|&qubit| qubit as u32).collect();
+
+        // Buffers can't be empty, so an oracle on no qubits is given a
+        // qubit which the kernels never read
+        if qubits.is_empty() {
+            qubits.push(0);
+        }
+
+        let qubit_buffer: Buffer<u32> = Buffer::builder()
+            .queue(self.pro_que.queue().clone())
+            .flags(MemFlags::new().read_only().copy_host_ptr())
+            .len(qubits.len())
+            .copy_host_slice(&qubits)
+            .build()?;
+
+        let table_buffer: Buffer<u32> = Buffer::builder()
+            .queue(self.pro_que.queue().clone())
+            .flags(MemFlags::new().read_only().copy_host_ptr())
+            .len(table.words().len())
+            .copy_host_slice(table.words())
+            .build()?;
+
+        Ok((qubit_buffer, table_buffer))
+    }
+}
+
+impl Backend for OpenCL {
+    fn allocate(&mut self, num_qubits: u32, initial: u64) -> Result<()> {
+        let num_amps = 1 << num_qubits;
+        self.check_memory(num_amps, 1)?;
+        self.pro_que.set_dims(num_amps);
+
+        let source_buffer: Buffer<Complex> = Buffer::builder()
+            .queue(self.pro_que.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(num_amps)
+            .build()?;
+
+        let apply = self.pro_que
+            .kernel_builder("initalize_register")
+            .arg(&source_buffer)
+            .arg(initial)
+            .build()?;
+
+        unsafe {
+            apply.enq()?;
+        }
+
+        self.buffer = source_buffer;
+
+        Ok(())
+    }
+
+    fn load(&mut self, amplitudes: &[Complex]) -> Result<()> {
+        self.check_memory(amplitudes.len(), 1)?;
+        self.pro_que.set_dims(amplitudes.len());
+
+        self.buffer = Buffer::builder()
+            .queue(self.pro_que.queue().clone())
+            .flags(MemFlags::new().read_write().copy_host_ptr())
+            .len(amplitudes.len())
+            .copy_host_slice(amplitudes)
+            .build()?;
+
+        Ok(())
+    }
+
+    fn apply_gate(&mut self, target: i32, gate: Gate) -> Result<()> {
+        let apply = self.pro_que
+            .kernel_builder("apply_gate")
+            .global_work_size(self.buffer.len() / 2)
+            .arg(&self.buffer)
+            .arg(target)
+            .arg(gate.a)
+            .arg(gate.b)
+            .arg(gate.c)
+            .arg(gate.d)
+            .build()?;
+
+        unsafe {
+            apply.enq()?;
+        }
+
+        Ok(())
+    }
+
+    fn apply_multi_controlled_gate(
+        &mut self,
+        control_mask: u64,
+        control_value: u64,
+        target: i32,
+        gate: Gate,
+    ) -> Result<()> {
+        let apply = self.pro_que
+            .kernel_builder("apply_multi_controlled_gate")
+            .global_work_size(self.buffer.len() / 2)
+            .arg(&self.buffer)
+            .arg(control_mask)
+            .arg(control_value)
+            .arg(target)
+            .arg(gate.a)
+            .arg(gate.b)
+            .arg(gate.c)
+            .arg(gate.d)
+            .build()?;
+
+        unsafe {
+            apply.enq()?;
+        }
+
+        Ok(())
+    }
+
+    fn apply_two_qubit_gate(&mut self, qubit0: i32, qubit1: i32, gate: TwoQubitGate) -> Result<()> {
+        // The kernel expects the lower qubit first
+        let matrix = if qubit0 < qubit1 {
+            gate.matrix
+        } else {
+            gate.reversed().matrix
+        };
+        let flat: Vec<Complex> = matrix.iter().flat_map(|row| row.iter().cloned()).collect();
+
+        let matrix_buffer: Buffer<Complex> = Buffer::builder()
+            .queue(self.pro_que.queue().clone())
+            .flags(MemFlags::new().read_only().copy_host_ptr())
+            .len(flat.len())
+            .copy_host_slice(&flat)
+            .build()?;
+
+        let apply = self.pro_que
+            .kernel_builder("apply_two_qubit_gate")
+            .global_work_size(self.buffer.len() / 4)
+            .arg(&self.buffer)
+            .arg(qubit0.min(qubit1))
+            .arg(qubit0.max(qubit1))
+            .arg(&matrix_buffer)
+            .build()?;
+
+        unsafe {
+            apply.enq()?;
+        }
+
+        Ok(())
+    }
+
+    fn apply_matrix(&mut self, qubits: &[i32], gate: &MatrixGate) -> Result<()> {
+        let qubits: Vec<u32> = qubits.iter().map(|&qubit| qubit as u32).collect();
+        let mut sorted_qubits = qubits.clone();
+        sorted_qubits.sort();
+
+        let qubit_buffer: Buffer<u32> = Buffer::builder()
+            .queue(self.pro_que.queue().clone())
+            .flags(MemFlags::new().read_only().copy_host_ptr())
+            .len(qubits.len())
+            .copy_host_slice(&qubits)
+            .build()?;
+
+        let sorted_buffer: Buffer<u32> = Buffer::builder()
+            .queue(self.pro_que.queue().clone())
+            .flags(MemFlags::new().read_only().copy_host_ptr())
+            .len(sorted_qubits.len())
+            .copy_host_slice(&sorted_qubits)
+            .build()?;
+
+        let matrix_buffer: Buffer<Complex> = Buffer::builder()
+            .queue(self.pro_que.queue().clone())
+            .flags(MemFlags::new().read_only().copy_host_ptr())
+            .len(gate.matrix().len())
+            .copy_host_slice(gate.matrix())
+            .build()?;
+
+        let apply = self.pro_que
+            .kernel_builder("apply_matrix")
+            .global_work_size(self.buffer.len() >> qubits.len())
+            .arg(&self.buffer)
+            .arg(qubits.len() as u32)
+            .arg(&qubit_buffer)
+            .arg(&sorted_buffer)
+            .arg(&matrix_buffer)
+            .build()?;
+
+        unsafe {
+            apply.enq()?;
+        }
+
+        Ok(())
+    }
+
+    fn apply_qft_layer(&mut self, target: i32, low: i32, inverse: bool) -> Result<()> {
+        let step = PI / (1_u64 << (target - low)) as Real;
+
+        let apply = self.pro_que
+            .kernel_builder("apply_qft_layer")
+            .global_work_size(self.buffer.len() / 2)
+            .arg(&self.buffer)
+            .arg(target)
+            .arg(low)
+            .arg(u32::from(inverse))
+            .arg(if inverse { -step } else { step })
+            .arg(FRAC_1_SQRT_2)
+            .build()?;
+
+        unsafe {
+            apply.enq()?;
+        }
+
+        Ok(())
+    }
+
+    fn swap(&mut self, first_qubit: i32, second_qubit: i32) -> Result<()> {
+        if first_qubit == second_qubit {
+            return Ok(());
+        }
+
+        // The kernel expects the lower qubit first
+        let apply = self.pro_que
+            .kernel_builder("swap")
+            .global_work_size(self.buffer.len() / 4)
+            .arg(&self.buffer)
+            .arg(first_qubit.min(second_qubit))
+            .arg(first_qubit.max(second_qubit))
+            .build()?;
+
+        unsafe {
+            apply.enq()?;
+        }
+
+        Ok(())
+    }
+
+    fn apply_permutation(&mut self, permutation: &Permutation) -> Result<()> {
+        // The permuted amplitudes are gathered into a second state vector
+        self.check_memory(self.buffer.len(), 2)?;
+
+        let permuted: Buffer<Complex> = Buffer::builder()
+            .queue(self.pro_que.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(self.buffer.len())
+            .build()?;
+
+        let [(source_start, source_width), (target_start, target_width)] = permutation.registers;
+        let apply = self.pro_que
+            .kernel_builder("apply_permutation")
+            .global_work_size(self.buffer.len())
+            .arg(&self.buffer)
+            .arg(&permuted)
+            .arg(permutation.operation as u32)
+            .arg(permutation.control_mask)
+            .arg(permutation.control_value)
+            .arg(source_start)
+            .arg(source_width)
+            .arg(target_start)
+            .arg(target_width)
+            .arg(permutation.result)
+            .arg(permutation.constants[0])
+            .arg(permutation.constants[1])
+            .build()?;
+
+        unsafe {
+            apply.enq()?;
+        }
+
+        self.buffer = permuted;
+
+        Ok(())
+    }
+
+    fn apply_phase_oracle(&mut self, qubits: &[i32], table: &TruthTable) -> Result<()> {
+        let (qubit_buffer, table_buffer) = self.upload_oracle(qubits, table)?;
+
+        let apply = self.pro_que
+            .kernel_builder("apply_phase_oracle")
+            .global_work_size(self.buffer.len())
+            .arg(&self.buffer)
+            .arg(qubits.len() as u32)
+            .arg(&qubit_buffer)
+            .arg(&table_buffer)
+            .build()?;
+
+        unsafe {
+            apply.enq()?;
+        }
+
+        Ok(())
+    }
+
+    fn apply_bit_oracle(&mut self, inputs: &[i32], output: i32, table: &TruthTable) -> Result<()> {
+        let (input_buffer, table_buffer) = self.upload_oracle(inputs, table)?;
+
+        let apply = self.pro_que
+            .kernel_builder("apply_bit_oracle")
+            .global_work_size(self.buffer.len() / 2)
+            .arg(&self.buffer)
+            .arg(inputs.len() as u32)
+            .arg(&input_buffer)
+            .arg(output as u32)
+            .arg(&table_buffer)
+            .build()?;
+
+        unsafe {
+            apply.enq()?;
+        }
+
+        Ok(())
+    }
+
+    fn probabilities(&self) -> Result<Vec<Real>> {
+        let result_buffer: Buffer<Real> = self.pro_que.create_buffer()?;
+
+        let apply = self.pro_que
+            .kernel_builder("calculate_probabilities")
+            .arg(&self.buffer)
+            .arg(&result_buffer)
+            .build()?;
+
+        unsafe {
+            apply.enq()?;
+        }
+
+        let mut vec_result = vec![0.0; self.buffer.len()];
+        result_buffer.read(&mut vec_result).enq()?;
+
+        Ok(vec_result)
+    }
+
+    fn cumulative_probabilities(&self) -> Result<Vec<Real>> {
+        let group_size = self.buffer.len().min(WORK_GROUP_SIZE);
+        let num_groups = self.buffer.len() / group_size;
+
+        let cumulative: Buffer<Real> = self.pro_que.create_buffer()?;
+        let block_sums: Buffer<Real> = Buffer::builder()
+            .queue(self.pro_que.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(num_groups)
+            .build()?;
+
+        let scan = self.pro_que
+            .kernel_builder("scan_probabilities")
+            .local_work_size(group_size)
+            .arg(&self.buffer)
+            .arg(&cumulative)
+            .arg_local::<Real>(group_size)
+            .arg(&block_sums)
+            .build()?;
+
+        unsafe {
+            scan.enq()?;
+        }
+
+        // There are few enough groups to scan their totals on the host
+        let mut sums = vec![0.0; num_groups];
+        block_sums.read(&mut sums).enq()?;
+
+        let mut offsets = Vec::with_capacity(num_groups);
+        let mut total = 0.0;
+        for sum in sums {
+            offsets.push(total);
+            total += sum;
+        }
+
+        let block_offsets: Buffer<Real> = Buffer::builder()
+            .queue(self.pro_que.queue().clone())
+            .flags(MemFlags::new().read_only().copy_host_ptr())
+            .len(num_groups)
+            .copy_host_slice(&offsets)
+            .build()?;
+
+        let add = self.pro_que
+            .kernel_builder("add_block_offsets")
+            .local_work_size(group_size)
+            .arg(&cumulative)
+            .arg(&block_offsets)
+            .build()?;
+
+        unsafe {
+            add.enq()?;
+        }
+
+        let mut vec_result = vec![0.0; self.buffer.len()];
+        cumulative.read(&mut vec_result).enq()?;
+
+        Ok(vec_result)
+    }
+
+    fn qubit_probability(&self, qubit: i32) -> Result<Real> {
+        let num_items = self.buffer.len() / 2;
+
+        // A register with no qubits has no amplitudes where a qubit is 1,
+        // and the kernel can't be run with no work items
+        if num_items == 0 {
+            return Ok(0.0);
+        }
+
+        let group_size = num_items.min(WORK_GROUP_SIZE);
+        let num_groups = num_items / group_size;
+
+        let partial_sums: Buffer<Real> = Buffer::builder()
+            .queue(self.pro_que.queue().clone())
+            .flags(MemFlags::new().read_write())
+            .len(num_groups)
+            .build()?;
+
+        let apply = self.pro_que
+            .kernel_builder("qubit_probability")
+            .global_work_size(num_items)
+            .local_work_size(group_size)
+            .arg(&self.buffer)
+            .arg(qubit)
+            .arg_local::<Real>(group_size)
+            .arg(&partial_sums)
+            .build()?;
+
+        unsafe {
+            apply.enq()?;
+        }
+
+        let mut sums = vec![0.0; num_groups];
+        partial_sums.read(&mut sums).enq()?;
+
+        Ok(sums.iter().sum())
+    }
+
+    fn collapse(&mut self, qubit: i32, outcome: bool, norm: Real) -> Result<()> {
+        let apply = self.pro_que
+            .kernel_builder("collapse")
+            .arg(&self.buffer)
+            .arg(qubit)
+            .arg(outcome as u32)
+            .arg(norm)
+            .build()?;
+
+        unsafe {
+            apply.enq()?;
+        }
+
+        Ok(())
+    }
+
+    fn amplitudes(&self) -> Result<Vec<Complex>> {
+        let mut vec_result = vec![Complex::new(0.0, 0.0); self.buffer.len()];
+        self.buffer.read(&mut vec_result).enq()?;
+
+        Ok(vec_result)
+    }
+
+    fn info(&self) -> String {
+        format!("Device: {}", self.device)
+    }
+
+    fn device(&self) -> Option<Device> {
+        Some(self.device.clone())
+    }
+}
@@ -0,0 +1,486 @@
+//! Quantum Circuits
+//!
+//! A `Circuit` records a sequence of operations on a register, without
+//! applying them, so it can be inspected, transformed and run any number
+//! of times. The builder methods mirror the methods of `State`.
+//!
+//! ```
+//!# extern crate qcgpu;
+//!# use qcgpu::Circuit;
+//!# use qcgpu::backends::Cpu;
+//! // Prepare a Bell state, and measure both qubits
+//! let mut circuit = Circuit::new(2);
+//! circuit.h(0).cx(0, 1).measure(0, 0).measure(1, 1);
+//!
+//! let (_, bits) = circuit.execute(Cpu::new()).unwrap();
+//! assert_eq!(bits[0], bits[1]);
+//! ```
+//!
+//! Measurements write their outcome to a classical bit, and running a circuit
+//! returns the values of the classical bits.
+
+use std::ops::Range;
+
+use arithmetic::Arithmetic;
+use backends::Backend;
+use error::Result;
+use gates::{Control, Gate, MatrixGate, TwoQubitGate};
+use gates::{h, rx, ry, rz, s, sdg, sqrt_x, t, tdg, u3, x, y, z};
+use precision::Real;
+use state::{QftOptions, State};
+
+/// An operation in a circuit. Each variant corresponds to a method of `State`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    /// A single qubit gate, as applied by `State::apply_gate`
+    Gate {
+        /// The qubit the gate is applied to
+        target: i32,
+        /// The gate
+        gate: Gate,
+    },
+    /// A single qubit gate with one control, as applied by `State::apply_controlled_gate`
+    ControlledGate {
+        /// The qubit which must be 1 for the gate to apply
+        control: i32,
+        /// The qubit the gate is applied to
+        target: i32,
+        /// The gate
+        gate: Gate,
+    },
+    /// A single qubit gate with any number of controls, as applied by
+    /// `State::apply_multi_controlled_gate`
+    MultiControlledGate {
+        /// The controls, and the value each requires
+        controls: Vec<Control>,
+        /// The qubit the gate is applied to
+        target: i32,
+        /// The gate
+        gate: Gate,
+    },
+    /// A Toffoli gate, as applied by `State::toffoli`
+    Toffoli {
+        /// The first control
+        control1: i32,
+        /// The second control
+        control2: i32,
+        /// The qubit which is flipped
+        target: i32,
+    },
+    /// A two qubit gate, as applied by `State::apply_two_qubit_gate`
+    TwoQubitGate {
+        /// The qubit which is the low bit of the gate's basis states
+        qubit0: i32,
+        /// The qubit which is the high bit of the gate's basis states
+        qubit1: i32,
+        /// The gate
+        gate: TwoQubitGate,
+    },
+    /// A gate on several qubits, as applied by `State::apply_matrix`
+    Matrix {
+        /// The qubits, the first of which is the lowest bit of the gate's basis states
+        qubits: Vec<i32>,
+        /// The gate
+        gate: MatrixGate,
+    },
+    /// A swap of two qubits, as applied by `State::swap`
+    Swap {
+        /// The first qubit
+        first: i32,
+        /// The second qubit
+        second: i32,
+    },
+    /// The quantum Fourier transform of a range of qubits, as applied by
+    /// `State::qft_with` and `State::inverse_qft_with`
+    Qft {
+        /// The qubits to transform
+        qubits: Range<i32>,
+        /// The options for the transform
+        options: QftOptions,
+        /// Whether to apply the inverse transform
+        inverse: bool,
+    },
+    /// An arithmetic operation, as applied by `State::apply_controlled_arithmetic`
+    Arithmetic {
+        /// The controls, which may be empty
+        controls: Vec<Control>,
+        /// The operation
+        operation: Arithmetic,
+    },
+    /// Modular exponentiation, as applied by `State::pow_mod`
+    PowMod {
+        /// The base
+        x: i32,
+        /// The modulus
+        n: i32,
+        /// The number of qubits of the exponent
+        input_width: i32,
+        /// The number of qubits the result is XORed into
+        output_width: i32,
+    },
+    /// A measurement of a qubit, as made by `State::measure_qubit`, whose
+    /// outcome is written to a classical bit
+    Measure {
+        /// The qubit to measure
+        qubit: i32,
+        /// The classical bit the outcome is written to
+        bit: usize,
+    },
+    /// Operations which are only applied if some classical bits hold a given
+    /// value when the condition is reached
+    Conditional {
+        /// The classical bits, the first of which is the lowest bit of the value
+        bits: Range<usize>,
+        /// The value the bits must hold
+        value: u64,
+        /// The operations to apply
+        operations: Vec<Operation>,
+    },
+}
+
+impl Operation {
+    /// Every qubit the operation acts on
+    pub fn qubits(&self) -> Vec<i32> {
+        match *self {
+            Operation::Gate { target, .. } => vec![target],
+            Operation::ControlledGate {
+                control, target, ..
+            } => vec![control, target],
+            Operation::MultiControlledGate {
+                ref controls,
+                target,
+                ..
+            } => controls
+                .iter()
+                .map(Control::qubit)
+                .chain(Some(target))
+                .collect(),
+            Operation::Toffoli {
+                control1,
+                control2,
+                target,
+            } => vec![control1, control2, target],
+            Operation::TwoQubitGate { qubit0, qubit1, .. } => vec![qubit0, qubit1],
+            Operation::Matrix { ref qubits, .. } => qubits.clone(),
+            Operation::Swap { first, second } => vec![first, second],
+            Operation::Qft { ref qubits, .. } => qubits.clone().collect(),
+            Operation::Arithmetic {
+                ref controls,
+                ref operation,
+            } => {
+                let mut qubits = operation.qubits();
+                qubits.extend(controls.iter().map(Control::qubit));
+                qubits
+            }
+            Operation::PowMod {
+                input_width,
+                output_width,
+                ..
+            } => (0..input_width.max(0) + output_width.max(0)).collect(),
+            Operation::Measure { qubit, .. } => vec![qubit],
+            Operation::Conditional { ref operations, .. } => {
+                let mut qubits: Vec<i32> = operations.iter().flat_map(Operation::qubits).collect();
+                qubits.sort();
+                qubits.dedup();
+                qubits
+            }
+        }
+    }
+
+    /// The number of classical bits needed for the bits the operation reads and writes
+    fn num_bits(&self) -> usize {
+        match *self {
+            Operation::Measure { bit, .. } => bit + 1,
+            Operation::Conditional {
+                ref bits,
+                ref operations,
+                ..
+            } => operations
+                .iter()
+                .map(Operation::num_bits)
+                .fold(bits.end, usize::max),
+            _ => 0,
+        }
+    }
+}
+
+/// A sequence of operations on a register of qubits, with classical bits
+/// to record the outcomes of measurements
+#[derive(Debug, Clone, PartialEq)]
+pub struct Circuit {
+    num_qubits: u32,
+    num_bits: usize,
+    operations: Vec<Operation>,
+}
+
+impl Circuit {
+    /// Create an empty circuit on a register of `num_qubits` qubits, with no classical bits
+    pub fn new(num_qubits: u32) -> Circuit {
+        Circuit {
+            num_qubits,
+            num_bits: 0,
+            operations: Vec::new(),
+        }
+    }
+
+    /// Give the circuit at least `num_bits` classical bits
+    ///
+    /// Measurements add the bits they write to, so this is only needed for
+    /// bits which may not be measured.
+    pub fn with_bits(mut self, num_bits: usize) -> Circuit {
+        self.num_bits = self.num_bits.max(num_bits);
+        self
+    }
+
+    /// The number of qubits in the register the circuit acts on
+    pub fn num_qubits(&self) -> u32 {
+        self.num_qubits
+    }
+
+    /// The number of classical bits
+    pub fn num_bits(&self) -> usize {
+        self.num_bits
+    }
+
+    /// The operations, in the order they are applied
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    /// Add an operation to the end of the circuit, adding any classical bits it uses
+    ///
+    /// The operation is not checked until the circuit is run.
+    pub fn push(&mut self, operation: Operation) -> &mut Circuit {
+        self.num_bits = self.num_bits.max(operation.num_bits());
+        self.operations.push(operation);
+        self
+    }
+
+    /// Run the circuit on a new register, stored on `backend`
+    ///
+    /// Returns the register, and the values of the classical bits.
+    ///
+    /// Returns an error if the register can't be created, or an operation fails.
+    /// See `State::try_run`.
+    pub fn execute<B: Backend + 'static>(&self, backend: B) -> Result<(State, Vec<bool>)> {
+        let mut state = State::try_with_backend(self.num_qubits, backend)?;
+        let bits = state.try_run(self)?;
+
+        Ok((state, bits))
+    }
+
+    /// Apply a gate to the target qubit
+    pub fn apply_gate(&mut self, target: i32, gate: Gate) -> &mut Circuit {
+        self.push(Operation::Gate { target, gate })
+    }
+
+    /// Apply a gate to every qubit in the register
+    pub fn apply_all(&mut self, gate: Gate) -> &mut Circuit {
+        for target in 0..self.num_qubits as i32 {
+            let _ = self.apply_gate(target, gate);
+        }
+        self
+    }
+
+    /// Apply a gate to the target qubit, for the states where the control qubit is 1
+    pub fn apply_controlled_gate(&mut self, control: i32, target: i32, gate: Gate) -> &mut Circuit {
+        self.push(Operation::ControlledGate {
+            control,
+            target,
+            gate,
+        })
+    }
+
+    /// Apply a gate to the target qubit, for the states where every control
+    /// qubit has its required value
+    pub fn apply_multi_controlled_gate(
+        &mut self,
+        controls: &[Control],
+        target: i32,
+        gate: Gate,
+    ) -> &mut Circuit {
+        self.push(Operation::MultiControlledGate {
+            controls: controls.to_vec(),
+            target,
+            gate,
+        })
+    }
+
+    /// Apply a two qubit gate, where `qubit0` is the low bit of the gate's basis states
+    pub fn apply_two_qubit_gate(
+        &mut self,
+        qubit0: i32,
+        qubit1: i32,
+        gate: TwoQubitGate,
+    ) -> &mut Circuit {
+        self.push(Operation::TwoQubitGate {
+            qubit0,
+            qubit1,
+            gate,
+        })
+    }
+
+    /// Apply a gate on several qubits, where the first qubit is the lowest
+    /// bit of the gate's basis states
+    pub fn apply_matrix(&mut self, qubits: &[i32], gate: &MatrixGate) -> &mut Circuit {
+        self.push(Operation::Matrix {
+            qubits: qubits.to_vec(),
+            gate: gate.clone(),
+        })
+    }
+
+    /// Apply the quantum Fourier transform to a range of qubits
+    pub fn qft(&mut self, qubits: Range<i32>) -> &mut Circuit {
+        self.qft_with(qubits, QftOptions::default())
+    }
+
+    /// Apply the inverse quantum Fourier transform to a range of qubits
+    pub fn inverse_qft(&mut self, qubits: Range<i32>) -> &mut Circuit {
+        self.inverse_qft_with(qubits, QftOptions::default())
+    }
+
+    /// Apply the quantum Fourier transform to a range of qubits, with the given options
+    pub fn qft_with(&mut self, qubits: Range<i32>, options: QftOptions) -> &mut Circuit {
+        self.push(Operation::Qft {
+            qubits,
+            options,
+            inverse: false,
+        })
+    }
+
+    /// Apply the inverse quantum Fourier transform to a range of qubits, with the
+    /// given options
+    pub fn inverse_qft_with(&mut self, qubits: Range<i32>, options: QftOptions) -> &mut Circuit {
+        self.push(Operation::Qft {
+            qubits,
+            options,
+            inverse: true,
+        })
+    }
+
+    /// Measure a qubit, writing the outcome to a classical bit
+    pub fn measure(&mut self, qubit: i32, bit: usize) -> &mut Circuit {
+        self.push(Operation::Measure { qubit, bit })
+    }
+
+    /// Measure every qubit, writing the outcome for each qubit to the classical
+    /// bit with the same index
+    pub fn measure_all(&mut self) -> &mut Circuit {
+        for qubit in 0..self.num_qubits {
+            let _ = self.measure(qubit as i32, qubit as usize);
+        }
+        self
+    }
+
+    /// Hadamard Gate
+    pub fn h(&mut self, target: i32) -> &mut Circuit {
+        self.apply_gate(target, h())
+    }
+
+    /// S Gate
+    pub fn s(&mut self, target: i32) -> &mut Circuit {
+        self.apply_gate(target, s())
+    }
+
+    /// T Gate
+    pub fn t(&mut self, target: i32) -> &mut Circuit {
+        self.apply_gate(target, t())
+    }
+
+    /// Pauli X (NOT) Gate
+    pub fn x(&mut self, target: i32) -> &mut Circuit {
+        self.apply_gate(target, x())
+    }
+
+    /// Pauli Y Gate
+    pub fn y(&mut self, target: i32) -> &mut Circuit {
+        self.apply_gate(target, y())
+    }
+
+    /// Pauli Z Gate
+    pub fn z(&mut self, target: i32) -> &mut Circuit {
+        self.apply_gate(target, z())
+    }
+
+    /// Inverse S Gate
+    pub fn sdg(&mut self, target: i32) -> &mut Circuit {
+        self.apply_gate(target, sdg())
+    }
+
+    /// Inverse T Gate
+    pub fn tdg(&mut self, target: i32) -> &mut Circuit {
+        self.apply_gate(target, tdg())
+    }
+
+    /// Square Root of NOT Gate
+    pub fn sqrt_x(&mut self, target: i32) -> &mut Circuit {
+        self.apply_gate(target, sqrt_x())
+    }
+
+    /// Rotation About the X Axis
+    pub fn rx(&mut self, target: i32, angle: Real) -> &mut Circuit {
+        self.apply_gate(target, rx(angle))
+    }
+
+    /// Rotation About the Y Axis
+    pub fn ry(&mut self, target: i32, angle: Real) -> &mut Circuit {
+        self.apply_gate(target, ry(angle))
+    }
+
+    /// Rotation About the Z Axis
+    pub fn rz(&mut self, target: i32, angle: Real) -> &mut Circuit {
+        self.apply_gate(target, rz(angle))
+    }
+
+    /// Universal Single Qubit Gate
+    pub fn u3(&mut self, target: i32, theta: Real, phi: Real, lambda: Real) -> &mut Circuit {
+        self.apply_gate(target, u3(theta, phi, lambda))
+    }
+
+    /// Controlled Not Gate
+    pub fn cx(&mut self, control: i32, target: i32) -> &mut Circuit {
+        self.apply_controlled_gate(control, target, x())
+    }
+
+    /// Toffoli (Controlled-Controlled-NOT gate)
+    pub fn toffoli(&mut self, control1: i32, control2: i32, target: i32) -> &mut Circuit {
+        self.push(Operation::Toffoli {
+            control1,
+            control2,
+            target,
+        })
+    }
+
+    /// Swap the states of two qubits
+    pub fn swap(&mut self, first: i32, second: i32) -> &mut Circuit {
+        self.push(Operation::Swap { first, second })
+    }
+
+    /// Apply an arithmetic operation to the registers held in ranges of qubits
+    pub fn apply_arithmetic(&mut self, operation: &Arithmetic) -> &mut Circuit {
+        self.apply_controlled_arithmetic(&[], operation)
+    }
+
+    /// Apply an arithmetic operation, for the states where every control qubit
+    /// has its required value
+    pub fn apply_controlled_arithmetic(
+        &mut self,
+        controls: &[Control],
+        operation: &Arithmetic,
+    ) -> &mut Circuit {
+        self.push(Operation::Arithmetic {
+            controls: controls.to_vec(),
+            operation: operation.clone(),
+        })
+    }
+
+    /// Caclulates f(a) = x^a mod n, as `State::pow_mod`
+    pub fn pow_mod(&mut self, x: i32, n: i32, input_width: i32, output_width: i32) -> &mut Circuit {
+        self.push(Operation::PowMod {
+            x,
+            n,
+            input_width,
+            output_width,
+        })
+    }
+}
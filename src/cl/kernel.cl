@@ -1,4 +1,18 @@
+/*
+ * The precision is selected by the host. When compiled with QCGPU_F64 defined,
+ * the kernels use double precision, which requires the cl_khr_fp64 extension.
+ */
+#ifdef QCGPU_F64
+#ifndef cl_khr_fp64
+#error "Double precision simulation requires the cl_khr_fp64 extension"
+#endif
+#pragma OPENCL EXTENSION cl_khr_fp64 : enable
+typedef double real_t;
+typedef double2 complex_f;
+#else
+typedef float real_t;
 typedef float2 complex_f;
+#endif
 
 /*
  * Addition of two complex numbers:
@@ -38,210 +52,447 @@ static complex_f mul(complex_f a, complex_f b)
  *
  * |a| = √(Re(a)^2 + Im(a)^2)
  */
-static float complex_abs(complex_f a)
+static real_t complex_abs(complex_f a)
 {
     return sqrt((a.x * a.x) + (a.y * a.y));
 }
 
-static complex_f cexp(float a) {
+static complex_f cexp(real_t a) {
     return (complex_f)(cos(a), sin(a));
 }
+/*
+ * States are indexed with 64 bit integers, so registers can be larger
+ * than 32 qubits on devices with enough memory.
+ */
+typedef ulong state_t;
+
+/*
+ * Inserts a zero bit into a state at the given position,
+ * shifting the higher bits up by one.
+ */
+static state_t insert_zero_bit(state_t state, uint bit)
+{
+    state_t const low_mask = (1UL << bit) - 1;
+    return ((state & ~low_mask) << 1) | (state & low_mask);
+}
+
 /*
  * Applies a single qubit gate to the register.
  * The gate matrix must be given in the form:
  *
  *  A B
  *  C D
+ *
+ * Each work item updates one pair of amplitudes in place,
+ * so the kernel is launched over half of the state vector.
  */
 __kernel void apply_gate(
-    __global complex_f *const amplitudes,
-    __global complex_f *amps,
+    __global complex_f *amplitudes,
     uint target,
     complex_f A,
     complex_f B,
     complex_f C,
     complex_f D)
 {
-    uint const state = get_global_id(0);
-    complex_f const amp = amplitudes[state];
-
-    uint const zero_state = state & (~(1 << target));
-    uint const one_state = state | (1 << target);
+    state_t const zero_state = insert_zero_bit(get_global_id(0), target);
+    state_t const one_state = zero_state | (1UL << target);
 
-    uint const bit_val = (((1 << target) & state) > 0) ? 1 : 0;
+    complex_f const zero_amp = amplitudes[zero_state];
+    complex_f const one_amp = amplitudes[one_state];
 
-    if (bit_val == 0)
-    {
-        // Bitval = 0
-
-        amps[state] = add(mul(A, amp), mul(B, amplitudes[one_state]));
-    }
-    else
-    {
-        amps[state] = add(mul(D, amp), mul(C, amplitudes[zero_state]));
-    }
+    amplitudes[zero_state] = add(mul(A, zero_amp), mul(B, one_amp));
+    amplitudes[one_state] = add(mul(C, zero_amp), mul(D, one_amp));
 }
 
 /*
- * Applies a controlled single qubit gate to the register.
+ * Applies a single qubit gate to the register, for the states where the
+ * control qubits have the required values. The qubits in control_mask are
+ * the controls, and control_value holds the value each must have, so a
+ * control can require its qubit to be either 1 or 0.
  */
-__kernel void apply_controlled_gate(
-    __global complex_f *const amplitudes,
-    __global complex_f *amps,
-    uint control,
+__kernel void apply_multi_controlled_gate(
+    __global complex_f *amplitudes,
+    ulong control_mask,
+    ulong control_value,
     uint target,
     complex_f A,
     complex_f B,
     complex_f C,
     complex_f D)
 {
-    uint const state = get_global_id(0);
-    complex_f const amp = amplitudes[state];
+    state_t const zero_state = insert_zero_bit(get_global_id(0), target);
+    state_t const one_state = zero_state | (1UL << target);
 
-    uint const zero_state = state & (~(1 << target));
-    uint const one_state = state | (1 << target);
+    if ((zero_state & control_mask) != control_value)
+    {
+        // A control doesn't have its required value, don't apply gate
+        return;
+    }
+
+    complex_f const zero_amp = amplitudes[zero_state];
+    complex_f const one_amp = amplitudes[one_state];
+
+    amplitudes[zero_state] = add(mul(A, zero_amp), mul(B, one_amp));
+    amplitudes[one_state] = add(mul(C, zero_amp), mul(D, one_amp));
+}
+
+/*
+ * Swaps the states of two qubits in the register.
+ *
+ * Only the states where the two qubits differ change, so each work item
+ * swaps one such pair, and the kernel is launched over a quarter of the
+ * state vector. The first qubit must be lower than the second.
+ */
+__kernel void swap(
+    __global complex_f *amplitudes,
+    uint first_qubit,
+    uint second_qubit)
+{
+    state_t const state = insert_zero_bit(insert_zero_bit(get_global_id(0), first_qubit), second_qubit);
+
+    state_t const first_state = state | (1UL << first_qubit);
+    state_t const second_state = state | (1UL << second_qubit);
+
+    complex_f const amp = amplitudes[first_state];
+    amplitudes[first_state] = amplitudes[second_state];
+    amplitudes[second_state] = amp;
+}
+
+/*
+ * Applies a two qubit gate to the register. The 4x4 matrix is given in row
+ * major order, with the basis states ordered |high low>.
+ *
+ * Each work item updates the four amplitudes which differ only in the two
+ * qubits, so the kernel is launched over a quarter of the state vector.
+ * The low qubit must be lower than the high qubit.
+ */
+__kernel void apply_two_qubit_gate(
+    __global complex_f *amplitudes,
+    uint low,
+    uint high,
+    __constant complex_f *matrix)
+{
+    state_t const state = insert_zero_bit(insert_zero_bit(get_global_id(0), low), high);
 
-    uint const bit_val = (((1 << target) & state) > 0) ? 1 : 0;
-    uint const control_val = (((1 << control) & state) > 0) ? 1 : 0;
+    state_t const states[4] = {
+        state,
+        state | (1UL << low),
+        state | (1UL << high),
+        state | (1UL << low) | (1UL << high)};
 
-    if (control_val == 0)
+    complex_f amps[4];
+    for (uint i = 0; i < 4; i++)
     {
-        // Control is 0, don't apply gate
-        amps[state] = amp;
+        amps[i] = amplitudes[states[i]];
     }
-    else
+
+    for (uint row = 0; row < 4; row++)
+    {
+        complex_f amp = (complex_f)(0, 0);
+        for (uint col = 0; col < 4; col++)
+        {
+            amp = add(amp, mul(matrix[row * 4 + col], amps[col]));
+        }
+        amplitudes[states[row]] = amp;
+    }
+}
+
+/*
+ * The largest number of qubits apply_matrix can act on.
+ * Must match MAX_MATRIX_QUBITS in src/gates.rs.
+ */
+#define MAX_MATRIX_QUBITS 5
+
+/*
+ * Applies a gate on several qubits to the register. The 2^k x 2^k matrix is
+ * given in row major order, with the basis states ordered by the qubits in
+ * the order given, where the first qubit is the lowest bit. sorted_qubits
+ * holds the same qubits in ascending order.
+ *
+ * Each work item updates the 2^k amplitudes which differ only in the
+ * qubits, so the kernel is launched over 2^-k of the state vector.
+ */
+__kernel void apply_matrix(
+    __global complex_f *amplitudes,
+    uint num_qubits,
+    __constant uint *qubits,
+    __constant uint *sorted_qubits,
+    __constant complex_f *matrix)
+{
+    state_t state = get_global_id(0);
+    for (uint i = 0; i < num_qubits; i++)
+    {
+        state = insert_zero_bit(state, sorted_qubits[i]);
+    }
+
+    uint const dim = 1 << num_qubits;
+    state_t states[1 << MAX_MATRIX_QUBITS];
+    complex_f amps[1 << MAX_MATRIX_QUBITS];
+
+    for (uint i = 0; i < dim; i++)
     {
-        // control is 1, apply gate.
-        if (bit_val == 0)
+        states[i] = state;
+        for (uint q = 0; q < num_qubits; q++)
         {
-            // Bitval = 0
-            amps[state] = add(mul(A, amp), mul(B, amplitudes[one_state]));
+            if ((i >> q) & 1)
+            {
+                states[i] |= 1UL << qubits[q];
+            }
         }
-        else
+        amps[i] = amplitudes[states[i]];
+    }
+
+    for (uint row = 0; row < dim; row++)
+    {
+        complex_f amp = (complex_f)(0, 0);
+        for (uint col = 0; col < dim; col++)
         {
-            amps[state] = add(mul(D, amp), mul(C, amplitudes[zero_state]));
+            amp = add(amp, mul(matrix[row * dim + col], amps[col]));
         }
+        amplitudes[states[row]] = amp;
     }
 }
 
 /*
- * Applies a controlled-controlled single qubit gate to the register.
+ * Applies one layer of the quantum Fourier transform: a Hadamard gate on the
+ * target qubit, followed by a rotation by pi/2^(target - k) of the states where
+ * both the target and qubit k are 1, for each qubit k from low up to the target.
+ *
+ * The rotations combine into a single phase, proportional to the value of the
+ * qubits between low and the target. step is the phase for a value of 1, and
+ * norm is 1/sqrt(2). The inverse layer is given a negative step, and applies
+ * the phase before the Hadamard gate.
  */
-__kernel void apply_controlled_controlled_gate(
-    __global complex_f *const amplitudes,
-    __global complex_f *amps,
-    uint control1,
-    uint control2,
+__kernel void apply_qft_layer(
+    __global complex_f *amplitudes,
     uint target,
-    complex_f A,
-    complex_f B,
-    complex_f C,
-    complex_f D)
+    uint low,
+    uint inverse,
+    real_t step,
+    real_t norm)
 {
-    uint const state = get_global_id(0);
-    complex_f const amp = amplitudes[state];
+    state_t const zero_state = insert_zero_bit(get_global_id(0), target);
+    state_t const one_state = zero_state | (1UL << target);
 
-    uint const zero_state = state & (~(1 << target));
-    uint const one_state = state | (1 << target);
+    state_t const value = (zero_state >> low) & ((1UL << (target - low)) - 1);
+    complex_f const phase = cexp(step * (real_t)value);
 
-    uint const bit_val = (((1 << target) & state) > 0) ? 1 : 0;
-    uint const control1_val = (((1 << control1) & state) > 0) ? 1 : 0;
-    uint const control2_val = (((1 << control2) & state) > 0) ? 1 : 0;
+    complex_f const zero_amp = amplitudes[zero_state];
+    complex_f one_amp = amplitudes[one_state];
 
-    if (control1_val == 0 || control2_val == 0)
+    if (inverse)
     {
-        // Control is 0, don't apply gate
-        amps[state] = amp;
+        one_amp = mul(one_amp, phase);
     }
-    else
+
+    amplitudes[zero_state] = (zero_amp + one_amp) * norm;
+    amplitudes[one_state] = (zero_amp - one_amp) * norm;
+
+    if (!inverse)
     {
-        // control is 1, apply gate.
-        if (bit_val == 0)
-        {
-            // Bitval = 0
-            amps[state] = add(mul(A, amp), mul(B, amplitudes[one_state]));
-        }
-        else
-        {
-            amps[state] = add(mul(D, amp), mul(C, amplitudes[zero_state]));
-        }
+        amplitudes[one_state] = mul(amplitudes[one_state], phase);
     }
 }
 
 /*
- * Swaps the states of two qubits in the register
+ * Arithmetic operations, which must match the Operation enum in src/arithmetic.rs
  */
-__kernel void swap(
-    __global complex_f *const amplitudes,
-    __global complex_f *amps,
-    uint first_qubit,
-    uint second_qubit)
-{
-    uint const state = get_global_id(0);
+#define OP_ADD 0
+#define OP_MULTIPLY_MOD 1
+#define OP_EXP_MOD 2
+#define OP_POW_MOD_XOR 3
+#define OP_LESS_THAN 4
 
-    uint const first_bit_mask = 1 << first_qubit;
-    uint const second_bit_mask = 1 << second_qubit;
+/*
+ * A mask of the lowest width bits
+ */
+static ulong low_mask(uint width)
+{
+    return width >= 64 ? ~0UL : (1UL << width) - 1;
+}
 
-    uint const new_second_bit = ((state & first_bit_mask) >> first_qubit) << second_qubit;
-    uint const new_first_bit = ((state & second_bit_mask) >> second_qubit) << first_qubit;
+/*
+ * Calculates a * b mod n, for a and b less than n. When n doesn't fit in
+ * 32 bits, the product is built up by doubling, so that no intermediate
+ * value overflows.
+ */
+static ulong mul_mod(ulong a, ulong b, ulong n)
+{
+    if (n <= 0xFFFFFFFFUL)
+    {
+        return a * b % n;
+    }
 
-    uint const new_state = (state & !first_bit_mask & !second_bit_mask) | new_first_bit | new_second_bit;
+    ulong r = 0;
+    while (b > 0)
+    {
+        if (b & 1)
+        {
+            r = r >= n - a ? r - (n - a) : r + a;
+        }
+        a = a >= n - a ? a - (n - a) : a + a;
+        b >>= 1;
+    }
 
-    amps[new_state] = amplitudes[state];
+    return r;
 }
 
-static uint pow_mod(uint x, uint y, uint n)
+/*
+ * Calculates x^y mod n
+ */
+static ulong pow_mod(ulong x, ulong y, ulong n)
 {
-    uint r = 1;
+    ulong r = 1 % n;
+    x %= n;
     while (y > 0)
     {
-        if ((y & 1) == 1)
+        if (y & 1)
         {
-            r = r * x % n;
+            r = mul_mod(r, x, n);
         }
-        y /= 2;
-        x = x * x % n;
+        y >>= 1;
+        x = mul_mod(x, x, n);
     }
 
     return r;
 }
 
 /*
- *  Calculates f(a) = x^a mod N
+ * Applies an arithmetic operation, which permutes the basis states.
+ *
+ * The operation reads the source register, and writes the target register or,
+ * for comparisons, the result qubit. Each work item gathers the amplitude of
+ * one state from the state the operation maps to it, so every write is to a
+ * different element of the output and the operations don't need to be
+ * their own inverse.
+ *
+ * The operations, given as the change to the target register, are:
+ *
+ *  OP_ADD:          target += c0 * source + c1, modulo 2^target_width
+ *  OP_MULTIPLY_MOD: target *= c0^-1, modulo c1, for target < c1
+ *  OP_EXP_MOD:      target *= c0^-source, modulo c1, for target < c1
+ *  OP_POW_MOD_XOR:  target ^= c0^source, modulo c1
+ *  OP_LESS_THAN:    result ^= source < target, or source < c0 if the target is empty
+ *
+ * The multiplications are given the inverse of the factor, as the preimage
+ * of each state is found by undoing the operation.
  */
-__kernel void apply_pow_mod(
-    __global complex_f *const amplitudes,
-    __global complex_f *amps,
-    uint x,
-    uint n,
-    uint input_width,
-    uint output_width)
+__kernel void apply_permutation(
+    __global complex_f const *amplitudes,
+    __global complex_f *permuted,
+    uint op,
+    ulong control_mask,
+    ulong control_value,
+    uint source_start,
+    uint source_width,
+    uint target_start,
+    uint target_width,
+    uint result,
+    ulong c0,
+    ulong c1)
 {
-    uint input_bit_range_from = output_width;
-    uint input_bit_range_to = output_width + input_width - 1;
-    uint target_bit_range_from = 0;
-    uint target_bit_range_to = output_width - 1;
+    state_t const state = get_global_id(0);
+    state_t preimage = state;
+
+    if ((state & control_mask) == control_value)
+    {
+        ulong const source = (state >> source_start) & low_mask(source_width);
+        ulong const target_mask = low_mask(target_width);
+        ulong target = (state >> target_start) & target_mask;
+
+        switch (op)
+        {
+        case OP_ADD:
+            target = (target - c0 * source - c1) & target_mask;
+            break;
+        case OP_MULTIPLY_MOD:
+            if (target < c1)
+            {
+                target = mul_mod(target, c0, c1);
+            }
+            break;
+        case OP_EXP_MOD:
+            if (target < c1)
+            {
+                target = mul_mod(target, pow_mod(c0, source, c1), c1);
+            }
+            break;
+        case OP_POW_MOD_XOR:
+            target ^= pow_mod(c0, source, c1) & target_mask;
+            break;
+        }
 
-    uint high_bit_mask = (1 << (input_bit_range_to + 1)) - 1;
-    uint target_bit_mask = ((1 << (1 + target_bit_range_to - target_bit_range_from)) - 1) << target_bit_range_from;
+        preimage = (state & ~(target_mask << target_start)) | (target << target_start);
 
-    uint const state = get_global_id(0);
+        if (op == OP_LESS_THAN)
+        {
+            ulong const right = target_width > 0 ? target : c0;
+            preimage = source < right ? state ^ (1UL << result) : state;
+        }
+    }
 
-    uint input = (state & high_bit_mask) >> input_bit_range_from;
-    uint result = (pow_mod(x, input, n) << target_bit_range_from) & target_bit_mask;
-    uint result_state = state ^ result;
+    permuted[state] = amplitudes[preimage];
+}
 
-    if (result_state == state)
+/*
+ * Looks up the value of the given qubits of a state in a truth table,
+ * packed into 32 bit words. The first qubit is the lowest bit of the value.
+ */
+static bool lookup(
+    state_t state,
+    uint num_qubits,
+    __global uint const *qubits,
+    __global uint const *table)
+{
+    ulong x = 0;
+    for (uint i = 0; i < num_qubits; i++)
     {
-        amps[state] = amplitudes[state];
+        x |= ((state >> qubits[i]) & 1UL) << i;
     }
-    else
+
+    return (table[x >> 5] >> (x & 31)) & 1;
+}
+
+/*
+ * Negates the amplitude of each state where the value of the qubits is
+ * true in the table. Launched over the whole state vector.
+ */
+__kernel void apply_phase_oracle(
+    __global complex_f *amplitudes,
+    uint num_qubits,
+    __global uint const *qubits,
+    __global uint const *table)
+{
+    state_t const state = get_global_id(0);
+
+    if (lookup(state, num_qubits, qubits, table))
     {
-        amps[state] = amplitudes[result_state];
-        amps[result_state] = amplitudes[state];
+        amplitudes[state] = neg(amplitudes[state]);
     }
+}
+
+/*
+ * Flips the output qubit of each state where the value of the input
+ * qubits is true in the table. Each work item swaps one pair of amplitudes
+ * which differ only in the output, so the kernel is launched over half of
+ * the state vector.
+ */
+__kernel void apply_bit_oracle(
+    __global complex_f *amplitudes,
+    uint num_inputs,
+    __global uint const *inputs,
+    uint output,
+    __global uint const *table)
+{
+    state_t const zero_state = insert_zero_bit(get_global_id(0), output);
+    state_t const one_state = zero_state | (1UL << output);
 
-    amps[result_state] = amplitudes[state];
+    if (lookup(zero_state, num_inputs, inputs, table))
+    {
+        complex_f const zero_amp = amplitudes[zero_state];
+        amplitudes[zero_state] = amplitudes[one_state];
+        amplitudes[one_state] = zero_amp;
+    }
 }
 
 /**
@@ -249,23 +500,130 @@ __kernel void apply_pow_mod(
  */
 __kernel void calculate_probabilities(
     __global complex_f *const amplitudes,
-    __global float *probabilities)
+    __global real_t *probabilities)
 {
-    uint const state = get_global_id(0);
+    state_t const state = get_global_id(0);
     complex_f amp = amplitudes[state];
 
     probabilities[state] = complex_abs(mul(amp, amp));
 }
 
+/*
+ * Calculates the cumulative probabilities of the states within each work
+ * group, with a scan in local memory, and writes the total probability of
+ * each group to block_sums. The host scans the block sums, and adds them
+ * to each group with add_block_offsets.
+ */
+__kernel void scan_probabilities(
+    __global complex_f *const amplitudes,
+    __global real_t *cumulative,
+    __local real_t *scratch,
+    __global real_t *block_sums)
+{
+    state_t const state = get_global_id(0);
+    uint const local_id = get_local_id(0);
+    uint const group_size = get_local_size(0);
+
+    complex_f const amp = amplitudes[state];
+    scratch[local_id] = (amp.x * amp.x) + (amp.y * amp.y);
+    barrier(CLK_LOCAL_MEM_FENCE);
+
+    for (uint offset = 1; offset < group_size; offset *= 2)
+    {
+        real_t const value = local_id >= offset ? scratch[local_id - offset] : 0;
+        barrier(CLK_LOCAL_MEM_FENCE);
+
+        scratch[local_id] += value;
+        barrier(CLK_LOCAL_MEM_FENCE);
+    }
+
+    cumulative[state] = scratch[local_id];
+
+    if (local_id == group_size - 1)
+    {
+        block_sums[get_group_id(0)] = scratch[local_id];
+    }
+}
+
+/*
+ * Adds the total probability of the preceding work groups to each
+ * cumulative probability. Must be launched with the same work group size
+ * as scan_probabilities.
+ */
+__kernel void add_block_offsets(
+    __global real_t *cumulative,
+    __global real_t *const block_offsets)
+{
+    cumulative[get_global_id(0)] += block_offsets[get_group_id(0)];
+}
+
+/*
+ * Calculates the probability of measuring the target qubit as 1.
+ *
+ * Each work item finds the probability of one state where the target is 1,
+ * and each work group reduces these in local memory to a partial sum,
+ * which the host adds up. The work group size must be a power of two.
+ */
+__kernel void qubit_probability(
+    __global complex_f *const amplitudes,
+    uint target,
+    __local real_t *scratch,
+    __global real_t *partial_sums)
+{
+    state_t const one_state = insert_zero_bit(get_global_id(0), target) | (1UL << target);
+    complex_f const amp = amplitudes[one_state];
+
+    uint const local_id = get_local_id(0);
+    scratch[local_id] = (amp.x * amp.x) + (amp.y * amp.y);
+    barrier(CLK_LOCAL_MEM_FENCE);
+
+    for (uint offset = get_local_size(0) / 2; offset > 0; offset /= 2)
+    {
+        if (local_id < offset)
+        {
+            scratch[local_id] += scratch[local_id + offset];
+        }
+        barrier(CLK_LOCAL_MEM_FENCE);
+    }
+
+    if (local_id == 0)
+    {
+        partial_sums[get_group_id(0)] = scratch[0];
+    }
+}
+
+/*
+ * Projects the register onto the states where the target qubit has the
+ * measured value. The remaining amplitudes are multiplied by norm, which
+ * should be 1 / sqrt(probability of the outcome) to renormalise the state.
+ */
+__kernel void collapse(
+    __global complex_f *amplitudes,
+    uint target,
+    uint outcome,
+    real_t norm)
+{
+    state_t const state = get_global_id(0);
+
+    if (((state >> target) & 1) == outcome)
+    {
+        amplitudes[state] *= norm;
+    }
+    else
+    {
+        amplitudes[state] = (complex_f)(0, 0);
+    }
+}
+
 /**
  * Initializes a register to the value 1|0..100...0>
  *                                          ^ target
  */
 __kernel void initalize_register(
     __global complex_f *amplitudes,
-    uint const target)
+    state_t const target)
 {
-    uint const state = get_global_id(0);
+    state_t const state = get_global_id(0);
     if (state == target)
     {
         amplitudes[state] = (complex_f)(1, 0);
@@ -0,0 +1,210 @@
+//! Device Discovery
+//!
+//! Lists the OpenCL devices available on the machine, and chooses
+//! between them, so that a register can be placed on a particular device.
+//!
+//! ```rust,no_run
+//! # extern crate qcgpu;
+//! use qcgpu::State;
+//! use qcgpu::backends::OpenCL;
+//! use qcgpu::device::Selection;
+//!
+//! # fn main() {
+//! let device = Selection::MostMemory.select().unwrap();
+//! let state = State::with_backend(5, OpenCL::with_device(&device).unwrap());
+//!
+//! println!("Running on {}", state.device().unwrap());
+//! # }
+//! ```
+
+use ocl;
+use ocl::enums::{DeviceInfo, DeviceInfoResult};
+use ocl::Platform;
+use std::fmt;
+
+use error::{Error, Result};
+
+/// The kind of an OpenCL device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceType {
+    /// A host processor
+    Cpu,
+    /// A graphics processor
+    Gpu,
+    /// A dedicated accelerator, such as an FPGA
+    Accelerator,
+    /// Any other device
+    Other,
+}
+
+/// Information about an OpenCL device
+#[derive(Debug, Clone, PartialEq)]
+pub struct Device {
+    /// The index of the platform the device belongs to
+    pub platform: usize,
+    /// The index of the device on its platform
+    pub index: usize,
+    /// The name of the platform the device belongs to
+    pub platform_name: String,
+    /// The name of the device
+    pub name: String,
+    /// The vendor of the device
+    pub vendor: String,
+    /// The kind of device
+    pub device_type: DeviceType,
+    /// The size of the global memory, in bytes
+    pub global_memory: u64,
+    /// The size of the largest buffer that can be allocated, in bytes
+    pub max_allocation: u64,
+    /// Whether the device supports double precision
+    pub fp64: bool,
+}
+
+/// Convert an error from OpenCL, which may come from either `ocl` or `ocl-core`
+pub(crate) fn ocl_error<E>(err: E) -> Error
+where
+    ocl::Error: From<E>,
+{
+    Error::OpenCL(ocl::Error::from(err))
+}
+
+/// The OpenCL platforms on the machine. Unlike `Platform::list`, this
+/// returns an error rather than panicking when OpenCL is unavailable.
+pub(crate) fn platforms() -> Result<Vec<Platform>> {
+    let ids = ocl::core::get_platform_ids().map_err(ocl_error)?;
+
+    Ok(Platform::list_from_core(ids))
+}
+
+impl Device {
+    /// Query the information about an OpenCL device
+    pub(crate) fn from_ocl(
+        platform_index: usize,
+        index: usize,
+        platform: &Platform,
+        device: &ocl::Device,
+    ) -> Result<Device> {
+        let info = |kind| device.info(kind).map_err(ocl_error);
+
+        let device_type = match info(DeviceInfo::Type)? {
+            DeviceInfoResult::Type(t) if t.contains(ocl::DeviceType::GPU) => DeviceType::Gpu,
+            DeviceInfoResult::Type(t) if t.contains(ocl::DeviceType::CPU) => DeviceType::Cpu,
+            DeviceInfoResult::Type(t) if t.contains(ocl::DeviceType::ACCELERATOR) => {
+                DeviceType::Accelerator
+            }
+            _ => DeviceType::Other,
+        };
+
+        let global_memory = match info(DeviceInfo::GlobalMemSize)? {
+            DeviceInfoResult::GlobalMemSize(size) => size,
+            _ => 0,
+        };
+
+        let max_allocation = match info(DeviceInfo::MaxMemAllocSize)? {
+            DeviceInfoResult::MaxMemAllocSize(size) => size,
+            _ => 0,
+        };
+
+        let fp64 = match info(DeviceInfo::Extensions)? {
+            DeviceInfoResult::Extensions(extensions) => extensions.contains("cl_khr_fp64"),
+            _ => false,
+        };
+
+        Ok(Device {
+            platform: platform_index,
+            index,
+            platform_name: platform.name().map_err(ocl_error)?,
+            name: device.name().map_err(ocl_error)?,
+            vendor: device.vendor().map_err(ocl_error)?,
+            device_type,
+            global_memory,
+            max_allocation,
+            fp64,
+        })
+    }
+
+    /// Find the OpenCL platform and device this describes
+    pub(crate) fn to_ocl(&self) -> Result<(Platform, ocl::Device)> {
+        let platform = *platforms()?
+            .get(self.platform)
+            .ok_or_else(|| Error::DeviceNotFound(self.name.clone()))?;
+
+        let device = *ocl::Device::list_all(platform)
+            .map_err(ocl_error)?
+            .get(self.index)
+            .ok_or_else(|| Error::DeviceNotFound(self.name.clone()))?;
+
+        Ok((platform, device))
+    }
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}, {:?}, {} MiB)",
+            self.name,
+            self.vendor,
+            self.device_type,
+            self.global_memory / (1024 * 1024)
+        )
+    }
+}
+
+/// List every OpenCL device, on every platform
+pub fn list_devices() -> Result<Vec<Device>> {
+    let mut devices = Vec::new();
+
+    for (platform_index, platform) in platforms()?.iter().enumerate() {
+        let platform_devices = ocl::Device::list_all(platform).map_err(ocl_error)?;
+
+        for (index, device) in platform_devices.iter().enumerate() {
+            devices.push(Device::from_ocl(platform_index, index, platform, device)?);
+        }
+    }
+
+    Ok(devices)
+}
+
+/// Policies for choosing a device
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    /// The first GPU found
+    FirstGpu,
+    /// The device with the most global memory
+    MostMemory,
+    /// The first device whose name contains the given string, ignoring case
+    Name(String),
+}
+
+impl Selection {
+    /// Choose a device from those available on the machine
+    pub fn select(&self) -> Result<Device> {
+        let devices = list_devices()?;
+
+        self.choose(&devices)
+            .cloned()
+            .ok_or_else(|| Error::DeviceNotFound(format!("{:?}", self)))
+    }
+
+    /// Choose a device from the given list
+    pub fn choose<'a>(&self, devices: &'a [Device]) -> Option<&'a Device> {
+        match *self {
+            Selection::FirstGpu => devices
+                .iter()
+                .find(|device| device.device_type == DeviceType::Gpu),
+            Selection::MostMemory => devices.iter().fold(None, |best: Option<&Device>, device| {
+                match best {
+                    Some(best) if best.global_memory >= device.global_memory => Some(best),
+                    _ => Some(device),
+                }
+            }),
+            Selection::Name(ref name) => {
+                let name = name.to_lowercase();
+                devices
+                    .iter()
+                    .find(|device| device.name.to_lowercase().contains(&name))
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Error Handling
+//!
+//! Every fallible operation in the library returns a `qcgpu::Result`,
+//! with the `Error` describing what went wrong.
+
+use ocl;
+use rayon;
+use std::error;
+use std::fmt;
+use std::ops::Range;
+use std::result;
+
+use gates::MAX_MATRIX_QUBITS;
+
+/// The errors that can occur while creating or operating on a register
+#[derive(Debug)]
+pub enum Error {
+    /// An error reported by OpenCL, such as an unavailable device
+    /// or a kernel that failed to build
+    OpenCL(ocl::Error),
+    /// The thread pool of a CPU backend could not be built
+    ThreadPool(rayon::ThreadPoolBuildError),
+    /// A qubit index was outside of the register
+    InvalidQubit {
+        /// The requested qubit
+        qubit: i32,
+        /// The number of qubits in the register
+        num_qubits: u32,
+    },
+    /// The same qubit was given more than once to an operation
+    /// which needs distinct qubits
+    DuplicateQubit(i32),
+    /// A bit string could not be parsed as a basis state
+    InvalidBitString(String),
+    /// There is not enough memory to store the register
+    OutOfMemory {
+        /// The number of bytes required
+        required: u64,
+        /// The number of bytes available, if known
+        available: Option<u64>,
+    },
+    /// The register has too many qubits for its amplitudes to be indexed
+    WidthOverflow(u32),
+    /// More qubits were requested than are in the register
+    NotEnoughQubits {
+        /// The number of qubits requested
+        requested: u32,
+        /// The number of qubits in the register
+        num_qubits: u32,
+    },
+    /// No OpenCL device matched the one requested
+    DeviceNotFound(String),
+    /// A matrix with the given number of elements is not the size of a gate
+    InvalidMatrix(usize),
+    /// A gate's matrix is not unitary
+    NotUnitary,
+    /// A gate was applied to the wrong number of qubits
+    WrongNumberOfQubits {
+        /// The number of qubits the gate acts on
+        expected: u32,
+        /// The number of qubits it was applied to
+        found: usize,
+    },
+    /// The modulus of an arithmetic operation is zero, or too large for its register
+    InvalidModulus {
+        /// The modulus
+        modulus: u64,
+        /// The number of qubits in the register the result is stored in
+        width: u32,
+    },
+    /// A modular multiplication isn't reversible, as the factor has no inverse
+    NotInvertible {
+        /// The factor
+        value: u64,
+        /// The modulus
+        modulus: u64,
+    },
+    /// The classical bits of a condition are reversed, outside of the
+    /// circuit's bits, or too many to be compared with a `u64`
+    InvalidCondition {
+        /// The bits of the condition
+        bits: Range<usize>,
+        /// The number of classical bits in the circuit
+        num_bits: usize,
+    },
+    /// A circuit description could not be parsed
+    Parse {
+        /// The line the problem was found on, starting from 1
+        line: usize,
+        /// The column the problem was found at, starting from 1
+        column: usize,
+        /// A description of the problem
+        message: String,
+    },
+    /// An operation can't be written in the format a circuit is being exported to
+    Unsupported {
+        /// A description of the operation
+        operation: String,
+        /// The name of the format
+        format: &'static str,
+    },
+}
+
+/// A specialized `Result` type for operations on registers
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::OpenCL(ref err) => write!(f, "OpenCL error: {}", err),
+            Error::ThreadPool(ref err) => write!(f, "could not build the thread pool: {}", err),
+            Error::InvalidQubit { qubit, num_qubits } => write!(
+                f,
+                "qubit {} is outside of the {} qubit register",
+                qubit, num_qubits
+            ),
+            Error::DuplicateQubit(qubit) => write!(f, "qubit {} was given more than once", qubit),
+            Error::InvalidBitString(ref bits) => write!(f, "invalid bit string {:?}", bits),
+            Error::OutOfMemory {
+                required,
+                available: Some(available),
+            } => write!(
+                f,
+                "the register needs {} bytes, but only {} bytes are available",
+                required, available
+            ),
+            Error::OutOfMemory {
+                required,
+                available: None,
+            } => write!(f, "could not allocate {} bytes for the register", required),
+            Error::WidthOverflow(num_qubits) => {
+                write!(f, "a {} qubit register is too wide to be indexed", num_qubits)
+            }
+            Error::NotEnoughQubits {
+                requested,
+                num_qubits,
+            } => write!(
+                f,
+                "{} qubits were requested, but the register only has {}",
+                requested, num_qubits
+            ),
+            Error::DeviceNotFound(ref device) => write!(f, "no device matched {}", device),
+            Error::InvalidMatrix(len) => write!(
+                f,
+                "a matrix with {} elements is not the size of a gate on 1 to {} qubits",
+                len, MAX_MATRIX_QUBITS
+            ),
+            Error::NotUnitary => write!(f, "the gate's matrix is not unitary"),
+            Error::WrongNumberOfQubits { expected, found } => write!(
+                f,
+                "a gate on {} qubits was applied to {} qubits",
+                expected, found
+            ),
+            Error::InvalidModulus { modulus, width } => write!(
+                f,
+                "the modulus {} is zero or too large for a {} qubit register",
+                modulus, width
+            ),
+            Error::NotInvertible { value, modulus } => {
+                write!(f, "{} has no inverse modulo {}", value, modulus)
+            }
+            Error::InvalidCondition { ref bits, num_bits } => write!(
+                f,
+                "the condition on bits {:?} is not a range of at most 64 of the {} classical bits",
+                bits, num_bits
+            ),
+            Error::Parse {
+                line,
+                column,
+                ref message,
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+            Error::Unsupported {
+                ref operation,
+                format,
+            } => write!(f, "{} can't be written in {}", operation, format),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<ocl::Error> for Error {
+    fn from(err: ocl::Error) -> Error {
+        Error::OpenCL(err)
+    }
+}
+
+impl From<rayon::ThreadPoolBuildError> for Error {
+    fn from(err: rayon::ThreadPoolBuildError) -> Error {
+        Error::ThreadPool(err)
+    }
+}
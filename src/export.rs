@@ -0,0 +1,88 @@
+//! Helpers shared by the writers of circuits in other formats
+
+use std::ops::Range;
+
+use arithmetic::Arithmetic;
+use circuit::Operation;
+use error::{Error, Result};
+use gates::Gate;
+use precision::Real;
+
+/// How close two gate entries must be to be treated as equal
+pub(crate) const TOLERANCE: Real = 1e-6;
+
+/// Check that the qubits of an operation are distinct, and inside of the register
+pub(crate) fn check_qubits(operation: &Operation, num_qubits: u32) -> Result<()> {
+    if let Operation::Conditional { ref operations, .. } = *operation {
+        return operations
+            .iter()
+            .try_for_each(|operation| check_qubits(operation, num_qubits));
+    }
+
+    let mut qubits = operation.qubits();
+    if let Some(&qubit) = qubits
+        .iter()
+        .find(|&&qubit| qubit < 0 || qubit as u32 >= num_qubits)
+    {
+        return Err(Error::InvalidQubit { qubit, num_qubits });
+    }
+
+    qubits.sort();
+    match qubits.windows(2).find(|pair| pair[0] == pair[1]) {
+        Some(pair) => Err(Error::DuplicateQubit(pair[0])),
+        None => Ok(()),
+    }
+}
+
+/// The operations of a condition on no bits, which always hold 0, so the
+/// operations are either always applied or never. Returns `None` for a
+/// condition on some bits, which has to be tested when it is reached.
+pub(crate) fn unconditional<'a>(
+    bits: &Range<usize>,
+    value: u64,
+    operations: &'a [Operation],
+) -> Option<&'a [Operation]> {
+    match (bits.is_empty(), value) {
+        (false, _) => None,
+        (true, 0) => Some(operations),
+        (true, _) => Some(&[]),
+    }
+}
+
+/// The name and parameters of an arithmetic operation, for its opaque gate
+pub(crate) fn arithmetic(operation: &Arithmetic) -> (&'static str, Vec<(&'static str, String)>) {
+    match *operation {
+        Arithmetic::AddConstant { constant, .. } => {
+            ("add_constant", vec![("constant", constant.to_string())])
+        }
+        Arithmetic::SubtractConstant { constant, .. } => {
+            ("subtract_constant", vec![("constant", constant.to_string())])
+        }
+        Arithmetic::AddRegister { .. } => ("add_register", vec![]),
+        Arithmetic::SubtractRegister { .. } => ("subtract_register", vec![]),
+        Arithmetic::MultiplyMod {
+            factor, modulus, ..
+        } => (
+            "multiply_mod",
+            vec![("factor", factor.to_string()), ("modulus", modulus.to_string())],
+        ),
+        Arithmetic::ExpMod { base, modulus, .. } => (
+            "exp_mod",
+            vec![("base", base.to_string()), ("modulus", modulus.to_string())],
+        ),
+        Arithmetic::PowModXor { base, modulus, .. } => (
+            "pow_mod_xor",
+            vec![("base", base.to_string()), ("modulus", modulus.to_string())],
+        ),
+        Arithmetic::LessThan { .. } => ("less_than", vec![]),
+        Arithmetic::LessThanConstant { constant, .. } => {
+            ("less_than_constant", vec![("constant", constant.to_string())])
+        }
+    }
+}
+
+/// Whether two gates are equal to within `TOLERANCE` in every entry
+pub(crate) fn approx_eq(left: &Gate, right: &Gate) -> bool {
+    (left.a - right.a).norm() < TOLERANCE && (left.b - right.b).norm() < TOLERANCE
+        && (left.c - right.c).norm() < TOLERANCE && (left.d - right.d).norm() < TOLERANCE
+}
@@ -3,12 +3,15 @@
 //! Gates and Gate Generation
 //!
 //! Matrices are in row major format, and
-//! all gates use the `num_complex::Complex<f32>;`
-//! datatype.
+//! all gates use the `qcgpu::Complex` datatype,
+//! which is `num_complex::Complex<f32>` or `num_complex::Complex<f64>`
+//! depending on the `f64` feature.
 
-use num_complex::Complex32;
 use std::fmt;
-use std::f32::consts::{FRAC_1_SQRT_2, E};
+use std::ops::Mul;
+use error::{Error, Result};
+use precision::{Complex, Real};
+use precision::consts::{FRAC_1_SQRT_2, FRAC_PI_4, E, PI};
 
 /// Representation of a gate
 ///
@@ -16,19 +19,200 @@ use std::f32::consts::{FRAC_1_SQRT_2, E};
 ///# extern crate qcgpu;
 ///# extern crate num_complex;
 ///# use qcgpu::Gate;
-///# use num_complex::Complex32;
+///# use qcgpu::Complex;
 /// Gate {
-///    a: Complex32::new(0.0, 0.0), b: Complex32::new(1.0, 0.0),
-///    c: Complex32::new(1.0, 0.0), d: Complex32::new(0.0, 0.0)
+///    a: Complex::new(0.0, 0.0), b: Complex::new(1.0, 0.0),
+///    c: Complex::new(1.0, 0.0), d: Complex::new(0.0, 0.0)
 /// };
 ///
 ///
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Gate {
-    pub a: Complex32,
-    pub b: Complex32,
-    pub c: Complex32,
-    pub d: Complex32,
+    pub a: Complex,
+    pub b: Complex,
+    pub c: Complex,
+    pub d: Complex,
+}
+
+impl Gate {
+    /// Create a gate from its ZYZ Euler angles, as
+    /// e^(i alpha) Rz(beta) Ry(gamma) Rz(delta).
+    ///
+    /// Every single qubit unitary can be written in this form.
+    pub fn from_euler_zyz(alpha: Real, beta: Real, gamma: Real, delta: Real) -> Gate {
+        let cos = (gamma / 2.0).cos();
+        let sin = (gamma / 2.0).sin();
+        let phase = |angle: Real| Complex::from_polar(&1.0, &(alpha + angle));
+
+        Gate {
+            a: phase(-(beta + delta) / 2.0) * cos,
+            b: -phase(-(beta - delta) / 2.0) * sin,
+            c: phase((beta - delta) / 2.0) * sin,
+            d: phase((beta + delta) / 2.0) * cos,
+        }
+    }
+
+    /// The ZYZ Euler angles `(alpha, beta, gamma, delta)` of a unitary gate,
+    /// such that the gate is e^(i alpha) Rz(beta) Ry(gamma) Rz(delta).
+    ///
+    /// ```
+    ///# extern crate qcgpu;
+    ///# use qcgpu::Gate;
+    ///# use qcgpu::gates::h;
+    /// let (alpha, beta, gamma, delta) = h().to_euler_zyz();
+    /// let gate = Gate::from_euler_zyz(alpha, beta, gamma, delta);
+    ///
+    /// assert!((gate.a - h().a).norm() < 1e-6);
+    /// assert!((gate.d - h().d).norm() < 1e-6);
+    /// ```
+    pub fn to_euler_zyz(&self) -> (Real, Real, Real, Real) {
+        // Removing the global phase leaves a special unitary [[a, -b*], [b, a*]]
+        let alpha = (self.a * self.d - self.b * self.c).arg() / 2.0;
+        let unphase = Complex::from_polar(&1.0, &-alpha);
+        let a = self.a * unphase;
+        let b = self.c * unphase;
+
+        // a = e^(-i(beta + delta)/2) cos(gamma/2), b = e^(i(beta - delta)/2) sin(gamma/2)
+        let gamma = 2.0 * b.norm().atan2(a.norm());
+        let beta = b.arg() - a.arg();
+        let delta = -b.arg() - a.arg();
+
+        (alpha, beta, gamma, delta)
+    }
+
+    /// The conjugate transpose of the gate, which undoes it if it is unitary
+    pub fn adjoint(&self) -> Gate {
+        Gate {
+            a: self.a.conj(),
+            b: self.c.conj(),
+            c: self.b.conj(),
+            d: self.d.conj(),
+        }
+    }
+
+    /// Raise a unitary gate to a real power.
+    ///
+    /// The gate is written as e^(i alpha) exp(-i theta/2 n.σ), a global phase and a
+    /// rotation by `theta` between 0 and pi about an axis `n`. The power scales both
+    /// angles, so `x().pow(0.5)` is `sqrt_x()` and `z().pow(0.5)` is `s()`.
+    ///
+    /// ```
+    ///# extern crate qcgpu;
+    ///# use qcgpu::gates::{t, z};
+    /// assert!(z().pow(0.25).approx_eq_up_to_phase(&t(), 1e-5));
+    /// ```
+    pub fn pow(&self, exponent: Real) -> Gate {
+        let mut alpha = (self.a * self.d - self.b * self.c).arg() / 2.0;
+        let mut unphase = Complex::from_polar(&1.0, &-alpha);
+
+        // Choose the square root of the determinant which keeps the rotation
+        // angle below pi, so the axis is well defined
+        if (self.a * unphase).re + (self.d * unphase).re < 0.0 {
+            alpha += PI;
+            unphase = -unphase;
+        }
+
+        // The special unitary cos(theta/2) - i sin(theta/2) n.σ, as a unit quaternion
+        let cos = ((self.a + self.d) * unphase).re / 2.0;
+        let x = -(self.b * unphase).im;
+        let y = (self.c * unphase).re;
+        let z = -(self.a * unphase).im;
+        let sin = (x * x + y * y + z * z).sqrt();
+
+        let half_angle = exponent * sin.atan2(cos);
+        let scale = if sin > 0.0 {
+            half_angle.sin() / sin
+        } else {
+            exponent
+        };
+        let (x, y, z) = (x * scale, y * scale, z * scale);
+        let phase = Complex::from_polar(&1.0, &(exponent * alpha));
+
+        Gate {
+            a: phase * Complex::new(half_angle.cos(), -z),
+            b: phase * Complex::new(-y, -x),
+            c: phase * Complex::new(y, -x),
+            d: phase * Complex::new(half_angle.cos(), z),
+        }
+    }
+
+    /// Whether the product of the gate and its adjoint is within `tolerance`
+    /// of the identity, in every entry
+    pub fn is_unitary(&self, tolerance: Real) -> bool {
+        is_unitary(&[self.a, self.b, self.c, self.d], 2, tolerance)
+    }
+
+    /// Whether two gates are equal to within `tolerance` in every entry, once
+    /// a global phase is removed. Gates which differ only by a global phase
+    /// have the same effect on a register.
+    pub fn approx_eq_up_to_phase(&self, other: &Gate, tolerance: Real) -> bool {
+        let left = [self.a, self.b, self.c, self.d];
+        let right = [other.a, other.b, other.c, other.d];
+
+        // The phase which best aligns the two gates is that of their inner product
+        let overlap = left
+            .iter()
+            .zip(&right)
+            .map(|(l, r)| r.conj() * l)
+            .fold(Complex::new(0.0, 0.0), |sum, term| sum + term);
+        let phase = if overlap.norm() > 0.0 {
+            overlap / overlap.norm()
+        } else {
+            Complex::new(1.0, 0.0)
+        };
+
+        left.iter()
+            .zip(&right)
+            .all(|(l, r)| (l - r * phase).norm() < tolerance)
+    }
+
+    /// The gate controlled by another qubit. When applied with
+    /// `apply_two_qubit_gate`, the first qubit is the control and the second
+    /// the target, so `x().controlled()` is the CNOT gate.
+    pub fn controlled(&self) -> TwoQubitGate {
+        let zero = Complex::new(0.0, 0.0);
+        let one = Complex::new(1.0, 0.0);
+
+        TwoQubitGate {
+            matrix: [
+                [one, zero, zero, zero],
+                [zero, self.a, zero, self.b],
+                [zero, zero, one, zero],
+                [zero, self.c, zero, self.d],
+            ],
+        }
+    }
+
+    /// The Kronecker product `self ⊗ other`. When applied with
+    /// `apply_two_qubit_gate`, `other` acts on the first qubit and `self` on the second.
+    pub fn kron(&self, other: &Gate) -> TwoQubitGate {
+        let left = [[self.a, self.b], [self.c, self.d]];
+        let right = [[other.a, other.b], [other.c, other.d]];
+
+        let mut matrix = [[Complex::new(0.0, 0.0); 4]; 4];
+        for (row, values) in matrix.iter_mut().enumerate() {
+            for (col, value) in values.iter_mut().enumerate() {
+                *value = left[row >> 1][col >> 1] * right[row & 1][col & 1];
+            }
+        }
+
+        TwoQubitGate { matrix }
+    }
+}
+
+/// The matrix product of two gates. Applying `a * b` has the same effect as
+/// applying `b` and then `a`.
+impl Mul for Gate {
+    type Output = Gate;
+
+    fn mul(self, rhs: Gate) -> Gate {
+        Gate {
+            a: self.a * rhs.a + self.b * rhs.c,
+            b: self.a * rhs.b + self.b * rhs.d,
+            c: self.c * rhs.a + self.d * rhs.c,
+            d: self.c * rhs.b + self.d * rhs.d,
+        }
+    }
 }
 
 impl fmt::Display for Gate {
@@ -37,6 +221,50 @@ impl fmt::Display for Gate {
     }
 }
 
+/// A control qubit of a controlled gate
+///
+/// ```
+///# extern crate qcgpu;
+///# use qcgpu::State;
+///# use qcgpu::backends::Cpu;
+///# use qcgpu::gates::{x, Control};
+/// let mut state = State::from_bit_string_with_backend("|010>", Cpu::new());
+///
+/// // Flip qubit 2 if qubit 1 is 1 and qubit 0 is 0
+/// state.apply_multi_controlled_gate(&[Control::Positive(1), Control::Negative(0)], 2, x());
+/// assert_eq!(state.measure(), 0b110);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    /// The gate is applied when the qubit is 1
+    Positive(i32),
+    /// The gate is applied when the qubit is 0
+    Negative(i32),
+}
+
+impl Control {
+    /// The index of the control qubit
+    pub fn qubit(&self) -> i32 {
+        match *self {
+            Control::Positive(qubit) | Control::Negative(qubit) => qubit,
+        }
+    }
+
+    /// The value the qubit must have for the gate to be applied
+    pub fn value(&self) -> bool {
+        match *self {
+            Control::Positive(_) => true,
+            Control::Negative(_) => false,
+        }
+    }
+}
+
+impl From<i32> for Control {
+    fn from(qubit: i32) -> Control {
+        Control::Positive(qubit)
+    }
+}
+
 /// Identity gate
 ///
 /// [1, 0]
@@ -45,10 +273,10 @@ impl fmt::Display for Gate {
 #[inline]
 pub fn id() -> Gate {
     Gate {
-        a: Complex32::new(1.0, 0.0),
-        b: Complex32::new(0.0, 0.0),
-        c: Complex32::new(0.0, 0.0),
-        d: Complex32::new(1.0, 0.0),
+        a: Complex::new(1.0, 0.0),
+        b: Complex::new(0.0, 0.0),
+        c: Complex::new(0.0, 0.0),
+        d: Complex::new(1.0, 0.0),
     }
 }
 
@@ -60,10 +288,10 @@ pub fn id() -> Gate {
 #[inline]
 pub fn h() -> Gate {
     Gate {
-        a: Complex32::new(FRAC_1_SQRT_2, 0.0),
-        b: Complex32::new(FRAC_1_SQRT_2, 0.0),
-        c: Complex32::new(FRAC_1_SQRT_2, 0.0),
-        d: Complex32::new(-FRAC_1_SQRT_2, 0.0),
+        a: Complex::new(FRAC_1_SQRT_2, 0.0),
+        b: Complex::new(FRAC_1_SQRT_2, 0.0),
+        c: Complex::new(FRAC_1_SQRT_2, 0.0),
+        d: Complex::new(-FRAC_1_SQRT_2, 0.0),
     }
 }
 
@@ -75,10 +303,10 @@ pub fn h() -> Gate {
 #[inline]
 pub fn negh() -> Gate {
     Gate {
-        a: Complex32::new(-FRAC_1_SQRT_2, 0.0),
-        b: Complex32::new(-FRAC_1_SQRT_2, 0.0),
-        c: Complex32::new(-FRAC_1_SQRT_2, 0.0),
-        d: Complex32::new(FRAC_1_SQRT_2, 0.0),
+        a: Complex::new(-FRAC_1_SQRT_2, 0.0),
+        b: Complex::new(-FRAC_1_SQRT_2, 0.0),
+        c: Complex::new(-FRAC_1_SQRT_2, 0.0),
+        d: Complex::new(FRAC_1_SQRT_2, 0.0),
     }
 }
 
@@ -90,10 +318,10 @@ pub fn negh() -> Gate {
 #[inline]
 pub fn x() -> Gate {
     Gate {
-        a: Complex32::new(0.0, 0.0),
-        b: Complex32::new(1.0, 0.0),
-        c: Complex32::new(1.0, 0.0),
-        d: Complex32::new(0.0, 0.0),
+        a: Complex::new(0.0, 0.0),
+        b: Complex::new(1.0, 0.0),
+        c: Complex::new(1.0, 0.0),
+        d: Complex::new(0.0, 0.0),
     }
 }
 
@@ -105,10 +333,10 @@ pub fn x() -> Gate {
 #[inline]
 pub fn y() -> Gate {
     Gate {
-        a: Complex32::new(0.0, 0.0),
-        b: Complex32::new(0.0, -1.0),
-        c: Complex32::new(0.0, 1.0),
-        d: Complex32::new(0.0, 0.0),
+        a: Complex::new(0.0, 0.0),
+        b: Complex::new(0.0, -1.0),
+        c: Complex::new(0.0, 1.0),
+        d: Complex::new(0.0, 0.0),
     }
 }
 
@@ -120,10 +348,10 @@ pub fn y() -> Gate {
 #[inline]
 pub fn z() -> Gate {
     Gate {
-        a: Complex32::new(1.0, 0.0),
-        b: Complex32::new(0.0, 0.0),
-        c: Complex32::new(0.0, 0.0),
-        d: Complex32::new(-1.0, 0.0),
+        a: Complex::new(1.0, 0.0),
+        b: Complex::new(0.0, 0.0),
+        c: Complex::new(0.0, 0.0),
+        d: Complex::new(-1.0, 0.0),
     }
 }
 
@@ -135,10 +363,10 @@ pub fn z() -> Gate {
 #[inline]
 pub fn s() -> Gate {
     Gate {
-        a: Complex32::new(1.0, 0.0),
-        b: Complex32::new(0.0, 0.0),
-        c: Complex32::new(0.0, 0.0),
-        d: Complex32::new(0.0, 1.0),
+        a: Complex::new(1.0, 0.0),
+        b: Complex::new(0.0, 0.0),
+        c: Complex::new(0.0, 0.0),
+        d: Complex::new(0.0, 1.0),
     }
 }
 
@@ -150,10 +378,10 @@ pub fn s() -> Gate {
 #[inline]
 pub fn t() -> Gate {
     Gate {
-        a: Complex32::new(1.0, 0.0),
-        b: Complex32::new(0.0, 0.0),
-        c: Complex32::new(0.0, 0.0),
-        d: Complex32::new(
+        a: Complex::new(1.0, 0.0),
+        b: Complex::new(0.0, 0.0),
+        c: Complex::new(0.0, 0.0),
+        d: Complex::new(
             0.707_106_781_186_547_524_400_844_362_104_849_039_3,
             0.707_106_781_186_547_524_400_844_362_104_849_039_3,
         ),
@@ -167,11 +395,524 @@ pub fn t() -> Gate {
 /// [1 ,0]
 ///
 /// [0, e^i*angle]
-pub fn r(angle: f32) -> Gate {
+pub fn r(angle: Real) -> Gate {
+    Gate {
+        a: Complex::new(1.0, 0.0),
+        b: Complex::new(0.0, 0.0),
+        c: Complex::new(0.0, 0.0),
+        d: Complex::new(E, 0.0).powc(Complex::new(0.0, angle)),
+    }
+}
+
+/// S† / Inverse Phase Gate
+///
+/// [1, 0]
+///
+/// [0, -i]
+#[inline]
+pub fn sdg() -> Gate {
+    Gate {
+        a: Complex::new(1.0, 0.0),
+        b: Complex::new(0.0, 0.0),
+        c: Complex::new(0.0, 0.0),
+        d: Complex::new(0.0, -1.0),
+    }
+}
+
+/// T† Gate
+///
+/// [1, 0]
+///
+/// [0, e^(-i pi/4)]
+#[inline]
+pub fn tdg() -> Gate {
+    Gate {
+        a: Complex::new(1.0, 0.0),
+        b: Complex::new(0.0, 0.0),
+        c: Complex::new(0.0, 0.0),
+        d: Complex::from_polar(&1.0, &-FRAC_PI_4),
+    }
+}
+
+/// Square root of NOT Gate
+///
+/// [(1 + i)/2, (1 - i)/2]
+///
+/// [(1 - i)/2, (1 + i)/2]
+#[inline]
+pub fn sqrt_x() -> Gate {
+    Gate {
+        a: Complex::new(0.5, 0.5),
+        b: Complex::new(0.5, -0.5),
+        c: Complex::new(0.5, -0.5),
+        d: Complex::new(0.5, 0.5),
+    }
+}
+
+/// Rotation about the X axis by `angle`
+///
+/// [cos(angle/2), -i sin(angle/2)]
+///
+/// [-i sin(angle/2), cos(angle/2)]
+pub fn rx(angle: Real) -> Gate {
+    let cos = Complex::new((angle / 2.0).cos(), 0.0);
+    let sin = Complex::new(0.0, -(angle / 2.0).sin());
+
+    Gate {
+        a: cos,
+        b: sin,
+        c: sin,
+        d: cos,
+    }
+}
+
+/// Rotation about the Y axis by `angle`
+///
+/// [cos(angle/2), -sin(angle/2)]
+///
+/// [sin(angle/2), cos(angle/2)]
+pub fn ry(angle: Real) -> Gate {
+    let cos = (angle / 2.0).cos();
+    let sin = (angle / 2.0).sin();
+
+    Gate {
+        a: Complex::new(cos, 0.0),
+        b: Complex::new(-sin, 0.0),
+        c: Complex::new(sin, 0.0),
+        d: Complex::new(cos, 0.0),
+    }
+}
+
+/// Rotation about the Z axis by `angle`.
+/// This is the phase shift gate `r(angle)`, up to a global phase.
+///
+/// [e^(-i angle/2), 0]
+///
+/// [0, e^(i angle/2)]
+pub fn rz(angle: Real) -> Gate {
     Gate {
-        a: Complex32::new(1.0, 0.0),
-        b: Complex32::new(0.0, 0.0),
-        c: Complex32::new(0.0, 0.0),
-        d: Complex32::new(E, 0.0).powc(Complex32::new(0.0, angle)),
+        a: Complex::from_polar(&1.0, &(-angle / 2.0)),
+        b: Complex::new(0.0, 0.0),
+        c: Complex::new(0.0, 0.0),
+        d: Complex::from_polar(&1.0, &(angle / 2.0)),
+    }
+}
+
+/// The universal single qubit gate U3, with the same convention as OpenQASM.
+/// Every single qubit gate is U3 for some angles, up to a global phase.
+///
+/// [cos(theta/2), -e^(i lambda) sin(theta/2)]
+///
+/// [e^(i phi) sin(theta/2), e^(i (phi + lambda)) cos(theta/2)]
+pub fn u3(theta: Real, phi: Real, lambda: Real) -> Gate {
+    let cos = (theta / 2.0).cos();
+    let sin = (theta / 2.0).sin();
+
+    Gate {
+        a: Complex::new(cos, 0.0),
+        b: -Complex::from_polar(&sin, &lambda),
+        c: Complex::from_polar(&sin, &phi),
+        d: Complex::from_polar(&cos, &(phi + lambda)),
+    }
+}
+
+/// Global phase gate, which multiplies every amplitude by e^(i angle).
+/// This has no observable effect on its own, but does when controlled.
+///
+/// [e^(i angle), 0]
+///
+/// [0, e^(i angle)]
+pub fn global_phase(angle: Real) -> Gate {
+    let phase = Complex::from_polar(&1.0, &angle);
+
+    Gate {
+        a: phase,
+        b: Complex::new(0.0, 0.0),
+        c: Complex::new(0.0, 0.0),
+        d: phase,
+    }
+}
+
+/// Representation of a two qubit gate, as a 4x4 matrix in row major format.
+///
+/// The rows and columns are ordered by the basis states `|q1 q0>`, where `q0`
+/// is the first qubit the gate is applied to and `q1` the second, so the
+/// matrix is applied to the amplitudes of `|00>`, `|01>`, `|10>` and `|11>`
+/// in that order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TwoQubitGate {
+    pub matrix: [[Complex; 4]; 4],
+}
+
+impl TwoQubitGate {
+    /// The same gate, with the roles of its two qubits exchanged
+    pub fn reversed(&self) -> TwoQubitGate {
+        // Exchanging the qubits swaps the states |01> and |10>
+        let permute = |i: usize| match i {
+            1 => 2,
+            2 => 1,
+            i => i,
+        };
+
+        let mut matrix = self.matrix;
+        for (row, values) in matrix.iter_mut().enumerate() {
+            for (col, value) in values.iter_mut().enumerate() {
+                *value = self.matrix[permute(row)][permute(col)];
+            }
+        }
+
+        TwoQubitGate { matrix }
+    }
+}
+
+impl fmt::Display for TwoQubitGate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, row) in self.matrix.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "[{}, {}, {}, {}]", row[0], row[1], row[2], row[3])?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// A diagonal two qubit gate, with the given entries on the diagonal
+fn diagonal(entries: [Complex; 4]) -> TwoQubitGate {
+    let mut matrix = [[Complex::new(0.0, 0.0); 4]; 4];
+    for (i, entry) in entries.iter().enumerate() {
+        matrix[i][i] = *entry;
+    }
+
+    TwoQubitGate { matrix }
+}
+
+/// A two qubit gate which acts on the states `|00>` and `|11>` with the outer
+/// 2x2 matrix, and on the states `|01>` and `|10>` with the inner 2x2 matrix
+fn two_level(outer: Gate, inner: Gate) -> TwoQubitGate {
+    let zero = Complex::new(0.0, 0.0);
+
+    TwoQubitGate {
+        matrix: [
+            [outer.a, zero, zero, outer.b],
+            [zero, inner.a, inner.b, zero],
+            [zero, inner.c, inner.d, zero],
+            [outer.c, zero, zero, outer.d],
+        ],
+    }
+}
+
+/// Controlled Z Gate
+///
+/// [1, 0, 0, 0]
+///
+/// [0, 1, 0, 0]
+///
+/// [0, 0, 1, 0]
+///
+/// [0, 0, 0, -1]
+#[inline]
+pub fn cz() -> TwoQubitGate {
+    diagonal([
+        Complex::new(1.0, 0.0),
+        Complex::new(1.0, 0.0),
+        Complex::new(1.0, 0.0),
+        Complex::new(-1.0, 0.0),
+    ])
+}
+
+/// iSWAP Gate
+///
+/// [1, 0, 0, 0]
+///
+/// [0, 0, i, 0]
+///
+/// [0, i, 0, 0]
+///
+/// [0, 0, 0, 1]
+#[inline]
+pub fn iswap() -> TwoQubitGate {
+    two_level(
+        id(),
+        Gate {
+            a: Complex::new(0.0, 0.0),
+            b: Complex::new(0.0, 1.0),
+            c: Complex::new(0.0, 1.0),
+            d: Complex::new(0.0, 0.0),
+        },
+    )
+}
+
+/// Square root of iSWAP Gate
+///
+/// [1, 0, 0, 0]
+///
+/// [0, 1/sqrt(2), i/sqrt(2), 0]
+///
+/// [0, i/sqrt(2), 1/sqrt(2), 0]
+///
+/// [0, 0, 0, 1]
+#[inline]
+pub fn sqrt_iswap() -> TwoQubitGate {
+    two_level(
+        id(),
+        Gate {
+            a: Complex::new(FRAC_1_SQRT_2, 0.0),
+            b: Complex::new(0.0, FRAC_1_SQRT_2),
+            c: Complex::new(0.0, FRAC_1_SQRT_2),
+            d: Complex::new(FRAC_1_SQRT_2, 0.0),
+        },
+    )
+}
+
+/// The XX interaction / Ising XX Gate, exp(-i * angle/2 * X⊗X)
+///
+/// [cos(angle/2), 0, 0, -i sin(angle/2)]
+///
+/// [0, cos(angle/2), -i sin(angle/2), 0]
+///
+/// [0, -i sin(angle/2), cos(angle/2), 0]
+///
+/// [-i sin(angle/2), 0, 0, cos(angle/2)]
+pub fn xx(angle: Real) -> TwoQubitGate {
+    let cos = Complex::new((angle / 2.0).cos(), 0.0);
+    let sin = Complex::new(0.0, -(angle / 2.0).sin());
+    let rotation = Gate {
+        a: cos,
+        b: sin,
+        c: sin,
+        d: cos,
+    };
+
+    two_level(rotation, rotation)
+}
+
+/// The YY interaction / Ising YY Gate, exp(-i * angle/2 * Y⊗Y)
+///
+/// [cos(angle/2), 0, 0, i sin(angle/2)]
+///
+/// [0, cos(angle/2), -i sin(angle/2), 0]
+///
+/// [0, -i sin(angle/2), cos(angle/2), 0]
+///
+/// [i sin(angle/2), 0, 0, cos(angle/2)]
+pub fn yy(angle: Real) -> TwoQubitGate {
+    let cos = Complex::new((angle / 2.0).cos(), 0.0);
+    let sin = Complex::new(0.0, -(angle / 2.0).sin());
+
+    two_level(
+        Gate {
+            a: cos,
+            b: -sin,
+            c: -sin,
+            d: cos,
+        },
+        Gate {
+            a: cos,
+            b: sin,
+            c: sin,
+            d: cos,
+        },
+    )
+}
+
+/// The ZZ interaction / Ising ZZ Gate, exp(-i * angle/2 * Z⊗Z)
+///
+/// [e^(-i angle/2), 0, 0, 0]
+///
+/// [0, e^(i angle/2), 0, 0]
+///
+/// [0, 0, e^(i angle/2), 0]
+///
+/// [0, 0, 0, e^(-i angle/2)]
+pub fn zz(angle: Real) -> TwoQubitGate {
+    let same = Complex::from_polar(&1.0, &(-angle / 2.0));
+    let different = Complex::from_polar(&1.0, &(angle / 2.0));
+
+    diagonal([same, different, different, same])
+}
+
+/// The fermionic simulation / fSim Gate, as used by Google's Sycamore processor.
+/// `theta` is the swap angle, and `phi` the controlled phase angle.
+///
+/// [1, 0, 0, 0]
+///
+/// [0, cos(theta), -i sin(theta), 0]
+///
+/// [0, -i sin(theta), cos(theta), 0]
+///
+/// [0, 0, 0, e^(-i phi)]
+pub fn fsim(theta: Real, phi: Real) -> TwoQubitGate {
+    let cos = Complex::new(theta.cos(), 0.0);
+    let sin = Complex::new(0.0, -theta.sin());
+
+    two_level(
+        Gate {
+            a: Complex::new(1.0, 0.0),
+            b: Complex::new(0.0, 0.0),
+            c: Complex::new(0.0, 0.0),
+            d: Complex::from_polar(&1.0, &-phi),
+        },
+        Gate {
+            a: cos,
+            b: sin,
+            c: sin,
+            d: cos,
+        },
+    )
+}
+
+/// The largest number of qubits a `MatrixGate` can act on
+pub const MAX_MATRIX_QUBITS: u32 = 5;
+
+/// How far the product of a matrix and its conjugate transpose may be from
+/// the identity for the matrix to be considered unitary
+const UNITARY_TOLERANCE: Real = 1e-4;
+
+/// Representation of a gate on up to `MAX_MATRIX_QUBITS` qubits, as a
+/// 2^k x 2^k matrix in row major format.
+///
+/// The rows and columns are ordered by the basis states of the qubits the
+/// gate is applied to, where the first qubit given is the lowest bit.
+///
+/// ```
+///# extern crate qcgpu;
+///# use qcgpu::Complex;
+///# use qcgpu::gates::MatrixGate;
+/// let zero = Complex::new(0.0, 0.0);
+/// let one = Complex::new(1.0, 0.0);
+///
+/// // A 3 qubit gate which cycles the basis states
+/// let mut matrix = vec![zero; 64];
+/// for i in 0..8 {
+///     matrix[((i + 1) % 8) * 8 + i] = one;
+/// }
+///
+/// let gate = MatrixGate::new(matrix).unwrap();
+/// assert_eq!(gate.num_qubits(), 3);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatrixGate {
+    num_qubits: u32,
+    matrix: Vec<Complex>,
+}
+
+impl MatrixGate {
+    /// Create a gate from a matrix in row major format.
+    ///
+    /// Returns an error if the matrix is not 2^k x 2^k for some k between 1 and
+    /// `MAX_MATRIX_QUBITS`, or is not unitary.
+    pub fn new(matrix: Vec<Complex>) -> Result<MatrixGate> {
+        let num_qubits = (1..MAX_MATRIX_QUBITS + 1)
+            .find(|k| 1 << (2 * k) == matrix.len())
+            .ok_or(Error::InvalidMatrix(matrix.len()))?;
+
+        let gate = MatrixGate { num_qubits, matrix };
+        if !gate.is_unitary() {
+            return Err(Error::NotUnitary);
+        }
+
+        Ok(gate)
+    }
+
+    /// The number of qubits the gate acts on
+    pub fn num_qubits(&self) -> u32 {
+        self.num_qubits
+    }
+
+    /// The number of rows, and columns, of the matrix
+    pub fn dimension(&self) -> usize {
+        1 << self.num_qubits
+    }
+
+    /// The matrix, in row major format
+    pub fn matrix(&self) -> &[Complex] {
+        &self.matrix
+    }
+
+    /// Whether the product of the matrix and its conjugate transpose is the identity,
+    /// to within the rounding errors of the precision being simulated at
+    pub fn is_unitary(&self) -> bool {
+        is_unitary(&self.matrix, self.dimension(), UNITARY_TOLERANCE)
+    }
+
+    /// The Kronecker product `self ⊗ other`. When applied with `apply_matrix`,
+    /// `other` acts on the first `other.num_qubits()` qubits and `self` on the rest.
+    ///
+    /// Returns an error if the product acts on more than `MAX_MATRIX_QUBITS` qubits.
+    ///
+    /// ```
+    ///# extern crate qcgpu;
+    ///# use qcgpu::gates::{h, x, MatrixGate};
+    /// let gate = MatrixGate::from(h()).kron(&MatrixGate::from(x())).unwrap();
+    /// assert_eq!(gate.num_qubits(), 2);
+    /// ```
+    pub fn kron(&self, other: &MatrixGate) -> Result<MatrixGate> {
+        let num_qubits = self.num_qubits + other.num_qubits;
+        if num_qubits > MAX_MATRIX_QUBITS {
+            return Err(Error::InvalidMatrix(1 << (2 * num_qubits)));
+        }
+
+        let (outer, inner) = (self.dimension(), other.dimension());
+        let dim = outer * inner;
+        let matrix = (0..dim * dim)
+            .map(|i| {
+                let (row, col) = (i / dim, i % dim);
+                self.matrix[(row / inner) * outer + col / inner]
+                    * other.matrix[(row % inner) * inner + col % inner]
+            })
+            .collect();
+
+        Ok(MatrixGate { num_qubits, matrix })
+    }
+
+    /// The conjugate transpose of the matrix, which is the inverse of the gate.
+    ///
+    /// The result isn't checked to be unitary again, so rounding can't turn a
+    /// gate which only just passed the check into an error.
+    pub(crate) fn adjoint(&self) -> MatrixGate {
+        let dim = self.dimension();
+        let matrix = (0..dim * dim)
+            .map(|i| self.matrix[(i % dim) * dim + i / dim].conj())
+            .collect();
+
+        MatrixGate {
+            num_qubits: self.num_qubits,
+            matrix,
+        }
+    }
+}
+
+/// Whether the product of a dim x dim matrix and its conjugate transpose is
+/// within `tolerance` of the identity
+fn is_unitary(matrix: &[Complex], dim: usize, tolerance: Real) -> bool {
+    (0..dim).all(|row| {
+        (0..dim).all(|col| {
+            let product = (0..dim)
+                .map(|i| matrix[row * dim + i] * matrix[col * dim + i].conj())
+                .fold(Complex::new(0.0, 0.0), |sum, term| sum + term);
+            let identity = if row == col { 1.0 } else { 0.0 };
+
+            (product - identity).norm() < tolerance
+        })
+    })
+}
+
+impl From<Gate> for MatrixGate {
+    fn from(gate: Gate) -> MatrixGate {
+        MatrixGate {
+            num_qubits: 1,
+            matrix: vec![gate.a, gate.b, gate.c, gate.d],
+        }
+    }
+}
+
+impl From<TwoQubitGate> for MatrixGate {
+    fn from(gate: TwoQubitGate) -> MatrixGate {
+        MatrixGate {
+            num_qubits: 2,
+            matrix: gate.matrix.iter().flat_map(|row| row.iter().cloned()).collect(),
+        }
     }
 }
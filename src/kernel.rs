@@ -1 +1,5 @@
+#[cfg(not(feature = "f64"))]
 pub static KERNEL: &'static str = include_str!("cl/kernel.cl");
+
+#[cfg(feature = "f64")]
+pub static KERNEL: &'static str = concat!("#define QCGPU_F64\n", include_str!("cl/kernel.cl"));
@@ -15,19 +15,39 @@
 //! * Optional simulation of decoherence
 //! * Optimized for maximally entangled states
 //! * Accelerated with GPUs, FPGAs and other OpenCL devices
+//! * A native CPU backend for machines without an OpenCL driver
 //! * Example implementations of Grover, Deutsch-Jozsa, Bernstein-Vazirani and Shors algorithm
 //! * Implements Hadamard, Pauli and phase gates, with support for arbitrary gates
 //! * Support for arbitrary controlled gates
+//! * Optional double precision simulation
 
 extern crate num_complex;
 extern crate ocl;
 extern crate rand;
+extern crate rayon;
 
+mod error;
+mod export;
 mod kernel;
+mod precision;
 mod state;
 mod utilities;
+pub mod algorithms;
+pub mod arithmetic;
+pub mod backends;
+pub mod circuit;
+pub mod device;
 pub mod gates;
+pub mod oracle;
+pub mod qasm;
+pub mod qasm3;
+pub mod quil;
 
-pub use state::State;
-pub use gates::Gate;
+pub use precision::{Complex, Real};
+pub use state::{QftOptions, State};
+pub use backends::Backend;
+pub use circuit::Circuit;
+pub use device::{list_devices, Device};
+pub use error::{Error, Result};
+pub use gates::{Control, Gate, MatrixGate, TwoQubitGate};
 pub use utilities::{gcd, get_width};
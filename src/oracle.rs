@@ -0,0 +1,99 @@
+//! Classical Oracles
+//!
+//! Oracles which evaluate a classical predicate `f` on the value of some
+//! qubits, for every basis state at once. A phase oracle negates the
+//! amplitude of each state where `f` is true:
+//!
+//! \\[\lvert x \rangle \mapsto (-1)^{f(x)} \lvert x \rangle\\]
+//!
+//! and a bit oracle flips an output qubit instead:
+//!
+//! \\[\lvert x \rangle \lvert y \rangle \mapsto \lvert x \rangle \lvert y \oplus f(x) \rangle\\]
+//!
+//! ```
+//!# extern crate qcgpu;
+//!# use qcgpu::State;
+//!# use qcgpu::backends::Cpu;
+//! let mut state = State::from_bit_string_with_backend("|0110>", Cpu::new());
+//!
+//! // Flip qubit 3 if qubits 0..3 hold an even number
+//! state.apply_bit_oracle(0..3, 3, |x| x & 1 == 0);
+//! assert_eq!(state.measure(), 0b1110);
+//! ```
+//!
+//! The predicate is evaluated on the host for every value of the input
+//! qubits, and the resulting `TruthTable` is applied by the backend.
+
+use error::{Error, Result};
+
+/// The values of a predicate on every integer below 2^num_inputs
+///
+/// The values are packed into 32 bit words, with the value for `x` in
+/// bit `x % 32` of word `x / 32`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TruthTable {
+    num_inputs: u32,
+    words: Vec<u32>,
+}
+
+impl TruthTable {
+    /// Evaluate `f` on every integer with `num_inputs` bits
+    ///
+    /// ```
+    /// # extern crate qcgpu;
+    /// use qcgpu::oracle::TruthTable;
+    ///
+    /// let table = TruthTable::new(3, |x| x > 5);
+    /// assert!(table.get(6) && !table.get(5));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_inputs` is 64 or more, as the inputs can't be counted
+    /// in a `u64`.
+    pub fn new<F>(num_inputs: u32, f: F) -> TruthTable
+    where
+        F: Fn(u64) -> bool,
+    {
+        assert!(num_inputs < 64, "a truth table can't have {} inputs", num_inputs);
+
+        let size = 1_u64 << num_inputs;
+        let mut words = vec![0; ((size + 31) / 32) as usize];
+
+        for x in 0..size {
+            if f(x) {
+                words[(x / 32) as usize] |= 1 << (x % 32);
+            }
+        }
+
+        TruthTable { num_inputs, words }
+    }
+
+    /// The number of bits of the inputs
+    pub fn num_inputs(&self) -> u32 {
+        self.num_inputs
+    }
+
+    /// The value of the predicate for `x`, which must be below 2^num_inputs
+    pub fn get(&self, x: u64) -> bool {
+        (self.words[(x / 32) as usize] >> (x % 32)) & 1 == 1
+    }
+
+    /// Check that the table is looked up with one qubit for each bit of
+    /// its inputs, so every value of the qubits is in the table
+    pub(crate) fn check_inputs(&self, qubits: &[i32]) -> Result<()> {
+        if qubits.len() != self.num_inputs as usize {
+            return Err(Error::WrongNumberOfQubits {
+                expected: self.num_inputs,
+                found: qubits.len(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The packed values, as given to the kernels
+    pub(crate) fn words(&self) -> &[u32] {
+        &self.words
+    }
+}
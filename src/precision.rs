@@ -0,0 +1,24 @@
+//! Floating Point Precision
+//!
+//! Amplitudes and gate matrices are stored as single precision floats by
+//! default. Enabling the `f64` feature switches the whole simulator, including
+//! the OpenCL kernels, to double precision.
+
+use num_complex;
+
+/// The floating point type used for amplitudes, probabilities and gate matrices
+#[cfg(not(feature = "f64"))]
+pub type Real = f32;
+
+/// The floating point type used for amplitudes, probabilities and gate matrices
+#[cfg(feature = "f64")]
+pub type Real = f64;
+
+/// A complex number with `Real` components
+pub type Complex = num_complex::Complex<Real>;
+
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts;
+
+#[cfg(feature = "f64")]
+pub use std::f64::consts;
@@ -0,0 +1,571 @@
+//! Writes a `Circuit` as OpenQASM 2.0
+//!
+//! Every unitary operation is lowered to the gates of `qelib1.inc`. Single
+//! qubit gates without a standard name are written as `u3`, with the global
+//! phase of a controlled gate moved onto its control. Gates with several
+//! controls are built from singly controlled square roots of the gate, and
+//! gates on several qubits are split into two-level unitaries, each of which
+//! is a multi-controlled gate.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use circuit::{Circuit, Operation};
+use error::{Error, Result};
+use export::{approx_eq, arithmetic, check_qubits, unconditional, TOLERANCE};
+use gates::{h, id, s, sdg, sqrt_x, t, tdg, x, y, z};
+use gates::{Control, Gate, MatrixGate};
+use precision::{Complex, Real};
+use state::{qft_sequence, QftGate, QftOptions};
+use super::Unsupported;
+
+/// The name of the format, for errors
+const FORMAT: &str = "OpenQASM 2.0";
+
+/// Write a circuit as OpenQASM 2.0
+pub(crate) fn export(circuit: &Circuit, unsupported: Unsupported) -> Result<String> {
+    for operation in circuit.operations() {
+        check_qubits(operation, circuit.num_qubits())?;
+    }
+
+    let registers = classical_registers(circuit)?;
+    let mut writer = Writer {
+        unsupported,
+        registers,
+        condition: None,
+        opaque: BTreeMap::new(),
+        lines: Vec::new(),
+    };
+
+    for operation in circuit.operations() {
+        writer.operation(operation)?;
+    }
+
+    let mut output = String::from("OPENQASM 2.0;\ninclude \"qelib1.inc\";\n");
+    for declaration in writer.opaque.values() {
+        output += declaration;
+        output += "\n";
+    }
+    if circuit.num_qubits() > 0 {
+        output += &format!("qreg q[{}];\n", circuit.num_qubits());
+    }
+    for register in &writer.registers {
+        output += &format!("creg {}[{}];\n", register.0, register.1.len());
+    }
+    for line in &writer.lines {
+        output += line;
+        output += "\n";
+    }
+
+    Ok(output)
+}
+
+/// Split the classical bits into registers, so the bits of each condition
+/// are exactly one register, as `if` compares a whole register
+fn classical_registers(circuit: &Circuit) -> Result<Vec<(String, Range<usize>)>> {
+    let conditions: Vec<&Range<usize>> = circuit
+        .operations()
+        .iter()
+        .filter_map(|operation| match *operation {
+            Operation::Conditional { ref bits, .. } if !bits.is_empty() => Some(bits),
+            _ => None,
+        })
+        .collect();
+
+    let mut boundaries = vec![0, circuit.num_bits()];
+    for bits in &conditions {
+        boundaries.push(bits.start);
+        boundaries.push(bits.end);
+    }
+    boundaries.sort();
+    boundaries.dedup();
+
+    if conditions
+        .iter()
+        .any(|bits| boundaries.iter().any(|&b| bits.start < b && b < bits.end))
+    {
+        return Err(Error::Unsupported {
+            operation: "a condition on bits which overlap those of another condition".to_string(),
+            format: FORMAT,
+        });
+    }
+
+    let ranges: Vec<Range<usize>> = boundaries.windows(2).map(|pair| pair[0]..pair[1]).collect();
+    let registers = if ranges.len() == 1 {
+        vec![("c".to_string(), ranges[0].clone())]
+    } else {
+        ranges
+            .into_iter()
+            .enumerate()
+            .map(|(i, bits)| (format!("c{}", i), bits))
+            .collect()
+    };
+
+    Ok(registers)
+}
+
+struct Writer {
+    unsupported: Unsupported,
+    /// The name and bits of each classical register
+    registers: Vec<(String, Range<usize>)>,
+    /// The `if` which each line is prefixed with, inside of a conditional operation
+    condition: Option<String>,
+    /// The declarations of the opaque gates, by name
+    opaque: BTreeMap<String, String>,
+    lines: Vec<String>,
+}
+
+impl Writer {
+    fn line(&mut self, text: String) {
+        let condition = self.condition.clone().unwrap_or_default();
+        self.lines.push(format!("{}{};", condition, text));
+    }
+
+    /// Apply a gate of `qelib1.inc`
+    fn apply(&mut self, name: &str, params: &[Real], qubits: &[i32]) {
+        let params = if params.is_empty() {
+            String::new()
+        } else {
+            let params: Vec<String> = params.iter().map(Real::to_string).collect();
+            format!("({})", params.join(", "))
+        };
+        let qubits: Vec<String> = qubits.iter().map(|qubit| format!("q[{}]", qubit)).collect();
+
+        self.line(format!("{}{} {}", name, params, qubits.join(", ")));
+    }
+
+    /// The name of a classical bit
+    fn bit(&self, bit: usize) -> String {
+        let register = self.registers
+            .iter()
+            .find(|register| register.1.contains(&bit))
+            .expect("every bit is in a register");
+
+        format!("{}[{}]", register.0, bit - register.1.start)
+    }
+
+    fn operation(&mut self, operation: &Operation) -> Result<()> {
+        match *operation {
+            Operation::Gate { target, ref gate } => self.gate(target, gate),
+            Operation::ControlledGate {
+                control,
+                target,
+                ref gate,
+            } => self.controlled(control, target, gate),
+            Operation::MultiControlledGate {
+                ref controls,
+                target,
+                ref gate,
+            } => self.multi_controlled(controls, target, gate),
+            Operation::Toffoli {
+                control1,
+                control2,
+                target,
+            } => self.apply("ccx", &[], &[control1, control2, target]),
+            Operation::TwoQubitGate {
+                qubit0,
+                qubit1,
+                ref gate,
+            } => self.matrix(&[qubit0, qubit1], &MatrixGate::from(*gate)),
+            Operation::Matrix {
+                ref qubits,
+                ref gate,
+            } => self.matrix(qubits, gate),
+            Operation::Swap { first, second } => self.apply("swap", &[], &[first, second]),
+            Operation::Qft {
+                ref qubits,
+                options,
+                inverse,
+            } => self.qft(qubits, options, inverse),
+            Operation::Arithmetic {
+                ref controls,
+                ref operation,
+            } => {
+                let (name, params) = arithmetic(operation);
+                return self.opaque(name, &params, controls, &operation.qubits());
+            }
+            Operation::PowMod {
+                x,
+                n,
+                input_width,
+                output_width,
+            } => {
+                let qubits: Vec<i32> = (0..input_width.max(0) + output_width.max(0)).collect();
+                let params = [("x", x.to_string()), ("n", n.to_string())];
+                return self.opaque("pow_mod", &params, &[], &qubits);
+            }
+            Operation::Measure { qubit, bit } => {
+                let bit = self.bit(bit);
+                self.line(format!("measure q[{}] -> {}", qubit, bit));
+            }
+            Operation::Conditional {
+                ref bits,
+                value,
+                ref operations,
+            } => return self.conditional(bits, value, operations),
+        }
+
+        Ok(())
+    }
+
+    fn conditional(&mut self, bits: &Range<usize>, value: u64, operations: &[Operation]) -> Result<()> {
+        if let Some(operations) = unconditional(bits, value, operations) {
+            return operations
+                .iter()
+                .try_for_each(|operation| self.operation(operation));
+        }
+
+        if self.condition.is_some() {
+            return Err(Error::Unsupported {
+                operation: "a nested condition".to_string(),
+                format: FORMAT,
+            });
+        }
+
+        // Every line of the operations repeats the condition, so they can't
+        // change the bits it depends on
+        let measures_condition = operations.iter().any(|operation| match *operation {
+            Operation::Measure { bit, .. } => bits.contains(&bit),
+            _ => false,
+        });
+        if measures_condition && operations.len() > 1 {
+            return Err(Error::Unsupported {
+                operation: "a condition on bits measured by the operations it controls".to_string(),
+                format: FORMAT,
+            });
+        }
+
+        let name = self.registers
+            .iter()
+            .find(|register| register.1 == *bits)
+            .map(|register| register.0.clone())
+            .expect("every condition is a register");
+        self.condition = Some(format!("if ({} == {}) ", name, value));
+
+        let result = operations
+            .iter()
+            .try_for_each(|operation| self.operation(operation));
+        self.condition = None;
+
+        result
+    }
+
+    fn gate(&mut self, target: i32, gate: &Gate) {
+        // Without a control, the global phase has no effect
+        let named = [
+            ("id", id()),
+            ("x", x()),
+            ("y", y()),
+            ("z", z()),
+            ("h", h()),
+            ("s", s()),
+            ("sdg", sdg()),
+            ("t", t()),
+            ("tdg", tdg()),
+            ("sx", sqrt_x()),
+            ("sxdg", sqrt_x().adjoint()),
+        ];
+        if let Some(&(name, _)) = named
+            .iter()
+            .find(|named| gate.approx_eq_up_to_phase(&named.1, TOLERANCE))
+        {
+            return self.apply(name, &[], &[target]);
+        }
+
+        let (_, theta, phi, lambda) = u3_angles(gate);
+        if theta.abs() < TOLERANCE {
+            self.apply("u1", &[phi + lambda], &[target]);
+        } else {
+            self.apply("u3", &[theta, phi, lambda], &[target]);
+        }
+    }
+
+    fn controlled(&mut self, control: i32, target: i32, gate: &Gate) {
+        let named = [
+            ("cx", x()),
+            ("cy", y()),
+            ("cz", z()),
+            ("ch", h()),
+            ("csx", sqrt_x()),
+        ];
+        if let Some(&(name, _)) = named
+            .iter()
+            .find(|named| approx_eq(gate, &named.1))
+        {
+            return self.apply(name, &[], &[control, target]);
+        }
+
+        // The global phase of the gate is applied when the control is 1
+        let (phase, theta, phi, lambda) = u3_angles(gate);
+        if theta.abs() < TOLERANCE {
+            self.apply("cu1", &[phi + lambda], &[control, target]);
+        } else {
+            self.apply("cu3", &[theta, phi, lambda], &[control, target]);
+        }
+        if phase.abs() >= TOLERANCE {
+            self.apply("u1", &[phase], &[control]);
+        }
+    }
+
+    /// A gate with any controls, where the negative controls are flipped before
+    /// and after the gate
+    fn multi_controlled(&mut self, controls: &[Control], target: i32, gate: &Gate) {
+        let negative: Vec<i32> = controls
+            .iter()
+            .filter(|control| !control.value())
+            .map(Control::qubit)
+            .collect();
+        let qubits: Vec<i32> = controls.iter().map(Control::qubit).collect();
+
+        for &qubit in &negative {
+            self.apply("x", &[], &[qubit]);
+        }
+        self.positively_controlled(&qubits, target, gate);
+        for &qubit in &negative {
+            self.apply("x", &[], &[qubit]);
+        }
+    }
+
+    /// A gate which applies when every control is 1.
+    ///
+    /// With more than one control, this uses the decomposition of Barenco et al.
+    /// into gates controlled by the last control and the others, where V is the
+    /// square root of the gate:
+    /// C^n U = C'V (C'X) V† (C'X) V, with V, V† controlled by the last control.
+    fn positively_controlled(&mut self, controls: &[i32], target: i32, gate: &Gate) {
+        let (&last, rest) = match controls.split_last() {
+            Some(split) => split,
+            None => return self.gate(target, gate),
+        };
+
+        if rest.is_empty() {
+            return self.controlled(last, target, gate);
+        }
+        if rest.len() == 1 && approx_eq(gate, &x()) {
+            return self.apply("ccx", &[], &[rest[0], last, target]);
+        }
+
+        let root = gate.pow(0.5);
+        self.controlled(last, target, &root);
+        self.positively_controlled(rest, last, &x());
+        self.controlled(last, target, &root.adjoint());
+        self.positively_controlled(rest, last, &x());
+        self.positively_controlled(rest, target, &root);
+    }
+
+    /// A gate on several qubits, as a product of two-level unitaries.
+    ///
+    /// Each entry below the diagonal is zeroed in turn by a two-level rotation
+    /// of its row and the diagonal's, and the phases left on the diagonal by
+    /// two-level phase gates. The product of these factors undoes the matrix,
+    /// so their adjoints are applied in the reverse order.
+    fn matrix(&mut self, qubits: &[i32], gate: &MatrixGate) {
+        let dim = gate.dimension();
+        let matrix = gate.matrix();
+        if dim == 2 {
+            return self.gate(
+                qubits[0],
+                &Gate {
+                    a: matrix[0],
+                    b: matrix[1],
+                    c: matrix[2],
+                    d: matrix[3],
+                },
+            );
+        }
+
+        let zero = Complex::new(0.0, 0.0);
+        let one = Complex::new(1.0, 0.0);
+        let mut matrix = matrix.to_vec();
+        let mut factors = Vec::new();
+
+        for col in 0..dim {
+            for row in col + 1..dim {
+                let (a, b) = (matrix[col * dim + col], matrix[row * dim + col]);
+                if b.norm() < TOLERANCE {
+                    continue;
+                }
+
+                let norm = (a.norm_sqr() + b.norm_sqr()).sqrt();
+                let rotation = Gate {
+                    a: a.conj() / norm,
+                    b: b.conj() / norm,
+                    c: -b / norm,
+                    d: a / norm,
+                };
+                apply_two_level(&mut matrix, dim, col, row, &rotation);
+                factors.push((col, row, rotation));
+            }
+
+            let phase = matrix[col * dim + col];
+            if (phase - one).norm() >= TOLERANCE {
+                let partner = if col + 1 < dim { col + 1 } else { col - 1 };
+                let fix = Gate {
+                    a: phase.conj(),
+                    b: zero,
+                    c: zero,
+                    d: one,
+                };
+                apply_two_level(&mut matrix, dim, col, partner, &fix);
+                factors.push((col, partner, fix));
+            }
+        }
+
+        for &(first, second, ref factor) in factors.iter().rev() {
+            self.two_level(qubits, first, second, &factor.adjoint());
+        }
+    }
+
+    /// A gate acting on the basis states `first` and `second` of the qubits.
+    ///
+    /// The states are brought next to each other along a Gray code, by
+    /// multi-controlled X gates, so they differ in a single qubit. The gate is
+    /// then applied to that qubit, controlled by the values of the others.
+    fn two_level(&mut self, qubits: &[i32], first: usize, second: usize, gate: &Gate) {
+        let mut path = vec![first];
+        for bit in 0..qubits.len() {
+            let current = path[path.len() - 1];
+            if (current ^ second) >> bit & 1 == 1 {
+                path.push(current ^ (1 << bit));
+            }
+        }
+
+        let steps: Vec<(usize, usize)> = path.windows(2)
+            .take(path.len() - 2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect();
+        for &(from, to) in &steps {
+            self.flip(qubits, from, to, &x());
+        }
+
+        let last = path[path.len() - 2];
+        let gate = if last < second {
+            *gate
+        } else {
+            Gate {
+                a: gate.d,
+                b: gate.c,
+                c: gate.b,
+                d: gate.a,
+            }
+        };
+        self.flip(qubits, last, second, &gate);
+
+        for &(from, to) in steps.iter().rev() {
+            self.flip(qubits, from, to, &x());
+        }
+    }
+
+    /// Apply a gate to the qubit where the basis states `from` and `to` differ,
+    /// controlled by the other qubits having their values in `from`
+    fn flip(&mut self, qubits: &[i32], from: usize, to: usize, gate: &Gate) {
+        let bit = (from ^ to).trailing_zeros() as usize;
+        let controls: Vec<Control> = (0..qubits.len())
+            .filter(|&i| i != bit)
+            .map(|i| {
+                if from >> i & 1 == 1 {
+                    Control::Positive(qubits[i])
+                } else {
+                    Control::Negative(qubits[i])
+                }
+            })
+            .collect();
+
+        self.multi_controlled(&controls, qubits[bit], gate);
+    }
+
+    /// The quantum Fourier transform, as Hadamard gates and controlled phases
+    fn qft(&mut self, qubits: &Range<i32>, options: QftOptions, inverse: bool) {
+        for gate in qft_sequence(qubits, options, inverse) {
+            match gate {
+                QftGate::H(target) => self.apply("h", &[], &[target]),
+                QftGate::Rotation {
+                    control,
+                    target,
+                    angle,
+                } => self.apply("cu1", &[angle], &[control, target]),
+                QftGate::Swap(first, second) => self.apply("swap", &[], &[first, second]),
+            }
+        }
+    }
+
+    /// An operation with no equivalent in OpenQASM 2.0, which is either an
+    /// error or an opaque gate, named for the operation and the number of
+    /// qubits it acts on
+    fn opaque(
+        &mut self,
+        operation: &str,
+        params: &[(&str, String)],
+        controls: &[Control],
+        qubits: &[i32],
+    ) -> Result<()> {
+        if self.unsupported == Unsupported::Error {
+            return Err(Error::Unsupported {
+                operation: operation.to_string(),
+                format: FORMAT,
+            });
+        }
+
+        let mut name = "c".repeat(controls.len()) + operation;
+        let qubits: Vec<i32> = controls
+            .iter()
+            .map(Control::qubit)
+            .chain(qubits.iter().cloned())
+            .collect();
+        name += &format!("_{}", qubits.len());
+
+        let (names, values): (Vec<&str>, Vec<&str>) = params
+            .iter()
+            .map(|&(name, ref value)| (name, value.as_str()))
+            .unzip();
+        let (names, values) = if params.is_empty() {
+            (String::new(), String::new())
+        } else {
+            (
+                format!("({})", names.join(", ")),
+                format!("({})", values.join(", ")),
+            )
+        };
+
+        let arguments: Vec<String> = (0..qubits.len()).map(|i| format!("a{}", i)).collect();
+        let declaration = format!("opaque {}{} {};", name, names, arguments.join(", "));
+        let _ = self.opaque.entry(name.clone()).or_insert(declaration);
+
+        let negative: Vec<i32> = controls
+            .iter()
+            .filter(|control| !control.value())
+            .map(Control::qubit)
+            .collect();
+        for &qubit in &negative {
+            self.apply("x", &[], &[qubit]);
+        }
+        let qubits: Vec<String> = qubits.iter().map(|qubit| format!("q[{}]", qubit)).collect();
+        self.line(format!("{}{} {}", name, values, qubits.join(", ")));
+        for &qubit in &negative {
+            self.apply("x", &[], &[qubit]);
+        }
+
+        Ok(())
+    }
+}
+
+/// The global phase and angles `(phase, theta, phi, lambda)` of a unitary
+/// gate, such that the gate is e^(i phase) U3(theta, phi, lambda)
+fn u3_angles(gate: &Gate) -> (Real, Real, Real, Real) {
+    let (alpha, beta, gamma, delta) = gate.to_euler_zyz();
+
+    // Rz(beta) Ry(gamma) Rz(delta) is e^(-i (beta + delta)/2) U3(gamma, beta, delta)
+    let phase = alpha - (beta + delta) / 2.0;
+    let phase = phase.sin().atan2(phase.cos());
+
+    (phase, gamma, beta, delta)
+}
+
+/// Multiply a dim x dim matrix on the left by a gate acting on rows `first` and `second`
+fn apply_two_level(matrix: &mut [Complex], dim: usize, first: usize, second: usize, gate: &Gate) {
+    for col in 0..dim {
+        let (a, b) = (matrix[first * dim + col], matrix[second * dim + col]);
+        matrix[first * dim + col] = gate.a * a + gate.b * b;
+        matrix[second * dim + col] = gate.c * a + gate.d * b;
+    }
+}
@@ -0,0 +1,257 @@
+//! Splits OpenQASM 2.0 and 3 source into tokens, keeping track of where each token
+//! starts so errors can point at it. The Quil lexer produces the same tokens.
+
+use error::{Error, Result};
+
+/// The symbols of OpenQASM 2.0 and 3, longest first so `->` isn't read as `-`
+const SYMBOLS: [&str; 26] = [
+    "->", "==", "!=", "<=", ">=", "&&", "||", ";", ",", "(", ")", "[", "]", "{", "}", "+", "-",
+    "*", "/", "%", "^", "=", "<", ">", "!", ":",
+];
+
+/// A token of OpenQASM source
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token {
+    /// An identifier or keyword
+    Identifier(String),
+    /// An integer literal
+    Integer(u64),
+    /// A real literal, kept as written so it can be parsed at the precision needed
+    Real(String),
+    /// An imaginary literal of Quil, such as `1.5i`, kept as written without the `i`
+    Imaginary(String),
+    /// A string literal, without the quotes
+    Str(String),
+    /// One of `SYMBOLS`
+    Symbol(&'static str),
+    /// The end of the source
+    End,
+}
+
+/// A token, and the line and column it starts at
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Spanned {
+    pub(crate) token: Token,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+}
+
+impl Spanned {
+    /// An error at the position of the token
+    pub(crate) fn error<S: Into<String>>(&self, message: S) -> Error {
+        Error::Parse {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+}
+
+/// A position in a list of tokens, which ends with `Token::End`
+#[derive(Debug, Clone)]
+pub(crate) struct Cursor {
+    tokens: Vec<Spanned>,
+    position: usize,
+}
+
+impl Cursor {
+    pub(crate) fn new(tokens: Vec<Spanned>) -> Cursor {
+        Cursor {
+            tokens,
+            position: 0,
+        }
+    }
+
+    /// The next token, without consuming it
+    pub(crate) fn peek(&self) -> &Spanned {
+        &self.tokens[self.position]
+    }
+
+    /// Consume the next token. The end of the source is never consumed.
+    pub(crate) fn next(&mut self) -> Spanned {
+        let token = self.tokens[self.position].clone();
+        if token.token != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    /// Whether the next token is the symbol
+    pub(crate) fn at(&self, symbol: &str) -> bool {
+        match self.peek().token {
+            Token::Symbol(next) => next == symbol,
+            _ => false,
+        }
+    }
+
+    /// Whether the next token is the keyword or identifier
+    pub(crate) fn at_identifier(&self, name: &str) -> bool {
+        match self.peek().token {
+            Token::Identifier(ref next) => next == name,
+            _ => false,
+        }
+    }
+
+    /// Consume the symbol, or return an error if the next token is anything else
+    pub(crate) fn expect(&mut self, symbol: &str) -> Result<Spanned> {
+        if self.at(symbol) {
+            Ok(self.next())
+        } else {
+            let token = self.peek();
+            Err(token.error(format!("expected `{}`, found {}", symbol, describe(token))))
+        }
+    }
+
+    /// Consume an identifier
+    pub(crate) fn identifier(&mut self, expected: &str) -> Result<(String, Spanned)> {
+        let token = self.next();
+        match token.token {
+            Token::Identifier(ref name) => Ok((name.clone(), token.clone())),
+            _ => Err(token.error(format!("expected {}, found {}", expected, describe(&token)))),
+        }
+    }
+
+    /// Consume an integer
+    pub(crate) fn integer(&mut self) -> Result<(u64, Spanned)> {
+        let token = self.next();
+        match token.token {
+            Token::Integer(value) => Ok((value, token.clone())),
+            _ => Err(token.error(format!("expected an integer, found {}", describe(&token)))),
+        }
+    }
+}
+
+/// A description of a token for error messages
+pub(crate) fn describe(token: &Spanned) -> String {
+    match token.token {
+        Token::Identifier(ref name) => format!("`{}`", name),
+        Token::Integer(value) => value.to_string(),
+        Token::Real(ref text) => text.clone(),
+        Token::Imaginary(ref text) => format!("{}i", text),
+        Token::Str(ref text) => format!("{:?}", text),
+        Token::Symbol("\n") => "the end of the line".to_string(),
+        Token::Symbol(symbol) => format!("`{}`", symbol),
+        Token::End => "the end of the file".to_string(),
+    }
+}
+
+/// Split the source into tokens, ending with `Token::End`
+///
+/// Whitespace and `//` comments are skipped.
+pub(crate) fn tokenize(source: &str) -> Result<Vec<Spanned>> {
+    let mut tokens = Vec::new();
+
+    for (line, text) in source.lines().enumerate() {
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let start = i;
+            let c = chars[i];
+            let error = |message: String| Error::Parse {
+                line: line + 1,
+                column: start + 1,
+                message,
+            };
+
+            let token = if c.is_whitespace() {
+                i += 1;
+                continue;
+            } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+                break;
+            } else if c.is_alphabetic() || c == '_' {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                Token::Identifier(chars[start..i].iter().collect())
+            } else if starts_number(&chars, i) {
+                let (end, token) = number(&chars, i, line + 1)?;
+                i = end;
+                token
+            } else if c == '"' {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    i += 1;
+                }
+                if i == chars.len() {
+                    return Err(error("unterminated string".to_string()));
+                }
+                i += 1;
+                Token::Str(chars[start + 1..i - 1].iter().collect())
+            } else {
+                let rest: String = chars[i..].iter().take(2).collect();
+                match SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)) {
+                    Some(symbol) => {
+                        i += symbol.len();
+                        Token::Symbol(symbol)
+                    }
+                    None => return Err(error(format!("unexpected character {:?}", c))),
+                }
+            };
+
+            tokens.push(Spanned {
+                token,
+                line: line + 1,
+                column: start + 1,
+            });
+        }
+    }
+
+    let line = source.lines().count().max(1);
+    let column = source.lines().last().map_or(0, |text| text.chars().count()) + 1;
+    tokens.push(Spanned {
+        token: Token::End,
+        line,
+        column,
+    });
+
+    Ok(tokens)
+}
+
+/// Whether a number starts at `i`
+pub(crate) fn starts_number(chars: &[char], i: usize) -> bool {
+    chars[i].is_ascii_digit()
+        || (chars[i] == '.' && chars.get(i + 1).map_or(false, |c| c.is_ascii_digit()))
+}
+
+/// Read the integer or real literal starting at `start` of a line, returning
+/// the index after its end and the token
+pub(crate) fn number(chars: &[char], start: usize, line: usize) -> Result<(usize, Token)> {
+    let mut i = start;
+    let mut real = false;
+    while i < chars.len() && chars[i].is_ascii_digit() {
+        i += 1;
+    }
+    if i < chars.len() && chars[i] == '.' {
+        real = true;
+        i += 1;
+        while i < chars.len() && chars[i].is_ascii_digit() {
+            i += 1;
+        }
+    }
+    if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+        let sign = chars.get(i + 1) == Some(&'+') || chars.get(i + 1) == Some(&'-');
+        let digits = if sign { i + 2 } else { i + 1 };
+        if digits < chars.len() && chars[digits].is_ascii_digit() {
+            real = true;
+            i = digits;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+        }
+    }
+
+    let text: String = chars[start..i].iter().collect();
+    if real {
+        Ok((i, Token::Real(text)))
+    } else {
+        match text.parse() {
+            Ok(value) => Ok((i, Token::Integer(value))),
+            Err(_) => Err(Error::Parse {
+                line,
+                column: start + 1,
+                message: format!("the integer {} is too large", text),
+            }),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! OpenQASM
+//!
+//! Imports circuits written in [OpenQASM 2.0](https://arxiv.org/abs/1707.03429),
+//! and exports circuits to it.
+//! Registers are laid out in the order they are declared, so the first `qreg`
+//! starts at qubit 0, and the first `creg` at bit 0.
+//!
+//! ```
+//!# extern crate qcgpu;
+//!# use qcgpu::qasm;
+//!# use qcgpu::backends::Cpu;
+//! let circuit = qasm::parse(r#"
+//!     OPENQASM 2.0;
+//!     include "qelib1.inc";
+//!
+//!     qreg q[2];
+//!     creg c[2];
+//!
+//!     h q[0];
+//!     cx q[0], q[1];
+//!     measure q -> c;
+//! "#).unwrap();
+//!
+//! let (_, bits) = circuit.execute(Cpu::new()).unwrap();
+//! assert_eq!(bits[0], bits[1]);
+//! ```
+//!
+//! The gates of `qelib1.inc` are supported, along with `gate` definitions,
+//! `measure`, `barrier`, and `if` statements comparing a classical register to
+//! an integer. `reset` and applying `opaque` gates can't be simulated, so they
+//! are errors, as is including any file other than `qelib1.inc`.
+//!
+//! `export` writes a circuit using the gates of `qelib1.inc`, so other
+//! simulators can run it:
+//!
+//! ```
+//!# extern crate qcgpu;
+//!# use qcgpu::{qasm, Circuit};
+//! let mut circuit = Circuit::new(2);
+//! circuit.h(0).cx(0, 1).measure_all();
+//!
+//! let source = qasm::export(&circuit).unwrap();
+//! assert!(source.contains("cx q[0], q[1];"));
+//! ```
+
+mod export;
+mod parser;
+pub(crate) mod lexer;
+pub(crate) mod standard;
+
+use circuit::Circuit;
+use error::Result;
+
+/// Parse an OpenQASM 2.0 program into a circuit.
+///
+/// Returns `Error::Parse`, with the line and column of the problem, if the
+/// program is invalid or uses a construct which isn't supported.
+pub fn parse(source: &str) -> Result<Circuit> {
+    parser::parse(source)
+}
+
+/// How `export_with` writes operations which have no equivalent in OpenQASM 2.0,
+/// such as arithmetic and `pow_mod`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unsupported {
+    /// Return `Error::Unsupported`
+    Error,
+    /// Declare an opaque gate for each kind of operation, and apply it. Other
+    /// tools can read the result, but not simulate those gates.
+    Opaque,
+}
+
+/// Write a circuit as OpenQASM 2.0.
+///
+/// Single qubit gates without a name in `qelib1.inc` are written as `u3`,
+/// and gates on several qubits are decomposed into single qubit and
+/// controlled gates. Each condition becomes a classical register, which every
+/// gate it controls is compared against.
+///
+/// Returns `Error::Unsupported` for arithmetic, `pow_mod`, nested conditions
+/// and conditions on overlapping bits. See `export_with` to write opaque gates
+/// for arithmetic instead. Returns `Error::InvalidQubit` or `Error::DuplicateQubit`
+/// if an operation's qubits are outside of the register or repeated.
+pub fn export(circuit: &Circuit) -> Result<String> {
+    export::export(circuit, Unsupported::Error)
+}
+
+/// Write a circuit as OpenQASM 2.0, choosing how to write the operations
+/// which have no equivalent. See `export`.
+///
+/// ```
+///# extern crate qcgpu;
+///# use qcgpu::{qasm, Circuit};
+///# use qcgpu::qasm::Unsupported;
+/// let mut circuit = Circuit::new(6);
+/// circuit.pow_mod(2, 15, 3, 3);
+///
+/// let source = qasm::export_with(&circuit, Unsupported::Opaque).unwrap();
+/// assert!(source.contains("opaque pow_mod_6(x, n) a0, a1, a2, a3, a4, a5;"));
+/// assert!(qasm::export(&circuit).is_err());
+/// ```
+pub fn export_with(circuit: &Circuit, unsupported: Unsupported) -> Result<String> {
+    export::export(circuit, unsupported)
+}
@@ -0,0 +1,694 @@
+//! Parses OpenQASM 2.0 into a `Circuit`
+//!
+//! Gate definitions are kept as lists of the gates they apply, and expanded
+//! each time they are used. The standard gates are applied directly.
+
+use std::collections::HashMap;
+
+use circuit::{Circuit, Operation};
+use error::Result;
+use gates::{u3, x};
+use precision::Real;
+use precision::consts::PI;
+use super::lexer::{describe, tokenize, Cursor, Spanned, Token};
+use super::standard;
+
+/// The most qubits a circuit can have
+const MAX_QUBITS: usize = 64;
+
+/// An expression for a gate parameter
+#[derive(Debug, Clone)]
+enum Expression {
+    Number(Real),
+    /// The parameter of the enclosing gate definition with the given index
+    Parameter(usize),
+    Negate(Box<Expression>),
+    Binary(&'static str, Box<Expression>, Box<Expression>),
+    Function(fn(Real) -> Real, Box<Expression>),
+}
+
+impl Expression {
+    /// The value of the expression, given the values of the parameters
+    fn evaluate(&self, params: &[Real]) -> Real {
+        match *self {
+            Expression::Number(value) => value,
+            Expression::Parameter(index) => params[index],
+            Expression::Negate(ref operand) => -operand.evaluate(params),
+            Expression::Binary(op, ref left, ref right) => {
+                let (left, right) = (left.evaluate(params), right.evaluate(params));
+                match op {
+                    "+" => left + right,
+                    "-" => left - right,
+                    "*" => left * right,
+                    "/" => left / right,
+                    _ => left.powf(right),
+                }
+            }
+            Expression::Function(function, ref operand) => function(operand.evaluate(params)),
+        }
+    }
+}
+
+/// The functions which can be used in expressions
+fn function(name: &str) -> Option<fn(Real) -> Real> {
+    match name {
+        "sin" => Some(Real::sin),
+        "cos" => Some(Real::cos),
+        "tan" => Some(Real::tan),
+        "exp" => Some(Real::exp),
+        "ln" => Some(Real::ln),
+        "sqrt" => Some(Real::sqrt),
+        _ => None,
+    }
+}
+
+/// A gate applied in the body of a gate definition
+#[derive(Debug, Clone)]
+struct GateCall {
+    name: String,
+    params: Vec<Expression>,
+    /// The indices of the qubit arguments of the enclosing definition
+    qubits: Vec<usize>,
+}
+
+/// A gate declared with `gate` or `opaque`
+#[derive(Debug, Clone)]
+struct Definition {
+    num_params: usize,
+    num_qubits: usize,
+    /// The gates applied, or `None` for an opaque gate
+    body: Option<Vec<GateCall>>,
+}
+
+/// A quantum or classical register
+#[derive(Debug, Clone, Copy)]
+struct Register {
+    start: usize,
+    size: usize,
+    quantum: bool,
+}
+
+/// Parse OpenQASM 2.0 source into a circuit
+pub(crate) fn parse(source: &str) -> Result<Circuit> {
+    let mut parser = Parser {
+        tokens: Cursor::new(tokenize(source)?),
+        registers: HashMap::new(),
+        num_qubits: 0,
+        num_bits: 0,
+        definitions: HashMap::new(),
+        included: false,
+        operations: Vec::new(),
+    };
+
+    parser.program()?;
+
+    let mut circuit = Circuit::new(parser.num_qubits as u32).with_bits(parser.num_bits);
+    for operation in parser.operations {
+        let _ = circuit.push(operation);
+    }
+
+    Ok(circuit)
+}
+
+struct Parser {
+    tokens: Cursor,
+    registers: HashMap<String, Register>,
+    num_qubits: usize,
+    num_bits: usize,
+    definitions: HashMap<String, Definition>,
+    /// Whether `qelib1.inc` has been included, so the standard gates are defined
+    included: bool,
+    operations: Vec<Operation>,
+}
+
+impl Parser {
+    fn program(&mut self) -> Result<()> {
+        let (keyword, token) = self.tokens.identifier("`OPENQASM 2.0;`")?;
+        if keyword != "OPENQASM" {
+            return Err(token.error("the file must start with `OPENQASM 2.0;`"));
+        }
+
+        let version = self.tokens.next();
+        match version.token {
+            Token::Real(ref text) if text.starts_with("2.") => {}
+            Token::Integer(2) => {}
+            _ => {
+                return Err(version.error(format!(
+                    "only OpenQASM 2.0 is supported, not {}",
+                    describe(&version)
+                )))
+            }
+        }
+        let _ = self.tokens.expect(";")?;
+
+        while self.tokens.peek().token != Token::End {
+            self.statement()?;
+        }
+
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<()> {
+        let (name, token) = self.tokens.identifier("a statement")?;
+
+        match name.as_str() {
+            "include" => self.include(),
+            "qreg" => self.declaration(true),
+            "creg" => self.declaration(false),
+            "gate" => self.gate_definition(),
+            "opaque" => self.opaque_definition(),
+            "barrier" => {
+                let _ = self.qubit_arguments()?;
+                let _ = self.tokens.expect(";")?;
+                Ok(())
+            }
+            "if" => self.conditional(),
+            "OPENQASM" => Err(token.error("the version can only be given at the start of the file")),
+            _ => {
+                let operations = self.quantum_operation(&name, &token)?;
+                self.operations.extend(operations);
+                Ok(())
+            }
+        }
+    }
+
+    fn include(&mut self) -> Result<()> {
+        let file = self.tokens.next();
+        match file.token {
+            Token::Str(ref name) if name == "qelib1.inc" => self.included = true,
+            Token::Str(_) => return Err(file.error("only \"qelib1.inc\" can be included")),
+            _ => {
+                return Err(file.error(format!(
+                    "expected a file name, found {}",
+                    describe(&file)
+                )))
+            }
+        }
+
+        let _ = self.tokens.expect(";")?;
+        Ok(())
+    }
+
+    /// Check that a name isn't already used by a register or gate
+    fn check_unused(&self, name: &str, token: &Spanned) -> Result<()> {
+        if self.registers.contains_key(name) || self.gate_arity(name).is_some() {
+            return Err(token.error(format!("`{}` is already defined", name)));
+        }
+
+        Ok(())
+    }
+
+    fn declaration(&mut self, quantum: bool) -> Result<()> {
+        let (name, token) = self.tokens.identifier("a register name")?;
+        self.check_unused(&name, &token)?;
+
+        let _ = self.tokens.expect("[")?;
+        let (size, size_token) = self.tokens.integer()?;
+        let _ = self.tokens.expect("]")?;
+        let _ = self.tokens.expect(";")?;
+
+        if size == 0 {
+            return Err(size_token.error("a register must have at least one bit"));
+        }
+
+        let start = if quantum { self.num_qubits } else { self.num_bits };
+        if quantum && size > (MAX_QUBITS - start) as u64 {
+            return Err(size_token.error(format!(
+                "the circuit can't have more than {} qubits",
+                MAX_QUBITS
+            )));
+        }
+
+        let size = size as usize;
+        if quantum {
+            self.num_qubits += size;
+        } else {
+            self.num_bits += size;
+        }
+
+        let _ = self.registers.insert(
+            name,
+            Register {
+                start,
+                size,
+                quantum,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// The parameter names of a gate definition, if it has any
+    fn parameter_names(&mut self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        if self.tokens.at("(") {
+            let _ = self.tokens.next();
+            while !self.tokens.at(")") {
+                if !names.is_empty() {
+                    let _ = self.tokens.expect(",")?;
+                }
+                let (name, token) = self.tokens.identifier("a parameter name")?;
+                if names.contains(&name) {
+                    return Err(token.error(format!("the parameter `{}` is given twice", name)));
+                }
+                names.push(name);
+            }
+            let _ = self.tokens.next();
+        }
+
+        Ok(names)
+    }
+
+    /// The qubit argument names of a gate definition
+    fn argument_names(&mut self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        loop {
+            let (name, token) = self.tokens.identifier("a qubit argument")?;
+            if names.contains(&name) {
+                return Err(token.error(format!("the argument `{}` is given twice", name)));
+            }
+            names.push(name);
+
+            if !self.tokens.at(",") {
+                return Ok(names);
+            }
+            let _ = self.tokens.next();
+        }
+    }
+
+    fn gate_definition(&mut self) -> Result<()> {
+        let (name, token) = self.tokens.identifier("a gate name")?;
+        self.check_unused(&name, &token)?;
+
+        let params = self.parameter_names()?;
+        let qubits = self.argument_names()?;
+        let _ = self.tokens.expect("{")?;
+
+        let mut body = Vec::new();
+        while !self.tokens.at("}") {
+            let (gate, token) = self.tokens.identifier("a gate")?;
+
+            if gate == "barrier" {
+                let _ = self.argument_names()?;
+                let _ = self.tokens.expect(";")?;
+                continue;
+            }
+
+            let call_params = self.parameters(&params)?;
+            let (_, num_qubits) = self.check_gate(&gate, &token, call_params.len())?;
+
+            let mut call_qubits = Vec::new();
+            for argument in self.argument_names()? {
+                let index = qubits.iter().position(|qubit| *qubit == argument).ok_or_else(|| {
+                    token.error(format!(
+                        "`{}` is not an argument of the gate `{}`",
+                        argument, name
+                    ))
+                })?;
+                if call_qubits.contains(&index) {
+                    return Err(token.error(format!(
+                        "the qubits of `{}` must be distinct",
+                        gate
+                    )));
+                }
+                call_qubits.push(index);
+            }
+            check_count(&gate, &token, num_qubits, call_qubits.len())?;
+            let _ = self.tokens.expect(";")?;
+
+            body.push(GateCall {
+                name: gate,
+                params: call_params,
+                qubits: call_qubits,
+            });
+        }
+        let _ = self.tokens.next();
+
+        let _ = self.definitions.insert(
+            name,
+            Definition {
+                num_params: params.len(),
+                num_qubits: qubits.len(),
+                body: Some(body),
+            },
+        );
+
+        Ok(())
+    }
+
+    fn opaque_definition(&mut self) -> Result<()> {
+        let (name, token) = self.tokens.identifier("a gate name")?;
+        self.check_unused(&name, &token)?;
+
+        let params = self.parameter_names()?;
+        let qubits = self.argument_names()?;
+        let _ = self.tokens.expect(";")?;
+
+        let _ = self.definitions.insert(
+            name,
+            Definition {
+                num_params: params.len(),
+                num_qubits: qubits.len(),
+                body: None,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// The number of parameters and qubits of a gate, if it is defined
+    fn gate_arity(&self, name: &str) -> Option<(usize, usize)> {
+        match name {
+            "U" => Some((3, 1)),
+            "CX" => Some((0, 2)),
+            _ => match self.definitions.get(name) {
+                Some(definition) => Some((definition.num_params, definition.num_qubits)),
+                None if self.included => standard::arity(name),
+                None => None,
+            },
+        }
+    }
+
+    /// Check that a gate is defined and given the right number of parameters,
+    /// and return its number of parameters and qubits
+    fn check_gate(&self, name: &str, token: &Spanned, num_params: usize) -> Result<(usize, usize)> {
+        let arity = match self.gate_arity(name) {
+            Some(arity) => arity,
+            None if standard::arity(name).is_some() => {
+                return Err(token.error(format!(
+                    "the gate `{}` is not defined, include \"qelib1.inc\" to use it",
+                    name
+                )))
+            }
+            None => return Err(token.error(format!("the gate `{}` is not defined", name))),
+        };
+
+        if arity.0 != num_params {
+            return Err(token.error(format!(
+                "the gate `{}` takes {} parameters, but {} were given",
+                name, arity.0, num_params
+            )));
+        }
+
+        Ok(arity)
+    }
+
+    /// A measurement, reset, or application of a gate
+    fn quantum_operation(&mut self, name: &str, token: &Spanned) -> Result<Vec<Operation>> {
+        match name {
+            "measure" => self.measurement(),
+            "reset" => Err(token.error("`reset` is not supported")),
+            _ => {
+                let params: Vec<Real> = self.parameters(&[])?
+                    .iter()
+                    .map(|param| param.evaluate(&[]))
+                    .collect();
+                let (_, num_qubits) = self.check_gate(name, token, params.len())?;
+
+                let arguments = self.qubit_arguments()?;
+                check_count(name, token, num_qubits, arguments.len())?;
+                let _ = self.tokens.expect(";")?;
+
+                let mut operations = Vec::new();
+                for qubits in broadcast(&arguments, token)? {
+                    let mut distinct = qubits.clone();
+                    distinct.sort();
+                    distinct.dedup();
+                    if distinct.len() != qubits.len() {
+                        return Err(token.error(format!("the qubits of `{}` must be distinct", name)));
+                    }
+
+                    self.expand(name, &params, &qubits, token, &mut operations)?;
+                }
+
+                Ok(operations)
+            }
+        }
+    }
+
+    /// Add the operations which apply a gate to `operations`
+    fn expand(
+        &self,
+        name: &str,
+        params: &[Real],
+        qubits: &[i32],
+        token: &Spanned,
+        operations: &mut Vec<Operation>,
+    ) -> Result<()> {
+        match name {
+            "U" => operations.push(Operation::Gate {
+                target: qubits[0],
+                gate: u3(params[0], params[1], params[2]),
+            }),
+            "CX" => operations.push(Operation::ControlledGate {
+                control: qubits[0],
+                target: qubits[1],
+                gate: x(),
+            }),
+            _ => match self.definitions.get(name) {
+                Some(&Definition {
+                    body: Some(ref body),
+                    ..
+                }) => for call in body {
+                    let call_params: Vec<Real> =
+                        call.params.iter().map(|param| param.evaluate(params)).collect();
+                    let call_qubits: Vec<i32> = call.qubits.iter().map(|&i| qubits[i]).collect();
+                    self.expand(&call.name, &call_params, &call_qubits, token, operations)?;
+                },
+                Some(_) => {
+                    return Err(token.error(format!(
+                        "the opaque gate `{}` can't be simulated",
+                        name
+                    )))
+                }
+                None => operations.extend(standard::operations(name, params, qubits)),
+            },
+        }
+
+        Ok(())
+    }
+
+    /// `measure a -> b;`, where `a` and `b` are both registers of the same size,
+    /// or a qubit and a bit
+    fn measurement(&mut self) -> Result<Vec<Operation>> {
+        let (qubits, qubit_token) = self.argument(true)?;
+        let _ = self.tokens.expect("->")?;
+        let (bits, _) = self.argument(false)?;
+        let _ = self.tokens.expect(";")?;
+
+        if qubits.len() != bits.len() {
+            return Err(qubit_token.error(format!(
+                "can't measure {} qubits into {} bits",
+                qubits.len(),
+                bits.len()
+            )));
+        }
+
+        Ok(qubits
+            .iter()
+            .zip(bits)
+            .map(|(&qubit, bit)| Operation::Measure {
+                qubit: qubit as i32,
+                bit,
+            })
+            .collect())
+    }
+
+    /// `if (c == value) operation`
+    fn conditional(&mut self) -> Result<()> {
+        let _ = self.tokens.expect("(")?;
+        let (name, token) = self.tokens.identifier("a classical register")?;
+        let register = match self.registers.get(&name) {
+            Some(register) if !register.quantum => *register,
+            Some(_) => return Err(token.error(format!("`{}` is not a classical register", name))),
+            None => return Err(token.error(format!("`{}` is not defined", name))),
+        };
+        if register.size > 64 {
+            return Err(token.error("can't compare a register of more than 64 bits"));
+        }
+
+        let _ = self.tokens.expect("==")?;
+        let (value, _) = self.tokens.integer()?;
+        let _ = self.tokens.expect(")")?;
+
+        let (operation, token) = self.tokens.identifier("a gate or measurement")?;
+        if ["barrier", "if", "gate", "opaque", "qreg", "creg", "include"].contains(&operation.as_str())
+        {
+            return Err(token.error(format!("`{}` can't be applied conditionally", operation)));
+        }
+
+        let operations = self.quantum_operation(&operation, &token)?;
+        self.operations.push(Operation::Conditional {
+            bits: register.start..register.start + register.size,
+            value,
+            operations,
+        });
+
+        Ok(())
+    }
+
+    /// A register, or an element of a register. Returns the indices of the qubits
+    /// or bits, and the token of the register name.
+    fn argument(&mut self, quantum: bool) -> Result<(Vec<usize>, Spanned)> {
+        let kind = if quantum { "quantum" } else { "classical" };
+        let (name, token) = self.tokens.identifier(&format!("a {} register", kind))?;
+
+        let register = match self.registers.get(&name) {
+            Some(register) if register.quantum == quantum => *register,
+            Some(_) => {
+                return Err(token.error(format!("`{}` is not a {} register", name, kind)))
+            }
+            None => return Err(token.error(format!("`{}` is not defined", name))),
+        };
+
+        if !self.tokens.at("[") {
+            return Ok(((register.start..register.start + register.size).collect(), token));
+        }
+
+        let _ = self.tokens.next();
+        let (index, index_token) = self.tokens.integer()?;
+        let _ = self.tokens.expect("]")?;
+
+        if index >= register.size as u64 {
+            return Err(index_token.error(format!(
+                "index {} is outside of the register `{}`, which has size {}",
+                index, name, register.size
+            )));
+        }
+
+        Ok((vec![register.start + index as usize], token))
+    }
+
+    /// A list of qubit arguments, separated by commas
+    fn qubit_arguments(&mut self) -> Result<Vec<Vec<i32>>> {
+        let mut arguments = Vec::new();
+        loop {
+            let (qubits, _) = self.argument(true)?;
+            arguments.push(qubits.iter().map(|&qubit| qubit as i32).collect());
+
+            if !self.tokens.at(",") {
+                return Ok(arguments);
+            }
+            let _ = self.tokens.next();
+        }
+    }
+
+    /// The parameters of a gate in parentheses, if there are any. `names` are
+    /// the names of the parameters of the enclosing gate definition.
+    fn parameters(&mut self, names: &[String]) -> Result<Vec<Expression>> {
+        let mut params = Vec::new();
+        if self.tokens.at("(") {
+            let _ = self.tokens.next();
+            while !self.tokens.at(")") {
+                if !params.is_empty() {
+                    let _ = self.tokens.expect(",")?;
+                }
+                params.push(self.sum(names)?);
+            }
+            let _ = self.tokens.next();
+        }
+
+        Ok(params)
+    }
+
+    /// Terms separated by `+` and `-`
+    fn sum(&mut self, names: &[String]) -> Result<Expression> {
+        let mut expression = self.product(names)?;
+        while self.tokens.at("+") || self.tokens.at("-") {
+            let op = if self.tokens.at("+") { "+" } else { "-" };
+            let _ = self.tokens.next();
+            expression = Expression::Binary(op, Box::new(expression), Box::new(self.product(names)?));
+        }
+
+        Ok(expression)
+    }
+
+    /// Factors separated by `*` and `/`
+    fn product(&mut self, names: &[String]) -> Result<Expression> {
+        let mut expression = self.unary(names)?;
+        while self.tokens.at("*") || self.tokens.at("/") {
+            let op = if self.tokens.at("*") { "*" } else { "/" };
+            let _ = self.tokens.next();
+            expression = Expression::Binary(op, Box::new(expression), Box::new(self.unary(names)?));
+        }
+
+        Ok(expression)
+    }
+
+    /// A negated expression, or a power
+    fn unary(&mut self, names: &[String]) -> Result<Expression> {
+        if self.tokens.at("-") {
+            let _ = self.tokens.next();
+            return Ok(Expression::Negate(Box::new(self.unary(names)?)));
+        }
+
+        let base = self.primary(names)?;
+        if self.tokens.at("^") {
+            let _ = self.tokens.next();
+            return Ok(Expression::Binary("^", Box::new(base), Box::new(self.unary(names)?)));
+        }
+
+        Ok(base)
+    }
+
+    /// A number, parameter, function call or parenthesised expression
+    fn primary(&mut self, names: &[String]) -> Result<Expression> {
+        let token = self.tokens.next();
+        match token.token {
+            Token::Integer(value) => Ok(Expression::Number(value as Real)),
+            Token::Real(ref text) => text.parse()
+                .map(Expression::Number)
+                .map_err(|_| token.error(format!("invalid number {}", text))),
+            Token::Identifier(ref name) if name == "pi" => Ok(Expression::Number(PI)),
+            Token::Identifier(ref name) => {
+                if let Some(index) = names.iter().position(|param| param == name) {
+                    return Ok(Expression::Parameter(index));
+                }
+
+                let function = function(name)
+                    .ok_or_else(|| token.error(format!("`{}` is not defined", name)))?;
+                let _ = self.tokens.expect("(")?;
+                let operand = self.sum(names)?;
+                let _ = self.tokens.expect(")")?;
+
+                Ok(Expression::Function(function, Box::new(operand)))
+            }
+            Token::Symbol("(") => {
+                let expression = self.sum(names)?;
+                let _ = self.tokens.expect(")")?;
+                Ok(expression)
+            }
+            _ => Err(token.error(format!("expected an expression, found {}", describe(&token)))),
+        }
+    }
+}
+
+/// Check that a gate is applied to the right number of qubits
+fn check_count(name: &str, token: &Spanned, num_qubits: usize, found: usize) -> Result<()> {
+    if num_qubits != found {
+        return Err(token.error(format!(
+            "the gate `{}` acts on {} qubits, but {} were given",
+            name, num_qubits, found
+        )));
+    }
+
+    Ok(())
+}
+
+/// The qubits of each application of a gate to its arguments. Registers
+/// apply the gate once for each of their qubits, and must be the same size.
+fn broadcast(arguments: &[Vec<i32>], token: &Spanned) -> Result<Vec<Vec<i32>>> {
+    let size = arguments.iter().map(Vec::len).max().unwrap_or(1);
+    if arguments.iter().any(|qubits| qubits.len() != 1 && qubits.len() != size) {
+        return Err(token.error("the registers a gate is applied to must be the same size"));
+    }
+
+    Ok((0..size)
+        .map(|i| {
+            arguments
+                .iter()
+                .map(|qubits| qubits[if qubits.len() == 1 { 0 } else { i }])
+                .collect()
+        })
+        .collect())
+}
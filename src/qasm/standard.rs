@@ -0,0 +1,142 @@
+//! The gates of the OpenQASM standard library, `qelib1.inc`, which are
+//! applied directly rather than expanded into their definitions.
+
+use circuit::Operation;
+use gates::{rx, ry, rz, u3, Gate};
+use gates::{h, id, r, s, sdg, sqrt_x, t, tdg, x, xx, y, z, zz};
+use precision::Real;
+use precision::consts::FRAC_PI_2;
+
+/// The name, number of parameters and number of qubits of each gate in `qelib1.inc`
+pub(crate) const STANDARD_GATES: [(&str, usize, usize); 35] = [
+    ("u3", 3, 1),
+    ("u2", 2, 1),
+    ("u1", 1, 1),
+    ("u", 3, 1),
+    ("p", 1, 1),
+    ("cx", 0, 2),
+    ("id", 0, 1),
+    ("x", 0, 1),
+    ("y", 0, 1),
+    ("z", 0, 1),
+    ("h", 0, 1),
+    ("s", 0, 1),
+    ("sdg", 0, 1),
+    ("t", 0, 1),
+    ("tdg", 0, 1),
+    ("sx", 0, 1),
+    ("sxdg", 0, 1),
+    ("rx", 1, 1),
+    ("ry", 1, 1),
+    ("rz", 1, 1),
+    ("cz", 0, 2),
+    ("cy", 0, 2),
+    ("ch", 0, 2),
+    ("csx", 0, 2),
+    ("crx", 1, 2),
+    ("cry", 1, 2),
+    ("crz", 1, 2),
+    ("cu1", 1, 2),
+    ("cp", 1, 2),
+    ("cu3", 3, 2),
+    ("swap", 0, 2),
+    ("rxx", 1, 2),
+    ("rzz", 1, 2),
+    ("ccx", 0, 3),
+    ("cswap", 0, 3),
+];
+
+/// The number of parameters and qubits of a gate in `qelib1.inc`
+pub(crate) fn arity(name: &str) -> Option<(usize, usize)> {
+    STANDARD_GATES
+        .iter()
+        .find(|gate| gate.0 == name)
+        .map(|&(_, num_params, num_qubits)| (num_params, num_qubits))
+}
+
+/// The operations which apply a gate from `qelib1.inc`. The number of
+/// parameters and qubits must match `arity`.
+pub(crate) fn operations(name: &str, params: &[Real], qubits: &[i32]) -> Vec<Operation> {
+    if let Some(gate) = single_qubit_gate(name, params) {
+        return vec![Operation::Gate {
+            target: qubits[0],
+            gate,
+        }];
+    }
+
+    // The controlled gates are the single qubit gate without the leading c
+    if let Some(gate) = single_qubit_gate(&name[1..], params) {
+        return vec![Operation::ControlledGate {
+            control: qubits[0],
+            target: qubits[1],
+            gate,
+        }];
+    }
+
+    match name {
+        "swap" => vec![Operation::Swap {
+            first: qubits[0],
+            second: qubits[1],
+        }],
+        "rxx" => vec![Operation::TwoQubitGate {
+            qubit0: qubits[0],
+            qubit1: qubits[1],
+            gate: xx(params[0]),
+        }],
+        "rzz" => vec![Operation::TwoQubitGate {
+            qubit0: qubits[0],
+            qubit1: qubits[1],
+            gate: zz(params[0]),
+        }],
+        "ccx" => vec![Operation::Toffoli {
+            control1: qubits[0],
+            control2: qubits[1],
+            target: qubits[2],
+        }],
+        "cswap" => {
+            let cx = Operation::ControlledGate {
+                control: qubits[2],
+                target: qubits[1],
+                gate: x(),
+            };
+
+            vec![
+                cx.clone(),
+                Operation::Toffoli {
+                    control1: qubits[0],
+                    control2: qubits[1],
+                    target: qubits[2],
+                },
+                cx,
+            ]
+        }
+        _ => unreachable!("{} is not a standard gate", name),
+    }
+}
+
+/// The single qubit gates, which also give the controlled gates `cx`, `cy`,
+/// `cz`, `ch`, `csx`, `crx`, `cry`, `crz`, `cu1`, `cp` and `cu3`
+fn single_qubit_gate(name: &str, params: &[Real]) -> Option<Gate> {
+    let gate = match name {
+        "u3" | "u" => u3(params[0], params[1], params[2]),
+        "u2" => u3(FRAC_PI_2, params[0], params[1]),
+        "u1" | "p" => r(params[0]),
+        "id" => id(),
+        "x" => x(),
+        "y" => y(),
+        "z" => z(),
+        "h" => h(),
+        "s" => s(),
+        "sdg" => sdg(),
+        "t" => t(),
+        "tdg" => tdg(),
+        "sx" => sqrt_x(),
+        "sxdg" => sqrt_x().adjoint(),
+        "rx" => rx(params[0]),
+        "ry" => ry(params[0]),
+        "rz" => rz(params[0]),
+        _ => return None,
+    };
+
+    Some(gate)
+}
@@ -0,0 +1,450 @@
+//! Runs a parsed OpenQASM 3 program on a register
+//!
+//! Each gate statement is expanded into circuit operations and applied as it
+//! is reached, so measurements can decide which statements run next.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use circuit::{Circuit, Operation};
+use error::Result;
+use gates::{global_phase, u3, x};
+use precision::Real;
+use qasm::lexer::Spanned;
+use qasm::standard;
+use state::State;
+use super::parser::{BitArgument, Expression, QubitArgument, Statement, Values};
+use super::Program;
+
+/// The value of a classical expression
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Int(i64),
+    Float(Real),
+}
+
+impl Value {
+    fn real(self) -> Real {
+        match self {
+            Value::Int(value) => value as Real,
+            Value::Float(value) => value,
+        }
+    }
+
+    /// Whether the value is non-zero
+    fn truth(self) -> bool {
+        match self {
+            Value::Int(value) => value != 0,
+            Value::Float(value) => value != 0.0,
+        }
+    }
+
+    /// The value as an integer, or an error at `token` if it has a fractional part
+    fn integer(self, token: &Spanned) -> Result<i64> {
+        match self {
+            Value::Int(value) => Ok(value),
+            Value::Float(value) if value.fract() == 0.0 && value.abs() < 9.0e18 => {
+                Ok(value as i64)
+            }
+            Value::Float(value) => Err(token.error(format!("{} is not an integer", value))),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Value {
+        Value::Int(i64::from(value))
+    }
+}
+
+/// Run a program on a register which has at least as many qubits as it declares,
+/// returning the final values of its bit registers
+pub(crate) fn run(program: &Program, state: &mut State) -> Result<HashMap<String, Vec<bool>>> {
+    let mut interpreter = Interpreter {
+        program,
+        state,
+        bits: program
+            .registers
+            .iter()
+            .map(|&(ref name, size)| (name.clone(), vec![false; size]))
+            .collect(),
+        variables: HashMap::new(),
+    };
+
+    interpreter.block(&program.statements)?;
+
+    Ok(interpreter.bits)
+}
+
+struct Interpreter<'a> {
+    program: &'a Program,
+    state: &'a mut State,
+    bits: HashMap<String, Vec<bool>>,
+    /// The values of the loop variables in scope
+    variables: HashMap<String, i64>,
+}
+
+impl<'a> Interpreter<'a> {
+    fn block(&mut self, statements: &[Statement]) -> Result<()> {
+        statements
+            .iter()
+            .try_for_each(|statement| self.statement(statement))
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<()> {
+        match *statement {
+            Statement::Gate {
+                ref name,
+                ref params,
+                ref qubits,
+                ref token,
+            } => {
+                let params = params
+                    .iter()
+                    .map(|param| self.evaluate(param, &[]).map(Value::real))
+                    .collect::<Result<Vec<Real>>>()?;
+                let arguments = qubits
+                    .iter()
+                    .map(|qubit| self.qubits(qubit))
+                    .collect::<Result<Vec<Vec<i32>>>>()?;
+
+                let mut circuit = Circuit::new(self.state.num_qubits);
+                for qubits in broadcast(&arguments) {
+                    let mut distinct = qubits.clone();
+                    distinct.sort();
+                    distinct.dedup();
+                    if distinct.len() != qubits.len() {
+                        return Err(token.error(format!("the qubits of `{}` must be distinct", name)));
+                    }
+
+                    let mut operations = Vec::new();
+                    self.expand(name, &params, &qubits, &mut operations)?;
+                    for operation in operations {
+                        let _ = circuit.push(operation);
+                    }
+                }
+
+                self.state.try_run(&circuit).map(|_| ())
+            }
+            Statement::Measure {
+                ref qubits,
+                ref bits,
+            } => {
+                let qubits = self.qubits(qubits)?;
+                let indices = match *bits {
+                    Some(ref bits) => self.bits(bits)?,
+                    None => Vec::new(),
+                };
+
+                for (i, &qubit) in qubits.iter().enumerate() {
+                    let outcome = self.state.try_measure_qubit(qubit)?;
+                    if let Some(ref bits) = *bits {
+                        self.set_bit(&bits.name, indices[i], outcome);
+                    }
+                }
+
+                Ok(())
+            }
+            Statement::Reset(ref qubits) => {
+                for qubit in self.qubits(qubits)? {
+                    if self.state.try_measure_qubit(qubit)? {
+                        self.state.try_apply_gate(qubit, x())?;
+                    }
+                }
+
+                Ok(())
+            }
+            Statement::Assign {
+                ref bits,
+                ref value,
+            } => {
+                let value = self.evaluate(value, &[])?;
+                let indices = self.bits(bits)?;
+
+                if bits.index.is_some() {
+                    self.set_bit(&bits.name, indices[0], value.truth());
+                } else {
+                    let value = value.integer(&bits.token)?;
+                    for (i, &index) in indices.iter().enumerate() {
+                        let bit = i < 64 && (value >> i) & 1 == 1;
+                        self.set_bit(&bits.name, index, bit);
+                    }
+                }
+
+                Ok(())
+            }
+            Statement::If {
+                ref condition,
+                ref then,
+                ref otherwise,
+            } => {
+                if self.evaluate(condition, &[])?.truth() {
+                    self.block(then)
+                } else {
+                    self.block(otherwise)
+                }
+            }
+            Statement::For {
+                ref variable,
+                ref values,
+                ref body,
+            } => {
+                for value in self.values(values)? {
+                    let _ = self.variables.insert(variable.clone(), value);
+                    let result = self.block(body);
+                    if result.is_err() {
+                        let _ = self.variables.remove(variable);
+                        return result;
+                    }
+                }
+
+                let _ = self.variables.remove(variable);
+                Ok(())
+            }
+            Statement::While {
+                ref condition,
+                ref body,
+            } => {
+                while self.evaluate(condition, &[])?.truth() {
+                    self.block(body)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn set_bit(&mut self, register: &str, index: usize, value: bool) {
+        if let Some(bits) = self.bits.get_mut(register) {
+            bits[index] = value;
+        }
+    }
+
+    /// The index of an element of a register, checked against its size
+    fn index(&self, index: &Expression, size: usize, token: &Spanned) -> Result<usize> {
+        let value = self.evaluate(index, &[])?.integer(token)?;
+        if value < 0 || value as u64 >= size as u64 {
+            return Err(token.error(format!(
+                "index {} is outside of a register of size {}",
+                value, size
+            )));
+        }
+
+        Ok(value as usize)
+    }
+
+    fn qubits(&self, argument: &QubitArgument) -> Result<Vec<i32>> {
+        Ok(match argument.index {
+            Some(ref index) => {
+                vec![(argument.start + self.index(index, argument.size, &argument.token)?) as i32]
+            }
+            None => (argument.start..argument.start + argument.size)
+                .map(|qubit| qubit as i32)
+                .collect(),
+        })
+    }
+
+    /// The indices of the bits of the register an argument refers to
+    fn bits(&self, argument: &BitArgument) -> Result<Vec<usize>> {
+        Ok(match argument.index {
+            Some(ref index) => vec![self.index(index, argument.size, &argument.token)?],
+            None => (0..argument.size).collect(),
+        })
+    }
+
+    /// The values of a `for` loop
+    fn values(&self, values: &Values) -> Result<Vec<i64>> {
+        match *values {
+            Values::Range {
+                ref start,
+                ref step,
+                ref end,
+                ref token,
+            } => {
+                let start = self.evaluate(start, &[])?.integer(token)?;
+                let end = self.evaluate(end, &[])?.integer(token)?;
+                let step = match *step {
+                    Some(ref step) => self.evaluate(step, &[])?.integer(token)?,
+                    None => 1,
+                };
+                if step == 0 {
+                    return Err(token.error("the step of a range can't be 0"));
+                }
+
+                let mut values = Vec::new();
+                let mut value = start;
+                while (step > 0 && value <= end) || (step < 0 && value >= end) {
+                    values.push(value);
+                    value = match value.checked_add(step) {
+                        Some(value) => value,
+                        None => break,
+                    };
+                }
+
+                Ok(values)
+            }
+            Values::List {
+                ref values,
+                ref token,
+            } => values
+                .iter()
+                .map(|value| self.evaluate(value, &[])?.integer(token))
+                .collect(),
+        }
+    }
+
+    /// Evaluate an expression, given the parameters of the gate definition it is in
+    fn evaluate(&self, expression: &Expression, params: &[Real]) -> Result<Value> {
+        Ok(match *expression {
+            Expression::Int(value) => Value::Int(value),
+            Expression::Float(value) => Value::Float(value),
+            Expression::Parameter(index) => Value::Float(params[index]),
+            Expression::Variable(ref name) => Value::Int(self.variables[name]),
+            Expression::Register(ref name, ref token) => {
+                let bits = &self.bits[name];
+                if bits.len() > 63 {
+                    return Err(token.error(format!(
+                        "`{}` has more than 63 bits, so it can't be used as an integer",
+                        name
+                    )));
+                }
+
+                Value::Int(
+                    bits.iter()
+                        .rev()
+                        .fold(0, |value, &bit| (value << 1) | i64::from(bit)),
+                )
+            }
+            Expression::Bit(ref name, ref index, ref token) => {
+                let bits = &self.bits[name];
+                Value::from(bits[self.index(index, bits.len(), token)?])
+            }
+            Expression::Unary(op, ref operand) => {
+                let operand = self.evaluate(operand, params)?;
+                match (op, operand) {
+                    ("!", operand) => Value::from(!operand.truth()),
+                    (_, Value::Int(value)) => Value::Int(value.wrapping_neg()),
+                    (_, Value::Float(value)) => Value::Float(-value),
+                }
+            }
+            Expression::Binary(op, ref left, ref right, ref token) => {
+                let left = self.evaluate(left, params)?;
+                let right = self.evaluate(right, params)?;
+                binary(op, left, right, token)?
+            }
+            Expression::Function(function, ref operand) => {
+                Value::Float(function(self.evaluate(operand, params)?.real()))
+            }
+        })
+    }
+
+    /// Add the operations which apply a gate to `operations`
+    fn expand(
+        &self,
+        name: &str,
+        params: &[Real],
+        qubits: &[i32],
+        operations: &mut Vec<Operation>,
+    ) -> Result<()> {
+        match name {
+            "U" => operations.push(Operation::Gate {
+                target: qubits[0],
+                gate: u3(params[0], params[1], params[2]),
+            }),
+            // A global phase has no effect, as gates can't be controlled
+            "gphase" => {}
+            _ => match self.program.definitions.get(name) {
+                Some(definition) => for call in &definition.body {
+                    let call_params = call.params
+                        .iter()
+                        .map(|param| self.evaluate(param, params).map(Value::real))
+                        .collect::<Result<Vec<Real>>>()?;
+                    let call_qubits: Vec<i32> = call.qubits.iter().map(|&i| qubits[i]).collect();
+                    self.expand(&call.name, &call_params, &call_qubits, operations)?;
+                },
+                None => standard_operations(name, params, qubits, operations),
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// Add the operations which apply a gate of `stdgates.inc`
+fn standard_operations(name: &str, params: &[Real], qubits: &[i32], operations: &mut Vec<Operation>) {
+    let name = match name {
+        "cu" => {
+            return operations.push(Operation::ControlledGate {
+                control: qubits[0],
+                target: qubits[1],
+                gate: global_phase(params[3]) * u3(params[0], params[1], params[2]),
+            })
+        }
+        "CX" => "cx",
+        "phase" => "p",
+        "cphase" => "cp",
+        name => name,
+    };
+
+    operations.extend(standard::operations(name, params, qubits));
+}
+
+/// Apply a binary operator. Arithmetic on integers gives integers, except
+/// for division, which always gives a real.
+fn binary(op: &str, left: Value, right: Value, token: &Spanned) -> Result<Value> {
+    let ordering = match (left, right) {
+        (Value::Int(l), Value::Int(r)) => Some(l.cmp(&r)),
+        _ => left.real().partial_cmp(&right.real()),
+    };
+
+    Ok(match op {
+        "||" => Value::from(left.truth() || right.truth()),
+        "&&" => Value::from(left.truth() && right.truth()),
+        "==" => Value::from(ordering == Some(Ordering::Equal)),
+        "!=" => Value::from(ordering != Some(Ordering::Equal)),
+        "<" => Value::from(ordering == Some(Ordering::Less)),
+        ">" => Value::from(ordering == Some(Ordering::Greater)),
+        "<=" => Value::from(ordering == Some(Ordering::Less) || ordering == Some(Ordering::Equal)),
+        ">=" => Value::from(ordering == Some(Ordering::Greater) || ordering == Some(Ordering::Equal)),
+        "/" => Value::Float(left.real() / right.real()),
+        _ => match (left, right) {
+            (Value::Int(l), Value::Int(r)) => match op {
+                "+" => Value::Int(l.wrapping_add(r)),
+                "-" => Value::Int(l.wrapping_sub(r)),
+                "*" => Value::Int(l.wrapping_mul(r)),
+                "%" if r == 0 => return Err(token.error("division by zero")),
+                "%" => Value::Int(l.wrapping_rem(r)),
+                _ if r >= 0 && r <= i64::from(u32::MAX) => match l.checked_pow(r as u32) {
+                    Some(power) => Value::Int(power),
+                    None => Value::Float(left.real().powf(right.real())),
+                },
+                _ => Value::Float(left.real().powf(right.real())),
+            },
+            _ => {
+                let (l, r) = (left.real(), right.real());
+                Value::Float(match op {
+                    "+" => l + r,
+                    "-" => l - r,
+                    "*" => l * r,
+                    "%" => l % r,
+                    _ => l.powf(r),
+                })
+            }
+        },
+    })
+}
+
+/// The qubits of each application of a gate to its arguments, where the
+/// arguments which are registers all have the same size
+fn broadcast(arguments: &[Vec<i32>]) -> Vec<Vec<i32>> {
+    let size = arguments.iter().map(Vec::len).max().unwrap_or(1);
+
+    (0..size)
+        .map(|i| {
+            arguments
+                .iter()
+                .map(|qubits| qubits[if qubits.len() == 1 { 0 } else { i }])
+                .collect()
+        })
+        .collect()
+}
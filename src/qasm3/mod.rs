@@ -0,0 +1,125 @@
+//! OpenQASM 3
+//!
+//! Runs programs written in a subset of [OpenQASM 3](https://openqasm.com)
+//! directly on a register. Unlike a `Circuit`, a program can decide what to
+//! do next from the outcomes of measurements, so dynamic circuits such as
+//! repeat-until-success can be simulated.
+//!
+//! ```
+//!# extern crate qcgpu;
+//!# use qcgpu::qasm3::Program;
+//!# use qcgpu::backends::Cpu;
+//! // Flip a coin until it lands on 1, then copy it to a second qubit
+//! let program = Program::parse(r#"
+//!     OPENQASM 3;
+//!     include "stdgates.inc";
+//!
+//!     qubit[2] q;
+//!     bit[2] c;
+//!
+//!     c[0] = 0;
+//!     while (c[0] == 0) {
+//!         reset q[0];
+//!         h q[0];
+//!         c[0] = measure q[0];
+//!     }
+//!     if (c[0]) {
+//!         x q[1];
+//!     } else {
+//!         z q[1];
+//!     }
+//!     c[1] = measure q[1];
+//! "#).unwrap();
+//!
+//! let (_, bits) = program.execute(Cpu::new()).unwrap();
+//! assert_eq!(bits["c"], vec![true, true]);
+//! ```
+//!
+//! The supported subset is:
+//!
+//! * `qubit` and `bit` declarations, and the older `qreg` and `creg`, outside
+//!   of any block. Registers are laid out in the order they are declared.
+//! * The gates of `stdgates.inc`, the built in `U` and `gphase`, and `gate`
+//!   definitions with parameters
+//! * `measure`, `reset` and `barrier`
+//! * Assigning to bits, as in `c = 5;`, `c[0] = measure q[0];` or `c = measure q;`
+//! * `if` and `else`, `for` loops over ranges such as `[0:2:10]` or sets such
+//!   as `{1, 4, 9}`, and `while` loops
+//! * Expressions of integers, reals, bits, loop variables and the constants
+//!   `pi`, `tau` and `euler`, with the arithmetic, comparison and logical
+//!   operators, and the functions `sin`, `cos`, `tan`, `arcsin`, `arccos`,
+//!   `arctan`, `exp`, `ln` and `sqrt`. Division always gives a real.
+//!
+//! Other constructs, such as classical variables, subroutines and gate
+//! modifiers, give an `Error::Parse` with the line and column where they are used.
+
+mod interpreter;
+mod parser;
+
+use std::collections::HashMap;
+
+use backends::Backend;
+use error::{Error, Result};
+use state::State;
+use self::parser::{Definition, Statement};
+
+/// A parsed OpenQASM 3 program
+#[derive(Debug, Clone)]
+pub struct Program {
+    num_qubits: u32,
+    /// The name and size of each bit register, in the order they are declared
+    registers: Vec<(String, usize)>,
+    definitions: HashMap<String, Definition>,
+    statements: Vec<Statement>,
+}
+
+impl Program {
+    /// Parse an OpenQASM 3 program.
+    ///
+    /// Returns `Error::Parse`, with the line and column of the problem, if the
+    /// program is invalid or uses a construct which isn't supported.
+    pub fn parse(source: &str) -> Result<Program> {
+        parser::parse(source)
+    }
+
+    /// The number of qubits the program declares
+    pub fn num_qubits(&self) -> u32 {
+        self.num_qubits
+    }
+
+    /// Run the program on a register, where the qubits it declares are the
+    /// first qubits of the register
+    ///
+    /// Returns the final value of each bit register, by name. The bits start
+    /// out false.
+    ///
+    /// Returns an error if the register has fewer qubits than the program
+    /// declares, or the backend fails. Problems which depend on the values
+    /// computed while running, such as an index outside of a register, give
+    /// an `Error::Parse` at the expression, and leave the register as it was
+    /// when the problem was found.
+    pub fn run(&self, state: &mut State) -> Result<HashMap<String, Vec<bool>>> {
+        if state.num_qubits < self.num_qubits {
+            return Err(Error::NotEnoughQubits {
+                requested: self.num_qubits,
+                num_qubits: state.num_qubits,
+            });
+        }
+
+        interpreter::run(self, state)
+    }
+
+    /// Run the program on a new register, stored on `backend`, with as many
+    /// qubits as the program declares
+    ///
+    /// Returns the register, and the values of the bit registers. See `run`.
+    pub fn execute<B: Backend + 'static>(
+        &self,
+        backend: B,
+    ) -> Result<(State, HashMap<String, Vec<bool>>)> {
+        let mut state = State::try_with_backend(self.num_qubits, backend)?;
+        let bits = self.run(&mut state)?;
+
+        Ok((state, bits))
+    }
+}
@@ -0,0 +1,931 @@
+//! Parses the supported subset of OpenQASM 3 into statements, resolving each
+//! name to the register, loop variable or gate it refers to, so the errors
+//! that can be found without running the program are reported up front.
+
+use std::collections::HashMap;
+
+use error::Result;
+use precision::Real;
+use precision::consts::{E, PI};
+use qasm::lexer::{describe, tokenize, Cursor, Spanned, Token};
+use qasm::standard;
+use super::Program;
+
+/// The most qubits a program can declare
+const MAX_QUBITS: usize = 64;
+
+/// The gates of `stdgates.inc`
+const STANDARD_GATES: [&str; 32] = [
+    "p", "x", "y", "z", "h", "s", "sdg", "t", "tdg", "sx", "rx", "ry", "rz", "cx", "cy", "cz", "cp",
+    "crx", "cry", "crz", "ch", "swap", "ccx", "cswap", "cu", "CX", "phase", "cphase", "id", "u1",
+    "u2", "u3",
+];
+
+/// The keywords of OpenQASM 3 outside of the supported subset
+const UNSUPPORTED: [&str; 23] = [
+    "int", "uint", "float", "angle", "bool", "complex", "duration", "stretch", "const", "input",
+    "output", "def", "defcal", "cal", "box", "let", "break", "continue", "return", "end",
+    "opaque", "delay", "switch",
+];
+
+/// A classical expression
+#[derive(Debug, Clone)]
+pub(crate) enum Expression {
+    Int(i64),
+    Float(Real),
+    /// The parameter of the enclosing gate definition with the given index
+    Parameter(usize),
+    /// A loop variable
+    Variable(String),
+    /// The value of a bit register, where its first bit is the lowest
+    Register(String, Spanned),
+    /// A bit of a register
+    Bit(String, Box<Expression>, Spanned),
+    Unary(&'static str, Box<Expression>),
+    Binary(&'static str, Box<Expression>, Box<Expression>, Spanned),
+    Function(fn(Real) -> Real, Box<Expression>),
+}
+
+/// A qubit register, or one qubit of it
+#[derive(Debug, Clone)]
+pub(crate) struct QubitArgument {
+    pub(crate) start: usize,
+    pub(crate) size: usize,
+    pub(crate) index: Option<Expression>,
+    pub(crate) token: Spanned,
+}
+
+/// A bit register, or one bit of it
+#[derive(Debug, Clone)]
+pub(crate) struct BitArgument {
+    pub(crate) name: String,
+    pub(crate) size: usize,
+    pub(crate) index: Option<Expression>,
+    pub(crate) token: Spanned,
+}
+
+/// The number of qubits or bits an argument refers to
+fn width(size: usize, index: &Option<Expression>) -> usize {
+    if index.is_some() {
+        1
+    } else {
+        size
+    }
+}
+
+/// The values a `for` loop iterates over
+#[derive(Debug, Clone)]
+pub(crate) enum Values {
+    /// `[start:end]` or `[start:step:end]`, which includes the end
+    Range {
+        start: Box<Expression>,
+        step: Option<Box<Expression>>,
+        end: Box<Expression>,
+        token: Spanned,
+    },
+    /// `{a, b, c}`
+    List {
+        values: Vec<Expression>,
+        token: Spanned,
+    },
+}
+
+/// A statement which does something when the program is run
+#[derive(Debug, Clone)]
+pub(crate) enum Statement {
+    Gate {
+        name: String,
+        params: Vec<Expression>,
+        qubits: Vec<QubitArgument>,
+        token: Spanned,
+    },
+    Measure {
+        qubits: QubitArgument,
+        bits: Option<BitArgument>,
+    },
+    Reset(QubitArgument),
+    Assign {
+        bits: BitArgument,
+        value: Expression,
+    },
+    If {
+        condition: Expression,
+        then: Vec<Statement>,
+        otherwise: Vec<Statement>,
+    },
+    For {
+        variable: String,
+        values: Values,
+        body: Vec<Statement>,
+    },
+    While {
+        condition: Expression,
+        body: Vec<Statement>,
+    },
+}
+
+/// A gate applied in the body of a gate definition
+#[derive(Debug, Clone)]
+pub(crate) struct GateCall {
+    pub(crate) name: String,
+    pub(crate) params: Vec<Expression>,
+    /// The indices of the qubit arguments of the enclosing definition
+    pub(crate) qubits: Vec<usize>,
+}
+
+/// A gate declared with `gate`
+#[derive(Debug, Clone)]
+pub(crate) struct Definition {
+    pub(crate) num_params: usize,
+    pub(crate) num_qubits: usize,
+    pub(crate) body: Vec<GateCall>,
+}
+
+/// What a name refers to
+#[derive(Debug, Clone, Copy)]
+enum Symbol {
+    Qubits { start: usize, size: usize },
+    Bits(usize),
+    Variable,
+}
+
+/// Parse an OpenQASM 3 program
+pub(crate) fn parse(source: &str) -> Result<Program> {
+    let mut parser = Parser {
+        tokens: Cursor::new(tokenize(source)?),
+        symbols: HashMap::new(),
+        num_qubits: 0,
+        registers: Vec::new(),
+        definitions: HashMap::new(),
+        included: false,
+        depth: 0,
+        gate_params: None,
+    };
+
+    let statements = parser.program()?;
+
+    Ok(Program {
+        num_qubits: parser.num_qubits as u32,
+        registers: parser.registers,
+        definitions: parser.definitions,
+        statements,
+    })
+}
+
+struct Parser {
+    tokens: Cursor,
+    symbols: HashMap<String, Symbol>,
+    num_qubits: usize,
+    /// The name and size of each bit register, in the order they are declared
+    registers: Vec<(String, usize)>,
+    definitions: HashMap<String, Definition>,
+    /// Whether `stdgates.inc` has been included, so the standard gates are defined
+    included: bool,
+    /// The depth of the blocks being parsed, as declarations must be outside of them
+    depth: usize,
+    /// The parameter names of the gate definition being parsed, if any
+    gate_params: Option<Vec<String>>,
+}
+
+impl Parser {
+    fn program(&mut self) -> Result<Vec<Statement>> {
+        let (keyword, token) = self.tokens.identifier("`OPENQASM 3;`")?;
+        if keyword != "OPENQASM" {
+            return Err(token.error("the file must start with `OPENQASM 3;`"));
+        }
+
+        let version = self.tokens.next();
+        match version.token {
+            Token::Integer(3) => {}
+            Token::Real(ref text) if text.starts_with("3.") => {}
+            _ => {
+                return Err(version.error(format!(
+                    "only OpenQASM 3 is supported, not {}",
+                    describe(&version)
+                )))
+            }
+        }
+        let _ = self.tokens.expect(";")?;
+
+        let mut statements = Vec::new();
+        while self.tokens.peek().token != Token::End {
+            if let Some(statement) = self.statement()? {
+                statements.push(statement);
+            }
+        }
+
+        Ok(statements)
+    }
+
+    /// Parse a statement. Declarations are recorded by the parser, so they
+    /// give `None`.
+    fn statement(&mut self) -> Result<Option<Statement>> {
+        let (name, token) = self.tokens.identifier("a statement")?;
+
+        let statement = match name.as_str() {
+            "include" => {
+                self.check_top_level(&token)?;
+                self.include()?;
+                None
+            }
+            "qubit" | "qreg" | "bit" | "creg" => {
+                self.check_top_level(&token)?;
+                self.declaration(&name)?
+            }
+            "gate" => {
+                self.check_top_level(&token)?;
+                self.gate_definition()?;
+                None
+            }
+            "barrier" => {
+                while !self.tokens.at(";") {
+                    let _ = self.qubit_argument()?;
+                    if !self.tokens.at(";") {
+                        let _ = self.tokens.expect(",")?;
+                    }
+                }
+                let _ = self.tokens.next();
+                None
+            }
+            "measure" => {
+                let qubits = self.qubit_argument()?;
+                let bits = if self.tokens.at("->") {
+                    let _ = self.tokens.next();
+                    let bits = self.bit_argument()?;
+                    check_widths(&qubits, &bits)?;
+                    Some(bits)
+                } else {
+                    None
+                };
+                let _ = self.tokens.expect(";")?;
+                Some(Statement::Measure { qubits, bits })
+            }
+            "reset" => {
+                let qubits = self.qubit_argument()?;
+                let _ = self.tokens.expect(";")?;
+                Some(Statement::Reset(qubits))
+            }
+            "if" => {
+                let condition = self.condition()?;
+                let then = self.block()?;
+                let otherwise = if self.tokens.at_identifier("else") {
+                    let _ = self.tokens.next();
+                    self.block()?
+                } else {
+                    Vec::new()
+                };
+                Some(Statement::If {
+                    condition,
+                    then,
+                    otherwise,
+                })
+            }
+            "for" => Some(self.for_loop()?),
+            "while" => {
+                let condition = self.condition()?;
+                let body = self.block()?;
+                Some(Statement::While { condition, body })
+            }
+            "OPENQASM" => {
+                return Err(token.error("the version can only be given at the start of the file"))
+            }
+            _ if UNSUPPORTED.contains(&name.as_str()) => {
+                return Err(token.error(format!("`{}` is not supported", name)))
+            }
+            _ => match self.symbols.get(&name).cloned() {
+                Some(Symbol::Bits(size)) => Some(self.assignment(name, size, token)?),
+                Some(_) => return Err(token.error(format!("`{}` can't be assigned to", name))),
+                None => Some(self.gate_call(name, token)?),
+            },
+        };
+
+        Ok(statement)
+    }
+
+    fn check_top_level(&self, token: &Spanned) -> Result<()> {
+        if self.depth > 0 {
+            return Err(token.error(format!(
+                "{} must be outside of any block",
+                describe(token)
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Check that a name isn't already used by a register, variable or gate
+    fn check_unused(&self, name: &str, token: &Spanned) -> Result<()> {
+        if self.symbols.contains_key(name) || self.gate_arity(name).is_some() {
+            return Err(token.error(format!("`{}` is already defined", name)));
+        }
+
+        Ok(())
+    }
+
+    /// A block in braces, or a single statement
+    fn block(&mut self) -> Result<Vec<Statement>> {
+        self.depth += 1;
+
+        let mut statements = Vec::new();
+        if self.tokens.at("{") {
+            let _ = self.tokens.next();
+            while !self.tokens.at("}") {
+                if let Some(statement) = self.statement()? {
+                    statements.push(statement);
+                }
+            }
+            let _ = self.tokens.next();
+        } else if let Some(statement) = self.statement()? {
+            statements.push(statement);
+        }
+
+        self.depth -= 1;
+        Ok(statements)
+    }
+
+    fn include(&mut self) -> Result<()> {
+        let file = self.tokens.next();
+        match file.token {
+            Token::Str(ref name) if name == "stdgates.inc" => self.included = true,
+            Token::Str(_) => return Err(file.error("only \"stdgates.inc\" can be included")),
+            _ => {
+                return Err(file.error(format!(
+                    "expected a file name, found {}",
+                    describe(&file)
+                )))
+            }
+        }
+
+        let _ = self.tokens.expect(";")?;
+        Ok(())
+    }
+
+    /// `qubit[n] q;`, `bit[n] c;`, and the older `qreg q[n];` and `creg c[n];`.
+    /// A bit register can be initialised, as in `bit[2] c = measure q;`.
+    fn declaration(&mut self, keyword: &str) -> Result<Option<Statement>> {
+        let quantum = keyword == "qubit" || keyword == "qreg";
+
+        let mut size = None;
+        if (keyword == "qubit" || keyword == "bit") && self.tokens.at("[") {
+            size = Some(self.size()?);
+        }
+        let (name, token) = self.tokens.identifier("a register name")?;
+        self.check_unused(&name, &token)?;
+        if keyword == "qreg" || keyword == "creg" {
+            size = Some(self.size()?);
+        }
+        let size = size.unwrap_or(1);
+
+        if quantum {
+            if size > MAX_QUBITS - self.num_qubits {
+                return Err(token.error(format!(
+                    "a program can't have more than {} qubits",
+                    MAX_QUBITS
+                )));
+            }
+
+            let _ = self.symbols.insert(
+                name,
+                Symbol::Qubits {
+                    start: self.num_qubits,
+                    size,
+                },
+            );
+            self.num_qubits += size;
+            let _ = self.tokens.expect(";")?;
+            return Ok(None);
+        }
+
+        let _ = self.symbols.insert(name.clone(), Symbol::Bits(size));
+        self.registers.push((name.clone(), size));
+
+        if self.tokens.at("=") {
+            return self.assignment(name, size, token).map(Some);
+        }
+        let _ = self.tokens.expect(";")?;
+        Ok(None)
+    }
+
+    /// The size of a register, in brackets
+    fn size(&mut self) -> Result<usize> {
+        let _ = self.tokens.expect("[")?;
+        let (size, token) = self.tokens.integer()?;
+        let _ = self.tokens.expect("]")?;
+
+        if size == 0 || size > u64::from(u32::MAX) {
+            return Err(token.error(format!("a register can't have {} bits", size)));
+        }
+
+        Ok(size as usize)
+    }
+
+    /// `c = measure q;`, or `c = expression;`, after the name of the bit register
+    fn assignment(&mut self, name: String, size: usize, token: Spanned) -> Result<Statement> {
+        let index = self.index(size)?;
+        let bits = BitArgument {
+            name,
+            size,
+            index,
+            token,
+        };
+        let _ = self.tokens.expect("=")?;
+
+        let statement = if self.tokens.at_identifier("measure") {
+            let _ = self.tokens.next();
+            let qubits = self.qubit_argument()?;
+            check_widths(&qubits, &bits)?;
+            Statement::Measure {
+                qubits,
+                bits: Some(bits),
+            }
+        } else {
+            let value = self.expression()?;
+            Statement::Assign { bits, value }
+        };
+
+        let _ = self.tokens.expect(";")?;
+        Ok(statement)
+    }
+
+    /// An index in brackets, if there is one, which is checked against the
+    /// size of the register if it is a constant
+    fn index(&mut self, size: usize) -> Result<Option<Expression>> {
+        if !self.tokens.at("[") {
+            return Ok(None);
+        }
+
+        let _ = self.tokens.next();
+        let token = self.tokens.peek().clone();
+        let index = self.expression()?;
+        let _ = self.tokens.expect("]")?;
+
+        if let Expression::Int(value) = index {
+            if value < 0 || value as u64 >= size as u64 {
+                return Err(token.error(format!(
+                    "index {} is outside of a register of size {}",
+                    value, size
+                )));
+            }
+        }
+
+        Ok(Some(index))
+    }
+
+    fn qubit_argument(&mut self) -> Result<QubitArgument> {
+        let (name, token) = self.tokens.identifier("a qubit register")?;
+        match self.symbols.get(&name).cloned() {
+            Some(Symbol::Qubits { start, size }) => Ok(QubitArgument {
+                start,
+                size,
+                index: self.index(size)?,
+                token,
+            }),
+            Some(_) => Err(token.error(format!("`{}` is not a qubit register", name))),
+            None => Err(token.error(format!("`{}` is not defined", name))),
+        }
+    }
+
+    fn bit_argument(&mut self) -> Result<BitArgument> {
+        let (name, token) = self.tokens.identifier("a bit register")?;
+        match self.symbols.get(&name).cloned() {
+            Some(Symbol::Bits(size)) => Ok(BitArgument {
+                name,
+                size,
+                index: self.index(size)?,
+                token,
+            }),
+            Some(_) => Err(token.error(format!("`{}` is not a bit register", name))),
+            None => Err(token.error(format!("`{}` is not defined", name))),
+        }
+    }
+
+    /// A condition in parentheses
+    fn condition(&mut self) -> Result<Expression> {
+        let _ = self.tokens.expect("(")?;
+        let condition = self.expression()?;
+        let _ = self.tokens.expect(")")?;
+        Ok(condition)
+    }
+
+    /// `for int i in [0:3] { ... }`, where the type is optional
+    fn for_loop(&mut self) -> Result<Statement> {
+        let (mut variable, mut token) = self.tokens.identifier("a loop variable")?;
+        if self.tokens.at("[") {
+            let _ = self.size()?;
+        }
+        if !self.tokens.at_identifier("in") {
+            let (name, name_token) = self.tokens.identifier("a loop variable")?;
+            variable = name;
+            token = name_token;
+        }
+        let (keyword, keyword_token) = self.tokens.identifier("`in`")?;
+        if keyword != "in" {
+            return Err(keyword_token.error(format!("expected `in`, found `{}`", keyword)));
+        }
+
+        let values = if self.tokens.at("{") {
+            let token = self.tokens.expect("{")?;
+            let mut values = Vec::new();
+            while !self.tokens.at("}") {
+                if !values.is_empty() {
+                    let _ = self.tokens.expect(",")?;
+                }
+                values.push(self.expression()?);
+            }
+            let _ = self.tokens.next();
+            Values::List { values, token }
+        } else {
+            let token = self.tokens.expect("[")?;
+            let start = Box::new(self.expression()?);
+            let _ = self.tokens.expect(":")?;
+            let mut end = Box::new(self.expression()?);
+            let mut step = None;
+            if self.tokens.at(":") {
+                let _ = self.tokens.next();
+                step = Some(end);
+                end = Box::new(self.expression()?);
+            }
+            let _ = self.tokens.expect("]")?;
+            Values::Range {
+                start,
+                step,
+                end,
+                token,
+            }
+        };
+
+        self.check_unused(&variable, &token)?;
+        let _ = self.symbols.insert(variable.clone(), Symbol::Variable);
+        let body = self.block();
+        let _ = self.symbols.remove(&variable);
+
+        Ok(Statement::For {
+            variable,
+            values,
+            body: body?,
+        })
+    }
+
+    /// The number of parameters and qubits of a gate, if it is defined
+    fn gate_arity(&self, name: &str) -> Option<(usize, usize)> {
+        match name {
+            "U" => Some((3, 1)),
+            "gphase" => Some((1, 0)),
+            _ => match self.definitions.get(name) {
+                Some(definition) => Some((definition.num_params, definition.num_qubits)),
+                None if self.included => standard_arity(name),
+                None => None,
+            },
+        }
+    }
+
+    /// Check that a gate is defined and given the right number of parameters,
+    /// and return its number of qubits
+    fn check_gate(&self, name: &str, token: &Spanned, num_params: usize) -> Result<usize> {
+        let (expected, num_qubits) = match self.gate_arity(name) {
+            Some(arity) => arity,
+            None if standard_arity(name).is_some() => {
+                return Err(token.error(format!(
+                    "the gate `{}` is not defined, include \"stdgates.inc\" to use it",
+                    name
+                )))
+            }
+            None => return Err(token.error(format!("`{}` is not defined", name))),
+        };
+
+        if expected != num_params {
+            return Err(token.error(format!(
+                "the gate `{}` takes {} parameters, but {} were given",
+                name, expected, num_params
+            )));
+        }
+
+        Ok(num_qubits)
+    }
+
+    /// The parameters of a gate in parentheses, if there are any
+    fn parameters(&mut self) -> Result<Vec<Expression>> {
+        let mut params = Vec::new();
+        if self.tokens.at("(") {
+            let _ = self.tokens.next();
+            while !self.tokens.at(")") {
+                if !params.is_empty() {
+                    let _ = self.tokens.expect(",")?;
+                }
+                params.push(self.expression()?);
+            }
+            let _ = self.tokens.next();
+        }
+
+        Ok(params)
+    }
+
+    fn gate_call(&mut self, name: String, token: Spanned) -> Result<Statement> {
+        let params = self.parameters()?;
+        let num_qubits = self.check_gate(&name, &token, params.len())?;
+
+        let mut qubits = Vec::new();
+        while !self.tokens.at(";") {
+            if !qubits.is_empty() {
+                let _ = self.tokens.expect(",")?;
+            }
+            qubits.push(self.qubit_argument()?);
+        }
+        let _ = self.tokens.next();
+
+        if qubits.len() != num_qubits {
+            return Err(token.error(format!(
+                "the gate `{}` acts on {} qubits, but {} were given",
+                name,
+                num_qubits,
+                qubits.len()
+            )));
+        }
+
+        // Registers apply the gate once for each of their qubits
+        let widths: Vec<usize> = qubits
+            .iter()
+            .map(|qubit| width(qubit.size, &qubit.index))
+            .collect();
+        let size = widths.iter().cloned().max().unwrap_or(1);
+        if widths.iter().any(|&width| width != 1 && width != size) {
+            return Err(token.error("the registers a gate is applied to must be the same size"));
+        }
+
+        Ok(Statement::Gate {
+            name,
+            params,
+            qubits,
+            token,
+        })
+    }
+
+    fn gate_definition(&mut self) -> Result<()> {
+        let (name, token) = self.tokens.identifier("a gate name")?;
+        self.check_unused(&name, &token)?;
+
+        let mut params: Vec<String> = Vec::new();
+        if self.tokens.at("(") {
+            let _ = self.tokens.next();
+            while !self.tokens.at(")") {
+                if !params.is_empty() {
+                    let _ = self.tokens.expect(",")?;
+                }
+                let (param, token) = self.tokens.identifier("a parameter name")?;
+                if params.contains(&param) {
+                    return Err(token.error(format!("the parameter `{}` is given twice", param)));
+                }
+                params.push(param);
+            }
+            let _ = self.tokens.next();
+        }
+
+        let mut qubits: Vec<String> = Vec::new();
+        while !self.tokens.at("{") {
+            if !qubits.is_empty() {
+                let _ = self.tokens.expect(",")?;
+            }
+            let (qubit, token) = self.tokens.identifier("a qubit argument")?;
+            if qubits.contains(&qubit) {
+                return Err(token.error(format!("the argument `{}` is given twice", qubit)));
+            }
+            qubits.push(qubit);
+        }
+        let _ = self.tokens.next();
+
+        self.gate_params = Some(params);
+        let body = self.gate_body(&name, &qubits);
+        let params = self.gate_params.take().unwrap_or_default();
+
+        let _ = self.definitions.insert(
+            name,
+            Definition {
+                num_params: params.len(),
+                num_qubits: qubits.len(),
+                body: body?,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// The gates applied by a gate definition, up to the closing brace
+    fn gate_body(&mut self, name: &str, arguments: &[String]) -> Result<Vec<GateCall>> {
+        let mut body = Vec::new();
+        while !self.tokens.at("}") {
+            let (gate, token) = self.tokens.identifier("a gate")?;
+
+            let mut qubits = Vec::new();
+            let params = if gate == "barrier" {
+                Vec::new()
+            } else {
+                self.parameters()?
+            };
+            while !self.tokens.at(";") {
+                if !qubits.is_empty() {
+                    let _ = self.tokens.expect(",")?;
+                }
+                let (argument, argument_token) = self.tokens.identifier("a qubit argument")?;
+                let index = arguments
+                    .iter()
+                    .position(|qubit| *qubit == argument)
+                    .ok_or_else(|| {
+                        argument_token.error(format!(
+                            "`{}` is not an argument of the gate `{}`",
+                            argument, name
+                        ))
+                    })?;
+                if qubits.contains(&index) {
+                    return Err(argument_token.error(format!(
+                        "the qubits of `{}` must be distinct",
+                        gate
+                    )));
+                }
+                qubits.push(index);
+            }
+            let _ = self.tokens.next();
+
+            if gate == "barrier" {
+                continue;
+            }
+
+            let num_qubits = self.check_gate(&gate, &token, params.len())?;
+            if qubits.len() != num_qubits {
+                return Err(token.error(format!(
+                    "the gate `{}` acts on {} qubits, but {} were given",
+                    gate,
+                    num_qubits,
+                    qubits.len()
+                )));
+            }
+
+            body.push(GateCall {
+                name: gate,
+                params,
+                qubits,
+            });
+        }
+        let _ = self.tokens.next();
+
+        Ok(body)
+    }
+
+    /// Parse an expression, from the operators which bind least tightly
+    fn expression(&mut self) -> Result<Expression> {
+        self.binary(0)
+    }
+
+    /// Operators of each precedence level, from the lowest, followed by the unary operators
+    fn binary(&mut self, level: usize) -> Result<Expression> {
+        const LEVELS: [&[&str]; 6] = [
+            &["||"],
+            &["&&"],
+            &["==", "!="],
+            &["<", ">", "<=", ">="],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut expression = self.binary(level + 1)?;
+        while let Some(&op) = LEVELS[level].iter().find(|&&op| self.tokens.at(op)) {
+            let token = self.tokens.next();
+            let right = self.binary(level + 1)?;
+            expression = Expression::Binary(op, Box::new(expression), Box::new(right), token);
+        }
+
+        Ok(expression)
+    }
+
+    /// A negated expression, or a power
+    fn unary(&mut self) -> Result<Expression> {
+        for &op in &["-", "!"] {
+            if self.tokens.at(op) {
+                let _ = self.tokens.next();
+                return Ok(Expression::Unary(op, Box::new(self.unary()?)));
+            }
+        }
+
+        let base = self.primary()?;
+        if self.tokens.at("^") {
+            let token = self.tokens.next();
+            let exponent = self.unary()?;
+            return Ok(Expression::Binary("^", Box::new(base), Box::new(exponent), token));
+        }
+
+        Ok(base)
+    }
+
+    /// A number, name, function call or parenthesised expression
+    fn primary(&mut self) -> Result<Expression> {
+        let token = self.tokens.next();
+        let name = match token.token {
+            Token::Integer(value) => {
+                return if value <= i64::MAX as u64 {
+                    Ok(Expression::Int(value as i64))
+                } else {
+                    Err(token.error(format!("the integer {} is too large", value)))
+                }
+            }
+            Token::Real(ref text) => {
+                return text.parse()
+                    .map(Expression::Float)
+                    .map_err(|_| token.error(format!("invalid number {}", text)))
+            }
+            Token::Symbol("(") => {
+                let expression = self.expression()?;
+                let _ = self.tokens.expect(")")?;
+                return Ok(expression);
+            }
+            Token::Identifier(ref name) => name.clone(),
+            _ => {
+                return Err(token.error(format!(
+                    "expected an expression, found {}",
+                    describe(&token)
+                )))
+            }
+        };
+
+        match name.as_str() {
+            "pi" | "π" => return Ok(Expression::Float(PI)),
+            "tau" | "τ" => return Ok(Expression::Float(2.0 * PI)),
+            "euler" | "ℇ" => return Ok(Expression::Float(E)),
+            "true" => return Ok(Expression::Int(1)),
+            "false" => return Ok(Expression::Int(0)),
+            _ => {}
+        }
+
+        if let Some(function) = function(&name) {
+            let _ = self.tokens.expect("(")?;
+            let operand = self.expression()?;
+            let _ = self.tokens.expect(")")?;
+            return Ok(Expression::Function(function, Box::new(operand)));
+        }
+
+        // Only the parameters are visible inside of a gate definition
+        if let Some(ref params) = self.gate_params {
+            return params
+                .iter()
+                .position(|param| *param == name)
+                .map(Expression::Parameter)
+                .ok_or_else(|| token.error(format!("`{}` is not a parameter of the gate", name)));
+        }
+
+        match self.symbols.get(&name).cloned() {
+            Some(Symbol::Variable) => Ok(Expression::Variable(name)),
+            Some(Symbol::Bits(size)) => match self.index(size)? {
+                Some(index) => Ok(Expression::Bit(name, Box::new(index), token)),
+                None => Ok(Expression::Register(name, token)),
+            },
+            Some(Symbol::Qubits { .. }) => Err(token.error(format!(
+                "the qubits `{}` can't be used in an expression, measure them first",
+                name
+            ))),
+            None => Err(token.error(format!("`{}` is not defined", name))),
+        }
+    }
+}
+
+/// The number of parameters and qubits of a gate in `stdgates.inc`
+fn standard_arity(name: &str) -> Option<(usize, usize)> {
+    match name {
+        "cu" => Some((4, 2)),
+        "CX" => Some((0, 2)),
+        "phase" => Some((1, 1)),
+        "cphase" => Some((1, 2)),
+        _ if STANDARD_GATES.contains(&name) => standard::arity(name),
+        _ => None,
+    }
+}
+
+/// Check that a measurement writes to as many bits as it measures qubits
+fn check_widths(qubits: &QubitArgument, bits: &BitArgument) -> Result<()> {
+    let (num_qubits, num_bits) = (width(qubits.size, &qubits.index), width(bits.size, &bits.index));
+    if num_qubits != num_bits {
+        return Err(qubits.token.error(format!(
+            "can't measure {} qubits into {} bits",
+            num_qubits, num_bits
+        )));
+    }
+
+    Ok(())
+}
+
+/// The functions which can be used in expressions
+fn function(name: &str) -> Option<fn(Real) -> Real> {
+    match name {
+        "sin" => Some(Real::sin),
+        "cos" => Some(Real::cos),
+        "tan" => Some(Real::tan),
+        "arcsin" => Some(Real::asin),
+        "arccos" => Some(Real::acos),
+        "arctan" => Some(Real::atan),
+        "exp" => Some(Real::exp),
+        "ln" | "log" => Some(Real::ln),
+        "sqrt" => Some(Real::sqrt),
+        _ => None,
+    }
+}
@@ -0,0 +1,305 @@
+//! Writes a `Circuit` as Quil
+//!
+//! Gates with a standard name are written with it, along with the `CONTROLLED`
+//! and `DAGGER` modifiers. Quil can define a gate by its matrix, so any other
+//! gate is written with `DEFGATE` rather than decomposed. The operations of a
+//! condition are skipped by jumping past them when a bit has the wrong value.
+
+use std::ops::Range;
+
+use circuit::{Circuit, Operation};
+use error::{Error, Result};
+use export::{approx_eq, arithmetic, check_qubits, unconditional, TOLERANCE};
+use gates::{h, id, r, rx, ry, rz, s, sdg, t, tdg, x, y, z};
+use gates::{Control, Gate, MatrixGate};
+use precision::Complex;
+use state::{qft_sequence, QftGate, QftOptions};
+
+/// The name of the format, for errors
+const FORMAT: &str = "Quil";
+
+/// Write a circuit as Quil
+pub(crate) fn export(circuit: &Circuit) -> Result<String> {
+    for operation in circuit.operations() {
+        check_qubits(operation, circuit.num_qubits())?;
+    }
+
+    let mut writer = Writer {
+        definitions: Vec::new(),
+        lines: Vec::new(),
+        labels: 0,
+    };
+    for operation in circuit.operations() {
+        writer.operation(operation)?;
+    }
+
+    let mut output = String::new();
+    if circuit.num_bits() > 0 {
+        output += &format!("DECLARE ro BIT[{}]\n", circuit.num_bits());
+    }
+    for (i, definition) in writer.definitions.iter().enumerate() {
+        let dim = definition.dimension();
+        output += &format!("DEFGATE G{}:\n", i);
+        for row in definition.matrix().chunks(dim) {
+            let entries: Vec<String> = row.iter().map(|&entry| complex(entry)).collect();
+            output += &format!("    {}\n", entries.join(", "));
+        }
+    }
+    for line in &writer.lines {
+        output += line;
+        output += "\n";
+    }
+
+    Ok(output)
+}
+
+/// A number as written in Quil, such as `0.5-0.5i`
+fn complex(value: Complex) -> String {
+    if value.im == 0.0 {
+        value.re.to_string()
+    } else if value.re == 0.0 {
+        format!("{}i", value.im)
+    } else if value.im < 0.0 {
+        format!("{}-{}i", value.re, -value.im)
+    } else {
+        format!("{}+{}i", value.re, value.im)
+    }
+}
+
+struct Writer {
+    /// The gates defined with `DEFGATE`, where the gate at index i is named `Gi`
+    definitions: Vec<MatrixGate>,
+    lines: Vec<String>,
+    /// The number of labels used so far
+    labels: usize,
+}
+
+impl Writer {
+    /// Apply a gate, given as its name and any modifiers and parameters
+    fn apply(&mut self, gate: &str, qubits: &[i32]) {
+        let qubits: Vec<String> = qubits.iter().map(i32::to_string).collect();
+        self.lines.push(format!("{} {}", gate, qubits.join(" ")));
+    }
+
+    fn operation(&mut self, operation: &Operation) -> Result<()> {
+        match *operation {
+            Operation::Gate { target, ref gate } => self.controlled(&[], target, gate),
+            Operation::ControlledGate {
+                control,
+                target,
+                ref gate,
+            } => self.controlled(&[Control::Positive(control)], target, gate),
+            Operation::MultiControlledGate {
+                ref controls,
+                target,
+                ref gate,
+            } => self.controlled(controls, target, gate),
+            Operation::Toffoli {
+                control1,
+                control2,
+                target,
+            } => self.apply("CCNOT", &[control1, control2, target]),
+            Operation::TwoQubitGate {
+                qubit0,
+                qubit1,
+                ref gate,
+            } => self.matrix(&[qubit0, qubit1], &MatrixGate::from(*gate)),
+            Operation::Matrix {
+                ref qubits,
+                ref gate,
+            } => self.matrix(qubits, gate),
+            Operation::Swap { first, second } => self.apply("SWAP", &[first, second]),
+            Operation::Qft {
+                ref qubits,
+                options,
+                inverse,
+            } => self.qft(qubits, options, inverse),
+            Operation::Arithmetic { ref operation, .. } => {
+                return Err(Error::Unsupported {
+                    operation: arithmetic(operation).0.to_string(),
+                    format: FORMAT,
+                })
+            }
+            Operation::PowMod { .. } => {
+                return Err(Error::Unsupported {
+                    operation: "pow_mod".to_string(),
+                    format: FORMAT,
+                })
+            }
+            Operation::Measure { qubit, bit } => {
+                self.lines.push(format!("MEASURE {} ro[{}]", qubit, bit));
+            }
+            Operation::Conditional {
+                ref bits,
+                value,
+                ref operations,
+            } => return self.conditional(bits, value, operations),
+        }
+
+        Ok(())
+    }
+
+    /// Jump past the operations unless every bit has its value, so the
+    /// condition is tested before any of them can change the bits
+    fn conditional(&mut self, bits: &Range<usize>, value: u64, operations: &[Operation]) -> Result<()> {
+        if let Some(operations) = unconditional(bits, value, operations) {
+            return operations
+                .iter()
+                .try_for_each(|operation| self.operation(operation));
+        }
+
+        // Bits past the 64 of the value must be 0
+        let expected = |bit: usize| bit - bits.start < 64 && value >> (bit - bits.start) & 1 == 1;
+
+        let label = format!("@skip{}", self.labels);
+        self.labels += 1;
+
+        for bit in bits.clone() {
+            let jump = if expected(bit) { "JUMP-UNLESS" } else { "JUMP-WHEN" };
+            self.lines.push(format!("{} {} ro[{}]", jump, label, bit));
+        }
+        for operation in operations {
+            self.operation(operation)?;
+        }
+        self.lines.push(format!("LABEL {}", label));
+
+        Ok(())
+    }
+
+    /// The name of a single qubit gate, with any modifiers and parameters.
+    /// Gates are matched exactly, with their global phase, so a circuit read
+    /// back has the same effect on the amplitudes.
+    fn name(&mut self, gate: &Gate) -> String {
+        let named = [
+            ("I", id()),
+            ("X", x()),
+            ("Y", y()),
+            ("Z", z()),
+            ("H", h()),
+            ("S", s()),
+            ("T", t()),
+            ("DAGGER S", sdg()),
+            ("DAGGER T", tdg()),
+        ];
+        if let Some(&(name, _)) = named.iter().find(|named| approx_eq(gate, &named.1)) {
+            return name.to_string();
+        }
+
+        // The rotations, with the angles they would need
+        let phase = gate.d.arg() - gate.a.arg();
+        let phase = phase.sin().atan2(phase.cos());
+        let angle = 2.0 * gate.b.norm().atan2(gate.a.norm());
+        let rotations = [("PHASE", phase), ("RZ", phase), ("RX", angle), ("RY", angle)];
+        for &(name, angle) in &rotations {
+            for &angle in &[angle, -angle] {
+                let rotation = match name {
+                    "PHASE" => r(angle),
+                    "RZ" => rz(angle),
+                    "RX" => rx(angle),
+                    _ => ry(angle),
+                };
+                if approx_eq(gate, &rotation) {
+                    return format!("{}({})", name, angle);
+                }
+            }
+        }
+
+        self.definition(&MatrixGate::from(*gate))
+    }
+
+    /// The name of a gate defined by its matrix, defining it if needed
+    fn definition(&mut self, gate: &MatrixGate) -> String {
+        let index = match self.definitions.iter().position(|defined| {
+            defined.num_qubits() == gate.num_qubits()
+                && defined
+                    .matrix()
+                    .iter()
+                    .zip(gate.matrix())
+                    .all(|(a, b)| (a - b).norm() < TOLERANCE)
+        }) {
+            Some(index) => index,
+            None => {
+                self.definitions.push(gate.clone());
+                self.definitions.len() - 1
+            }
+        };
+
+        format!("G{}", index)
+    }
+
+    /// A gate with any controls, where the negative controls are flipped
+    /// before and after the gate
+    fn controlled(&mut self, controls: &[Control], target: i32, gate: &Gate) {
+        let negative: Vec<i32> = controls
+            .iter()
+            .filter(|control| !control.value())
+            .map(Control::qubit)
+            .collect();
+        let mut qubits: Vec<i32> = controls.iter().map(Control::qubit).collect();
+        qubits.push(target);
+
+        for &qubit in &negative {
+            self.apply("X", &[qubit]);
+        }
+
+        let is_phase = approx_eq(gate, &r(gate.d.arg()));
+        let name = match controls.len() {
+            0 => self.name(gate),
+            1 if approx_eq(gate, &x()) => "CNOT".to_string(),
+            1 if approx_eq(gate, &z()) => "CZ".to_string(),
+            1 if is_phase => format!("CPHASE({})", gate.d.arg()),
+            2 if approx_eq(gate, &x()) => "CCNOT".to_string(),
+            n => "CONTROLLED ".repeat(n) + &self.name(gate),
+        };
+        self.apply(&name, &qubits);
+
+        for &qubit in &negative {
+            self.apply("X", &[qubit]);
+        }
+    }
+
+    /// A gate on several qubits. Quil lists the qubit of the highest bit of
+    /// the matrix first.
+    fn matrix(&mut self, qubits: &[i32], gate: &MatrixGate) {
+        let matrix = gate.matrix();
+        if gate.num_qubits() == 1 {
+            let gate = Gate {
+                a: matrix[0],
+                b: matrix[1],
+                c: matrix[2],
+                d: matrix[3],
+            };
+            return self.controlled(&[], qubits[0], &gate);
+        }
+
+        let qubits: Vec<i32> = qubits.iter().rev().cloned().collect();
+        let swap = [0, 6, 9, 15];
+        let is_swap = gate.num_qubits() == 2
+            && matrix.iter().enumerate().all(|(i, entry)| {
+                let expected = if swap.contains(&i) { 1.0 } else { 0.0 };
+                (entry - expected).norm() < TOLERANCE
+            });
+
+        let name = if is_swap {
+            "SWAP".to_string()
+        } else {
+            self.definition(gate)
+        };
+        self.apply(&name, &qubits);
+    }
+
+    /// The quantum Fourier transform, as Hadamard gates and controlled phases
+    fn qft(&mut self, qubits: &Range<i32>, options: QftOptions, inverse: bool) {
+        for gate in qft_sequence(qubits, options, inverse) {
+            match gate {
+                QftGate::H(target) => self.apply("H", &[target]),
+                QftGate::Rotation {
+                    control,
+                    target,
+                    angle,
+                } => self.apply(&format!("CPHASE({})", angle), &[control, target]),
+                QftGate::Swap(first, second) => self.apply("SWAP", &[first, second]),
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Splits Quil source into tokens
+//!
+//! Quil is written one instruction per line, so the end of each line which
+//! isn't blank is kept as the symbol `"\n"`.
+
+use error::{Error, Result};
+use qasm::lexer::{number, starts_number, Spanned, Token};
+
+/// The symbols of Quil. `;` separates instructions on the same line, so it is
+/// read as the end of a line.
+const SYMBOLS: [&str; 13] = [
+    "(", ")", "[", "]", ",", "+", "-", "*", "/", "^", ":", "@", "%",
+];
+
+/// Split the source into tokens, ending with `Token::End`
+///
+/// Whitespace and `#` comments are skipped. Hyphens join the words of upper
+/// case identifiers such as `JUMP-WHEN`, so `pi-1` is still a subtraction.
+pub(crate) fn tokenize(source: &str) -> Result<Vec<Spanned>> {
+    let mut tokens: Vec<Spanned> = Vec::new();
+
+    for (line, text) in source.lines().enumerate() {
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let start = i;
+            let c = chars[i];
+            let error = |message: String| Error::Parse {
+                line: line + 1,
+                column: start + 1,
+                message,
+            };
+
+            let token = if c.is_whitespace() {
+                i += 1;
+                continue;
+            } else if c == '#' {
+                break;
+            } else if c == ';' {
+                i += 1;
+                Token::Symbol("\n")
+            } else if c.is_alphabetic() || c == '_' {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_'
+                    || (chars[i] == '-' && joins_words(&chars, start, i)))
+                {
+                    i += 1;
+                }
+                Token::Identifier(chars[start..i].iter().collect())
+            } else if starts_number(&chars, i) {
+                let (end, token) = number(&chars, i, line + 1)?;
+                i = end;
+
+                // A number directly followed by `i` is imaginary
+                let imaginary = chars.get(i) == Some(&'i')
+                    && !chars
+                        .get(i + 1)
+                        .map_or(false, |&c| c.is_alphanumeric() || c == '_');
+                if imaginary {
+                    i += 1;
+                    Token::Imaginary(chars[start..i - 1].iter().collect())
+                } else {
+                    token
+                }
+            } else if c == '"' {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    i += 1;
+                }
+                if i == chars.len() {
+                    return Err(error("unterminated string".to_string()));
+                }
+                i += 1;
+                Token::Str(chars[start + 1..i - 1].iter().collect())
+            } else {
+                match SYMBOLS.iter().find(|symbol| symbol.starts_with(c)) {
+                    Some(symbol) => {
+                        i += 1;
+                        Token::Symbol(symbol)
+                    }
+                    None => return Err(error(format!("unexpected character {:?}", c))),
+                }
+            };
+
+            push(&mut tokens, token, line + 1, start + 1);
+        }
+
+        push(&mut tokens, Token::Symbol("\n"), line + 1, chars.len() + 1);
+    }
+
+    let line = source.lines().count().max(1);
+    let column = source.lines().last().map_or(0, |text| text.chars().count()) + 1;
+    tokens.push(Spanned {
+        token: Token::End,
+        line,
+        column,
+    });
+
+    Ok(tokens)
+}
+
+/// Add a token, leaving out the ends of blank lines
+fn push(tokens: &mut Vec<Spanned>, token: Token, line: usize, column: usize) {
+    let blank = token == Token::Symbol("\n")
+        && tokens
+            .last()
+            .map_or(true, |last| last.token == Token::Symbol("\n"));
+
+    if !blank {
+        tokens.push(Spanned {
+            token,
+            line,
+            column,
+        });
+    }
+}
+
+/// Whether the hyphen at `i` joins two words of an upper case identifier
+/// starting at `start`
+fn joins_words(chars: &[char], start: usize, i: usize) -> bool {
+    chars[start..i]
+        .iter()
+        .all(|c| !c.is_lowercase())
+        && chars.get(i + 1).map_or(false, |c| c.is_alphabetic())
+}
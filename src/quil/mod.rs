@@ -0,0 +1,73 @@
+//! Quil
+//!
+//! Imports programs written in [Quil](https://arxiv.org/abs/1608.03355) as
+//! circuits, and exports circuits to it. Qubits are numbered as in the
+//! program, and `DECLARE`d bits are laid out in the order they are declared,
+//! starting from bit 0.
+//!
+//! ```
+//!# extern crate qcgpu;
+//!# use qcgpu::quil;
+//!# use qcgpu::backends::Cpu;
+//! let circuit = quil::parse("
+//!     DECLARE ro BIT[2]
+//!     H 0
+//!     MEASURE 0 ro[0]
+//!     JUMP-UNLESS @end ro[0]
+//!     X 1
+//!     LABEL @end
+//!     MEASURE 1 ro[1]
+//! ").unwrap();
+//!
+//! let (_, bits) = circuit.execute(Cpu::new()).unwrap();
+//! assert_eq!(bits[0], bits[1]);
+//! ```
+//!
+//! The standard gates are supported, along with the `CONTROLLED` and
+//! `DAGGER` modifiers, gates defined by a matrix with `DEFGATE`, `DECLARE` of
+//! `BIT` memory, `MEASURE`, and `JUMP-WHEN` and `JUMP-UNLESS` to a later
+//! label, which become conditional operations. A `JUMP` at the end of the
+//! code skipped by one of those skips an else block. Jumping backward makes a
+//! loop, so it is an error, as are `RESET` and classical arithmetic.
+//!
+//! `export` writes a circuit as Quil, defining gates without a standard name
+//! by their matrices:
+//!
+//! ```
+//!# extern crate qcgpu;
+//!# use qcgpu::{quil, Circuit};
+//! let mut circuit = Circuit::new(2);
+//! circuit.h(0).cx(0, 1).measure_all();
+//!
+//! let source = quil::export(&circuit).unwrap();
+//! assert!(source.contains("CNOT 0 1"));
+//! ```
+
+mod export;
+mod lexer;
+mod parser;
+
+use circuit::Circuit;
+use error::Result;
+
+/// Parse a Quil program into a circuit.
+///
+/// Returns `Error::Parse`, with the line and column of the problem, if the
+/// program is invalid or uses an instruction which isn't supported.
+pub fn parse(source: &str) -> Result<Circuit> {
+    parser::parse(source)
+}
+
+/// Write a circuit as Quil.
+///
+/// Classical bits are written as the `ro` memory. Single qubit gates without
+/// a standard name, and gates on several qubits other than swaps, are
+/// defined with `DEFGATE` by their matrices. A condition on several bits is
+/// written as a jump for each bit, over the operations it controls.
+///
+/// Returns `Error::Unsupported` for arithmetic and `pow_mod`. Returns
+/// `Error::InvalidQubit` or `Error::DuplicateQubit` if an operation's qubits
+/// are outside of the register or repeated.
+pub fn export(circuit: &Circuit) -> Result<String> {
+    export::export(circuit)
+}
@@ -0,0 +1,1016 @@
+//! Parses Quil into a `Circuit`
+//!
+//! The instructions are read into a list first, so each jump can be matched
+//! with its label. Quil has no blocks, so only jumps which skip forward over
+//! some instructions can be simulated, as a conditional operation on the bit
+//! they test. Jumping backward makes a loop, which a circuit can't represent.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use circuit::{Circuit, Operation};
+use error::Result;
+use gates::{h, id, iswap, r, rx, ry, rz, s, t, x, y, z};
+use gates::{Control, Gate, MatrixGate, MAX_MATRIX_QUBITS};
+use precision::{Complex, Real};
+use precision::consts::PI;
+use qasm::lexer::{describe, Cursor, Spanned, Token};
+use super::lexer::tokenize;
+
+/// The most qubits a circuit can have
+const MAX_QUBITS: u64 = 64;
+
+/// How far the parameter of a standard gate can be from a real number
+const TOLERANCE: Real = 1e-6;
+
+/// Instructions which can't be simulated as part of a circuit
+const UNSUPPORTED: [&str; 37] = [
+    "RESET", "WAIT", "INCLUDE", "DEFCIRCUIT", "FORKED", "MOVE", "EXCHANGE", "CONVERT", "LOAD",
+    "STORE", "NEG", "NOT", "AND", "IOR", "XOR", "ADD", "SUB", "MUL", "DIV", "EQ", "GT", "GE",
+    "LT", "LE", "DEFFRAME", "DEFWAVEFORM", "DEFCAL", "PULSE", "CAPTURE", "RAW-CAPTURE", "DELAY",
+    "FENCE", "SET-FREQUENCY", "SHIFT-FREQUENCY", "SET-PHASE", "SHIFT-PHASE", "SET-SCALE",
+];
+
+/// An expression for a gate parameter, or an entry of a gate matrix
+#[derive(Debug, Clone)]
+enum Expression {
+    Number(Complex),
+    /// The parameter of the enclosing `DEFGATE` with the given index
+    Parameter(usize),
+    Negate(Box<Expression>),
+    Binary(&'static str, Box<Expression>, Box<Expression>),
+    Function(fn(Complex) -> Complex, Box<Expression>),
+}
+
+impl Expression {
+    /// The value of the expression, given the values of the parameters
+    fn evaluate(&self, params: &[Complex]) -> Complex {
+        match *self {
+            Expression::Number(value) => value,
+            Expression::Parameter(index) => params[index],
+            Expression::Negate(ref operand) => -operand.evaluate(params),
+            Expression::Binary(op, ref left, ref right) => {
+                let (left, right) = (left.evaluate(params), right.evaluate(params));
+                match op {
+                    "+" => left + right,
+                    "-" => left - right,
+                    "*" => left * right,
+                    "/" => left / right,
+                    // Keep real powers exact, and defined for a base of 0
+                    _ if left.im == 0.0 && right.im == 0.0 && left.re >= 0.0 => {
+                        Complex::new(left.re.powf(right.re), 0.0)
+                    }
+                    _ => left.powc(right),
+                }
+            }
+            Expression::Function(function, ref operand) => function(operand.evaluate(params)),
+        }
+    }
+}
+
+/// The functions which can be used in expressions
+fn function(name: &str) -> Option<fn(Complex) -> Complex> {
+    match name {
+        "sin" => Some(|z| z.sin()),
+        "cos" => Some(|z| z.cos()),
+        "sqrt" => Some(|z| z.sqrt()),
+        "exp" => Some(|z| z.exp()),
+        "cis" => Some(|z| (Complex::new(0.0, 1.0) * z).exp()),
+        _ => None,
+    }
+}
+
+/// A gate without its controls. The qubits it is applied to are listed as in
+/// Quil, where the first is the highest bit of the matrix's basis states.
+#[derive(Debug, Clone)]
+enum Base {
+    Single(Gate),
+    Swap,
+    Matrix(MatrixGate),
+}
+
+impl Base {
+    fn num_qubits(&self) -> usize {
+        match *self {
+            Base::Single(_) => 1,
+            Base::Swap => 2,
+            Base::Matrix(ref gate) => gate.num_qubits() as usize,
+        }
+    }
+
+    fn adjoint(&self) -> Base {
+        match *self {
+            Base::Single(ref gate) => Base::Single(gate.adjoint()),
+            Base::Swap => Base::Swap,
+            Base::Matrix(ref gate) => Base::Matrix(gate.adjoint()),
+        }
+    }
+
+    fn matrix(&self) -> MatrixGate {
+        match *self {
+            Base::Single(gate) => MatrixGate::from(gate),
+            Base::Swap => {
+                let zero = Complex::new(0.0, 0.0);
+                let one = Complex::new(1.0, 0.0);
+                let mut matrix = vec![zero; 16];
+                for &(row, col) in &[(0, 0), (1, 2), (2, 1), (3, 3)] {
+                    matrix[row * 4 + col] = one;
+                }
+                MatrixGate::new(matrix).expect("the swap gate is unitary")
+            }
+            Base::Matrix(ref gate) => gate.clone(),
+        }
+    }
+}
+
+/// A gate, and the values its controls must have. The controls are the
+/// first qubits it is applied to.
+#[derive(Debug, Clone)]
+struct Unitary {
+    controls: Vec<bool>,
+    base: Base,
+}
+
+/// The number of parameters of a standard gate, or `None` if there is no
+/// standard gate with the name
+fn standard_params(name: &str) -> Option<usize> {
+    match name {
+        "I" | "X" | "Y" | "Z" | "H" | "S" | "T" | "CZ" | "CNOT" | "CCNOT" | "SWAP" | "CSWAP"
+        | "ISWAP" => Some(0),
+        "PHASE" | "RX" | "RY" | "RZ" | "CPHASE00" | "CPHASE01" | "CPHASE10" | "CPHASE"
+        | "PSWAP" => Some(1),
+        _ => None,
+    }
+}
+
+/// A standard gate, given its parameters
+fn standard(name: &str, params: &[Real]) -> Unitary {
+    let angle = params.first().cloned().unwrap_or(0.0);
+    let controlled = |controls: &[bool], gate: Gate| Unitary {
+        controls: controls.to_vec(),
+        base: Base::Single(gate),
+    };
+
+    match name {
+        "I" => controlled(&[], id()),
+        "X" => controlled(&[], x()),
+        "Y" => controlled(&[], y()),
+        "Z" => controlled(&[], z()),
+        "H" => controlled(&[], h()),
+        "S" => controlled(&[], s()),
+        "T" => controlled(&[], t()),
+        "PHASE" => controlled(&[], r(angle)),
+        "RX" => controlled(&[], rx(angle)),
+        "RY" => controlled(&[], ry(angle)),
+        "RZ" => controlled(&[], rz(angle)),
+        "CZ" => controlled(&[true], z()),
+        "CNOT" => controlled(&[true], x()),
+        "CCNOT" => controlled(&[true, true], x()),
+        // CPHASEab changes the phase of the state where the qubits are a and b
+        "CPHASE00" => controlled(&[false], x() * r(angle) * x()),
+        "CPHASE01" => controlled(&[false], r(angle)),
+        "CPHASE10" => controlled(&[true], x() * r(angle) * x()),
+        "CPHASE" => controlled(&[true], r(angle)),
+        "SWAP" => Unitary {
+            controls: vec![],
+            base: Base::Swap,
+        },
+        "CSWAP" => Unitary {
+            controls: vec![true],
+            base: Base::Swap,
+        },
+        "ISWAP" => Unitary {
+            controls: vec![],
+            base: Base::Matrix(MatrixGate::from(iswap())),
+        },
+        _ => {
+            let zero = Complex::new(0.0, 0.0);
+            let one = Complex::new(1.0, 0.0);
+            let phase = Complex::new(0.0, angle).exp();
+            let mut matrix = vec![zero; 16];
+            for &(row, col, value) in &[(0, 0, one), (1, 2, phase), (2, 1, phase), (3, 3, one)] {
+                matrix[row * 4 + col] = value;
+            }
+
+            Unitary {
+                controls: vec![],
+                base: Base::Matrix(MatrixGate::new(matrix).expect("PSWAP is unitary")),
+            }
+        }
+    }
+}
+
+/// A gate defined with `DEFGATE`, as a matrix whose entries may depend on its parameters
+#[derive(Debug, Clone)]
+struct Definition {
+    num_params: usize,
+    /// The entries of the matrix, in row major order
+    entries: Vec<Expression>,
+}
+
+impl Definition {
+    /// The gate, given the values of its parameters. Returns an error if the
+    /// matrix isn't unitary.
+    fn base(&self, params: &[Complex]) -> Result<Base> {
+        let matrix: Vec<Complex> = self.entries
+            .iter()
+            .map(|entry| entry.evaluate(params))
+            .collect();
+        let gate = MatrixGate::new(matrix)?;
+
+        Ok(if gate.num_qubits() == 1 {
+            let matrix = gate.matrix();
+            Base::Single(Gate {
+                a: matrix[0],
+                b: matrix[1],
+                c: matrix[2],
+                d: matrix[3],
+            })
+        } else {
+            Base::Matrix(gate)
+        })
+    }
+}
+
+/// An instruction, as written
+#[derive(Debug, Clone)]
+enum Instruction {
+    Operation(Box<Operation>),
+    Label(String),
+    /// `JUMP` to a label
+    Jump(String),
+    /// `JUMP-WHEN` a bit is 1, if `when` is true, or `JUMP-UNLESS` it is
+    JumpWhen {
+        label: String,
+        bit: usize,
+        when: bool,
+    },
+    Halt,
+}
+
+/// Parse a Quil program into a circuit
+pub(crate) fn parse(source: &str) -> Result<Circuit> {
+    let mut parser = Parser {
+        tokens: Cursor::new(tokenize(source)?),
+        registers: HashMap::new(),
+        num_qubits: 0,
+        num_bits: 0,
+        definitions: HashMap::new(),
+        instructions: Vec::new(),
+    };
+
+    while parser.tokens.peek().token != Token::End {
+        parser.instruction()?;
+    }
+
+    let mut flow = Flow::new(parser.instructions)?;
+    let (operations, _) = flow.block(None)?;
+
+    let mut circuit = Circuit::new(parser.num_qubits as u32).with_bits(parser.num_bits);
+    for operation in operations {
+        let _ = circuit.push(operation);
+    }
+
+    Ok(circuit)
+}
+
+struct Parser {
+    tokens: Cursor,
+    /// The bits of each declared memory region
+    registers: HashMap<String, Range<usize>>,
+    num_qubits: usize,
+    num_bits: usize,
+    definitions: HashMap<String, Definition>,
+    instructions: Vec<(Instruction, Spanned)>,
+}
+
+impl Parser {
+    fn instruction(&mut self) -> Result<()> {
+        let (name, token) = self.tokens.identifier("an instruction")?;
+
+        match name.as_str() {
+            "DECLARE" => self.declaration()?,
+            "DEFGATE" => return self.gate_definition(),
+            "MEASURE" => {
+                let (qubit, _) = self.qubit()?;
+                if self.at_end_of_line() {
+                    return Err(token.error("MEASURE must write its outcome to a classical bit"));
+                }
+                let bit = self.address()?;
+                let measure = Operation::Measure { qubit, bit };
+                self.instructions
+                    .push((Instruction::Operation(Box::new(measure)), token));
+            }
+            "LABEL" => {
+                let label = self.label()?;
+                self.instructions.push((Instruction::Label(label), token));
+            }
+            "JUMP" => {
+                let label = self.label()?;
+                self.instructions.push((Instruction::Jump(label), token));
+            }
+            "JUMP-WHEN" | "JUMP-UNLESS" => {
+                let label = self.label()?;
+                let bit = self.address()?;
+                let when = name == "JUMP-WHEN";
+                self.instructions
+                    .push((Instruction::JumpWhen { label, bit, when }, token));
+            }
+            "HALT" => self.instructions.push((Instruction::Halt, token)),
+            "NOP" => {}
+            "PRAGMA" => while !self.at_end_of_line() {
+                let _ = self.tokens.next();
+            },
+            _ if UNSUPPORTED.contains(&name.as_str()) => {
+                return Err(token.error(format!("`{}` isn't supported", name)))
+            }
+            _ => {
+                let operation = self.gate(name, token.clone())?;
+                self.instructions
+                    .push((Instruction::Operation(Box::new(operation)), token));
+            }
+        }
+
+        self.end_of_line()
+    }
+
+    fn at_end_of_line(&self) -> bool {
+        self.tokens.at("\n") || self.tokens.peek().token == Token::End
+    }
+
+    fn end_of_line(&mut self) -> Result<()> {
+        if !self.at_end_of_line() {
+            let token = self.tokens.peek();
+            return Err(token.error(format!(
+                "expected the end of the line, found {}",
+                describe(token)
+            )));
+        }
+
+        let _ = self.tokens.next();
+        Ok(())
+    }
+
+    fn declaration(&mut self) -> Result<()> {
+        let (name, token) = self.tokens.identifier("a memory name")?;
+        if self.registers.contains_key(&name) {
+            return Err(token.error(format!("`{}` is already declared", name)));
+        }
+
+        let (kind, kind_token) = self.tokens.identifier("a memory type")?;
+        if kind != "BIT" {
+            return Err(kind_token.error(format!("only BIT memory is supported, not `{}`", kind)));
+        }
+
+        let size = if self.tokens.at("[") {
+            let _ = self.tokens.next();
+            let (size, size_token) = self.tokens.integer()?;
+            let _ = self.tokens.expect("]")?;
+
+            if size == 0 {
+                return Err(size_token.error("memory must have at least one bit"));
+            }
+            size as usize
+        } else {
+            1
+        };
+
+        let _ = self.registers
+            .insert(name, self.num_bits..self.num_bits + size);
+        self.num_bits += size;
+
+        Ok(())
+    }
+
+    /// A classical bit, written `name[index]`, or `name` for the first bit
+    fn address(&mut self) -> Result<usize> {
+        let (name, token) = self.tokens.identifier("a classical bit")?;
+        let bits = self.registers
+            .get(&name)
+            .cloned()
+            .ok_or_else(|| token.error(format!("`{}` is not declared", name)))?;
+
+        if !self.tokens.at("[") {
+            return Ok(bits.start);
+        }
+
+        let _ = self.tokens.next();
+        let (index, index_token) = self.tokens.integer()?;
+        let _ = self.tokens.expect("]")?;
+
+        if index >= bits.len() as u64 {
+            return Err(index_token.error(format!(
+                "index {} is outside of `{}`, which has {} bits",
+                index,
+                name,
+                bits.len()
+            )));
+        }
+
+        Ok(bits.start + index as usize)
+    }
+
+    fn qubit(&mut self) -> Result<(i32, Spanned)> {
+        let token = self.tokens.next();
+        let index = match token.token {
+            Token::Integer(index) => index,
+            _ => {
+                return Err(token.error(format!(
+                    "expected a qubit index, found {}",
+                    describe(&token)
+                )))
+            }
+        };
+
+        if index >= MAX_QUBITS {
+            return Err(token.error(format!(
+                "the circuit can't have more than {} qubits",
+                MAX_QUBITS
+            )));
+        }
+        self.num_qubits = self.num_qubits.max(index as usize + 1);
+
+        Ok((index as i32, token))
+    }
+
+    fn label(&mut self) -> Result<String> {
+        let _ = self.tokens.expect("@")?;
+        let (label, _) = self.tokens.identifier("a label")?;
+        Ok(label)
+    }
+
+    fn gate_definition(&mut self) -> Result<()> {
+        let (name, token) = self.tokens.identifier("a gate name")?;
+        if standard_params(&name).is_some() || self.definitions.contains_key(&name)
+            || name == "CONTROLLED" || name == "DAGGER"
+        {
+            return Err(token.error(format!("the gate `{}` is already defined", name)));
+        }
+
+        let params = self.parameter_names()?;
+        if self.tokens.at_identifier("AS") {
+            let _ = self.tokens.next();
+            let (kind, kind_token) = self.tokens.identifier("a gate type")?;
+            if kind != "MATRIX" {
+                return Err(kind_token.error(format!(
+                    "only gates defined AS MATRIX are supported, not AS {}",
+                    kind
+                )));
+            }
+        }
+        let _ = self.tokens.expect(":")?;
+        self.end_of_line()?;
+
+        let (mut entries, _) = self.row(&params)?;
+        let dimension = entries.len();
+        let max = 1 << MAX_MATRIX_QUBITS;
+        if dimension < 2 || dimension > max || !dimension.is_power_of_two() {
+            return Err(token.error(format!(
+                "the matrix of `{}` has {} columns, but must have a power of 2 between 2 and {}",
+                name, dimension, max
+            )));
+        }
+
+        for _ in 1..dimension {
+            let (row, row_token) = self.row(&params)?;
+            if row.len() != dimension {
+                return Err(row_token.error(format!(
+                    "each row of the matrix of `{}` must have {} entries",
+                    name, dimension
+                )));
+            }
+            entries.extend(row);
+        }
+
+        let definition = Definition {
+            num_params: params.len(),
+            entries,
+        };
+        if params.is_empty() && definition.base(&[]).is_err() {
+            return Err(token.error(format!("the matrix of `{}` is not unitary", name)));
+        }
+        let _ = self.definitions.insert(name, definition);
+
+        Ok(())
+    }
+
+    /// The parameter names of a gate definition, if it has any
+    fn parameter_names(&mut self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        if self.tokens.at("(") {
+            let _ = self.tokens.next();
+            while !self.tokens.at(")") {
+                if !names.is_empty() {
+                    let _ = self.tokens.expect(",")?;
+                }
+                let _ = self.tokens.expect("%")?;
+                let (name, token) = self.tokens.identifier("a parameter name")?;
+                if names.contains(&name) {
+                    return Err(token.error(format!("the parameter `%{}` is given twice", name)));
+                }
+                names.push(name);
+            }
+            let _ = self.tokens.next();
+        }
+
+        Ok(names)
+    }
+
+    /// A row of a gate matrix, as entries separated by commas on one line
+    fn row(&mut self, names: &[String]) -> Result<(Vec<Expression>, Spanned)> {
+        let token = self.tokens.peek().clone();
+        let mut entries = vec![self.sum(names)?];
+        while self.tokens.at(",") {
+            let _ = self.tokens.next();
+            entries.push(self.sum(names)?);
+        }
+        self.end_of_line()?;
+
+        Ok((entries, token))
+    }
+
+    /// A gate applied to some qubits, after any `CONTROLLED` and `DAGGER`
+    /// modifiers, of which `name` is the first
+    fn gate(&mut self, name: String, token: Spanned) -> Result<Operation> {
+        let mut modifiers = Vec::new();
+        let (mut name, mut token) = (name, token);
+        while name == "CONTROLLED" || name == "DAGGER" {
+            modifiers.push(name == "CONTROLLED");
+            let (next, next_token) = self.tokens.identifier("a gate")?;
+            name = next;
+            token = next_token;
+        }
+
+        let params = self.parameters()?;
+        let mut unitary = self.unitary(&name, &token, &params)?;
+
+        // The innermost modifier applies first, and each control comes
+        // before the qubits of the gate it controls
+        for &controlled in modifiers.iter().rev() {
+            if controlled {
+                unitary.controls.insert(0, true);
+            } else {
+                unitary.base = unitary.base.adjoint();
+            }
+        }
+
+        let mut qubits = Vec::new();
+        while !self.at_end_of_line() {
+            let (qubit, qubit_token) = self.qubit()?;
+            if qubits.contains(&qubit) {
+                return Err(qubit_token.error(format!("qubit {} is used twice", qubit)));
+            }
+            qubits.push(qubit);
+        }
+
+        let num_qubits = unitary.controls.len() + unitary.base.num_qubits();
+        if qubits.len() != num_qubits {
+            return Err(token.error(format!(
+                "the gate `{}` acts on {} qubits, but {} were given",
+                name,
+                num_qubits,
+                qubits.len()
+            )));
+        }
+
+        operation(&unitary, &qubits)
+            .ok_or_else(|| token.error(format!(
+                "the gate `{}` acts on more than the {} qubits a matrix can have",
+                name, MAX_MATRIX_QUBITS
+            )))
+    }
+
+    /// A standard or defined gate, given its parameters
+    fn unitary(&self, name: &str, token: &Spanned, params: &[(Expression, Spanned)]) -> Result<Unitary> {
+        let num_params = standard_params(name)
+            .or_else(|| self.definitions.get(name).map(|definition| definition.num_params))
+            .ok_or_else(|| token.error(format!("the gate `{}` is not defined", name)))?;
+
+        if params.len() != num_params {
+            return Err(token.error(format!(
+                "the gate `{}` takes {} parameters, but {} were given",
+                name,
+                num_params,
+                params.len()
+            )));
+        }
+
+        let values: Vec<Complex> = params.iter().map(|param| param.0.evaluate(&[])).collect();
+        if standard_params(name).is_some() {
+            let mut real = Vec::new();
+            for (value, param) in values.iter().zip(params) {
+                if value.im.abs() > TOLERANCE {
+                    return Err(param.1.error("the parameters of standard gates must be real"));
+                }
+                real.push(value.re);
+            }
+
+            return Ok(standard(name, &real));
+        }
+
+        let base = self.definitions[name]
+            .base(&values)
+            .map_err(|_| token.error(format!("the matrix of `{}` is not unitary", name)))?;
+
+        Ok(Unitary {
+            controls: vec![],
+            base,
+        })
+    }
+
+    /// The parameters of a gate in parentheses, if there are any
+    fn parameters(&mut self) -> Result<Vec<(Expression, Spanned)>> {
+        let mut params = Vec::new();
+        if self.tokens.at("(") {
+            let _ = self.tokens.next();
+            while !self.tokens.at(")") {
+                if !params.is_empty() {
+                    let _ = self.tokens.expect(",")?;
+                }
+                let token = self.tokens.peek().clone();
+                params.push((self.sum(&[])?, token));
+            }
+            let _ = self.tokens.next();
+        }
+
+        Ok(params)
+    }
+
+    /// Terms separated by `+` and `-`. `names` are the names of the
+    /// parameters of the enclosing gate definition.
+    fn sum(&mut self, names: &[String]) -> Result<Expression> {
+        let mut expression = self.product(names)?;
+        while self.tokens.at("+") || self.tokens.at("-") {
+            let op = if self.tokens.at("+") { "+" } else { "-" };
+            let _ = self.tokens.next();
+            expression = Expression::Binary(op, Box::new(expression), Box::new(self.product(names)?));
+        }
+
+        Ok(expression)
+    }
+
+    /// Factors separated by `*` and `/`
+    fn product(&mut self, names: &[String]) -> Result<Expression> {
+        let mut expression = self.power(names)?;
+        while self.tokens.at("*") || self.tokens.at("/") {
+            let op = if self.tokens.at("*") { "*" } else { "/" };
+            let _ = self.tokens.next();
+            expression = Expression::Binary(op, Box::new(expression), Box::new(self.power(names)?));
+        }
+
+        Ok(expression)
+    }
+
+    /// A power, which is right associative. In Quil, a sign binds more
+    /// tightly, so `-2^2` is 4.
+    fn power(&mut self, names: &[String]) -> Result<Expression> {
+        let base = self.unary(names)?;
+        if self.tokens.at("^") {
+            let _ = self.tokens.next();
+            return Ok(Expression::Binary("^", Box::new(base), Box::new(self.power(names)?)));
+        }
+
+        Ok(base)
+    }
+
+    /// A signed expression
+    fn unary(&mut self, names: &[String]) -> Result<Expression> {
+        if self.tokens.at("-") {
+            let _ = self.tokens.next();
+            return Ok(Expression::Negate(Box::new(self.unary(names)?)));
+        }
+        if self.tokens.at("+") {
+            let _ = self.tokens.next();
+            return self.unary(names);
+        }
+
+        self.primary(names)
+    }
+
+    /// A number, parameter, function call or parenthesised expression
+    fn primary(&mut self, names: &[String]) -> Result<Expression> {
+        let token = self.tokens.next();
+        let real = |text: &str| {
+            text.parse::<Real>()
+                .map_err(|_| token.error(format!("invalid number {}", text)))
+        };
+
+        match token.token {
+            Token::Integer(value) => Ok(Expression::Number(Complex::new(value as Real, 0.0))),
+            Token::Real(ref text) => Ok(Expression::Number(Complex::new(real(text)?, 0.0))),
+            Token::Imaginary(ref text) => Ok(Expression::Number(Complex::new(0.0, real(text)?))),
+            Token::Identifier(ref name) if name == "pi" => {
+                Ok(Expression::Number(Complex::new(PI, 0.0)))
+            }
+            Token::Identifier(ref name) if name == "i" => {
+                Ok(Expression::Number(Complex::new(0.0, 1.0)))
+            }
+            Token::Identifier(ref name) => {
+                let function = function(name)
+                    .ok_or_else(|| token.error(format!("`{}` is not defined", name)))?;
+                let _ = self.tokens.expect("(")?;
+                let operand = self.sum(names)?;
+                let _ = self.tokens.expect(")")?;
+
+                Ok(Expression::Function(function, Box::new(operand)))
+            }
+            Token::Symbol("%") => {
+                let (name, name_token) = self.tokens.identifier("a parameter name")?;
+                names
+                    .iter()
+                    .position(|param| *param == name)
+                    .map(Expression::Parameter)
+                    .ok_or_else(|| name_token.error(format!("`%{}` is not defined", name)))
+            }
+            Token::Symbol("(") => {
+                let expression = self.sum(names)?;
+                let _ = self.tokens.expect(")")?;
+                Ok(expression)
+            }
+            _ => Err(token.error(format!("expected an expression, found {}", describe(&token)))),
+        }
+    }
+}
+
+/// The operation applying a gate to qubits listed as in Quil, or `None` if
+/// it needs a matrix on more than `MAX_MATRIX_QUBITS` qubits
+fn operation(unitary: &Unitary, qubits: &[i32]) -> Option<Operation> {
+    let (control_qubits, targets) = qubits.split_at(unitary.controls.len());
+    let controls: Vec<Control> = control_qubits
+        .iter()
+        .zip(&unitary.controls)
+        .map(|(&qubit, &value)| {
+            if value {
+                Control::Positive(qubit)
+            } else {
+                Control::Negative(qubit)
+            }
+        })
+        .collect();
+    let positive = unitary.controls.iter().all(|&value| value);
+
+    let operation = match unitary.base {
+        Base::Single(gate) => match controls.len() {
+            0 => Operation::Gate {
+                target: targets[0],
+                gate,
+            },
+            1 if positive => Operation::ControlledGate {
+                control: qubits[0],
+                target: targets[0],
+                gate,
+            },
+            2 if positive && gate == x() => Operation::Toffoli {
+                control1: qubits[0],
+                control2: qubits[1],
+                target: targets[0],
+            },
+            _ => Operation::MultiControlledGate {
+                controls,
+                target: targets[0],
+                gate,
+            },
+        },
+        Base::Swap if controls.is_empty() => Operation::Swap {
+            first: targets[0],
+            second: targets[1],
+        },
+        ref base => {
+            if qubits.len() > MAX_MATRIX_QUBITS as usize {
+                return None;
+            }
+
+            // The first qubit in Quil is the highest bit of the matrix
+            Operation::Matrix {
+                qubits: qubits.iter().rev().cloned().collect(),
+                gate: controlled_matrix(&unitary.controls, &base.matrix()),
+            }
+        }
+    };
+
+    Some(operation)
+}
+
+/// The matrix of a gate with controls, which are the highest bits of its
+/// basis states, the first control being the highest
+fn controlled_matrix(controls: &[bool], gate: &MatrixGate) -> MatrixGate {
+    let inner = gate.dimension();
+    let dim = inner << controls.len();
+    let active = controls
+        .iter()
+        .fold(0, |value, &control| (value << 1) | control as usize);
+
+    let matrix = (0..dim * dim)
+        .map(|i| {
+            let (row, col) = (i / dim, i % dim);
+            if row / inner != col / inner {
+                Complex::new(0.0, 0.0)
+            } else if row / inner == active {
+                gate.matrix()[(row % inner) * inner + col % inner]
+            } else if row == col {
+                Complex::new(1.0, 0.0)
+            } else {
+                Complex::new(0.0, 0.0)
+            }
+        })
+        .collect();
+
+    MatrixGate::new(matrix).expect("a controlled unitary is unitary")
+}
+
+/// The operations of some instructions, and the label and position of the
+/// `JUMP` they end with, if it skips an else block
+type Block = (Vec<Operation>, Option<(String, Spanned)>);
+
+/// Turns the instructions into operations, by putting the instructions which
+/// a jump skips over in a conditional operation
+struct Flow {
+    instructions: Vec<(Instruction, Spanned)>,
+    position: usize,
+    /// The labels which end the code skipped by the enclosing jumps, innermost last
+    ends: Vec<String>,
+}
+
+impl Flow {
+    /// Check that every jump goes forward to a label, and each label is unique
+    fn new(instructions: Vec<(Instruction, Spanned)>) -> Result<Flow> {
+        let mut labels = HashMap::new();
+        for (position, instruction) in instructions.iter().enumerate() {
+            if let Instruction::Label(ref label) = instruction.0 {
+                if labels.insert(label.clone(), position).is_some() {
+                    return Err(instruction.1.error(format!("the label @{} is already defined", label)));
+                }
+            }
+        }
+
+        for (position, instruction) in instructions.iter().enumerate() {
+            let label = match instruction.0 {
+                Instruction::Jump(ref label) | Instruction::JumpWhen { ref label, .. } => label,
+                _ => continue,
+            };
+
+            match labels.get(label) {
+                None => return Err(instruction.1.error(format!("the label @{} is not defined", label))),
+                Some(&target) if target < position => {
+                    return Err(instruction.1.error(format!(
+                        "jumping back to @{} makes a loop, which a circuit can't represent",
+                        label
+                    )))
+                }
+                Some(_) => {}
+            }
+        }
+
+        Ok(Flow {
+            instructions,
+            position: 0,
+            ends: Vec::new(),
+        })
+    }
+
+    /// The operations of the instructions up to the label `end`, or the end
+    /// of the program if it is `None`. The label isn't consumed.
+    fn block(&mut self, end: Option<&str>) -> Result<Block> {
+        let mut operations = Vec::new();
+
+        while self.position < self.instructions.len() {
+            let (instruction, token) = self.instructions[self.position].clone();
+            match instruction {
+                Instruction::Label(ref label) if Some(label.as_str()) == end => break,
+                Instruction::Label(ref label) if self.ends.contains(label) => {
+                    return Err(token.error(format!(
+                        "the code skipped by a jump to @{} must not contain @{}, as it is the end \
+                         of the code skipped by another jump",
+                        end.unwrap_or_default(),
+                        label
+                    )))
+                }
+                Instruction::Label(_) => self.position += 1,
+                Instruction::Operation(operation) => {
+                    operations.push(*operation);
+                    self.position += 1;
+                }
+                Instruction::Halt => {
+                    if self.position + 1 != self.instructions.len() {
+                        return Err(token.error("HALT is only supported at the end of the program"));
+                    }
+                    self.position += 1;
+                }
+                Instruction::Jump(label) => {
+                    let ends_block = match self.instructions.get(self.position + 1) {
+                        Some(&(Instruction::Label(ref next), _)) => Some(next.as_str()) == end,
+                        _ => false,
+                    };
+                    if !ends_block {
+                        return Err(token.error(
+                            "JUMP is only supported at the end of the code skipped by \
+                             JUMP-WHEN or JUMP-UNLESS, to skip an else block",
+                        ));
+                    }
+
+                    self.position += 1;
+                    return Ok((operations, Some((label, token))));
+                }
+                Instruction::JumpWhen { label, bit, when } => {
+                    self.position += 1;
+                    let conditional = self.conditional(&label, bit, when, &token)?;
+                    operations.extend(conditional);
+                }
+            }
+        }
+
+        Ok((operations, None))
+    }
+
+    /// The code skipped by a jump to `label` when `bit` is `when`, and the
+    /// else block it may have
+    fn conditional(&mut self, label: &str, bit: usize, when: bool, token: &Spanned) -> Result<Vec<Operation>> {
+        self.ends.push(label.to_string());
+        let (skipped, otherwise) = self.block(Some(label))?;
+        let _ = self.ends.pop();
+
+        // The skipped code runs when the jump isn't taken
+        let value = if when { 0 } else { 1 };
+        let shared = self.ends.iter().any(|end| end == label);
+
+        let end = match otherwise {
+            Some((end, _)) => end,
+            None => {
+                if !shared {
+                    self.position += 1;
+                }
+                return Ok(vec![condition(bit, value, skipped)]);
+            }
+        };
+
+        // The else block is only reached by taking the jump, so the bit must
+        // still hold the value it had then
+        if shared {
+            return Err(token.error(format!(
+                "@{} can't start an else block, as it also ends the code skipped by another jump",
+                label
+            )));
+        }
+        if skipped.iter().any(|operation| measures(operation, bit)) {
+            return Err(token.error(
+                "the bit tested by the jump can't be measured before its else block",
+            ));
+        }
+        self.position += 1;
+
+        self.ends.push(end.clone());
+        let (taken, otherwise) = self.block(Some(&end))?;
+        let _ = self.ends.pop();
+        if let Some((_, jump)) = otherwise {
+            return Err(jump.error(
+                "JUMP is only supported at the end of the code skipped by \
+                 JUMP-WHEN or JUMP-UNLESS, to skip an else block",
+            ));
+        }
+        if !self.ends.contains(&end) {
+            self.position += 1;
+        }
+
+        Ok(vec![
+            condition(bit, value, skipped),
+            condition(bit, 1 - value, taken),
+        ])
+    }
+}
+
+/// A conditional operation on a single bit. If the only operation is a
+/// condition on the following bits, as when several jumps to the same label
+/// test one bit each, the two are combined.
+fn condition(bit: usize, value: u64, operations: Vec<Operation>) -> Operation {
+    if let [Operation::Conditional {
+        ref bits,
+        value: inner,
+        operations: ref inner_operations,
+    }] = operations[..]
+    {
+        if bits.start == bit + 1 && bits.len() < 64 {
+            return Operation::Conditional {
+                bits: bit..bits.end,
+                value: value | inner << 1,
+                operations: inner_operations.clone(),
+            };
+        }
+    }
+
+    Operation::Conditional {
+        bits: bit..bit + 1,
+        value,
+        operations,
+    }
+}
+
+/// Whether an operation measures a qubit into the bit
+fn measures(operation: &Operation, bit: usize) -> bool {
+    match *operation {
+        Operation::Measure { bit: measured, .. } => measured == bit,
+        Operation::Conditional { ref operations, .. } => {
+            operations.iter().any(|operation| measures(operation, bit))
+        }
+        _ => false,
+    }
+}
@@ -1,35 +1,182 @@
-use ocl::enums::DeviceInfo::Type;
-use ocl::{Buffer, MemFlags, ProQue};
-use num_complex::Complex32;
 use std::fmt;
 use std::collections::HashMap;
-use rand::{self, random};
+use std::mem::size_of;
+use std::ops::Range;
+use rand::{self, Isaac64Rng, Rng, SeedableRng};
 use rand::distributions::{Normal, Sample};
 
-use kernel::KERNEL;
-use gates::Gate;
-use gates::{h, r, s, t, x, y, z};
+use arithmetic::{Arithmetic, Permutation};
+use backends::{Backend, OpenCL};
+use circuit::{Circuit, Operation};
+use device::Device;
+use error::{Error, Result};
+use precision::{Complex, Real};
+use precision::consts::PI;
+use gates::{Control, Gate, MatrixGate, TwoQubitGate};
+use oracle::TruthTable;
+use gates::{h, r, rx, ry, rz, s, sdg, sqrt_x, t, tdg, u3, x, y, z};
 
 /// Representation of a quantum register
-
-#[derive(Debug)]
+///
+/// Methods which can fail have a `try_` variant which returns a `qcgpu::Result`,
+/// the other methods panic on failure.
+///
+/// Every random number used by the register, for measurement and decoherence,
+/// is drawn from its random number generator. Use `with_seed` or `set_rng`
+/// to make a simulation reproducible.
 pub struct State {
-    /// The OpenCL Buffer for the state vector
-    pub buffer: Buffer<Complex32>,
-    pro_que: ProQue,
+    /// The backend storing the state vector. Use the method `info()` to get the devices identifier
+    backend: Box<dyn Backend>,
+    /// The source of randomness for measurement and decoherence
+    rng: Box<dyn Rng + Send>,
     /// Number of amplitudes stored in the state vector
     pub num_amps: usize,
     /// Number of qubits in the register
     pub num_qubits: u32,
-    /// The OpenCL Backend used. Use the method `info()` to get the devices identifier
-    pub backend: usize,
 
     /// The amount of decoherence
     #[cfg(feature = "decoherence")]
     pub decoherence: f32,
 }
 
+/// The number of amplitudes in a register of `num_qubits` qubits,
+/// checking that the whole state vector can be indexed.
+fn num_amps(num_qubits: u32) -> Result<usize> {
+    1_usize
+        .checked_shl(num_qubits)
+        .filter(|num_amps| num_amps.checked_mul(size_of::<Complex>()).is_some())
+        .ok_or(Error::WidthOverflow(num_qubits))
+}
+
+/// A generator seeded from the thread's generator. Unlike `rand::thread_rng`,
+/// it can be moved to another thread along with the register.
+fn random_rng() -> Isaac64Rng {
+    rand::thread_rng().gen()
+}
+
+/// Parse a bit string such as `|0110>` into its width and value
+fn parse_bit_string(bit_string: &str) -> Result<(u32, u64)> {
+    let bits = bit_string.to_string().replace("|", "").replace(">", "");
+
+    if bits.is_empty() || bits.chars().any(|c| c != '0' && c != '1') {
+        return Err(Error::InvalidBitString(bit_string.to_string()));
+    }
+
+    let num_qubits = bits.len() as u32;
+    if num_qubits > 64 {
+        return Err(Error::WidthOverflow(num_qubits));
+    }
+
+    let value = u64::from_str_radix(bits.as_str(), 2)
+        .map_err(|_| Error::InvalidBitString(bit_string.to_string()))?;
+
+    Ok((num_qubits, value))
+}
+
+/// Options for the quantum Fourier transform
+///
+/// The default is the exact transform, including the swaps which reverse the
+/// order of the qubits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QftOptions {
+    /// Whether to reverse the order of the qubits at the end of the transform.
+    /// Omitting the swaps leaves the result in bit reversed order, which is
+    /// fine when it is only going to be measured.
+    pub swaps: bool,
+    /// The approximate transform only applies the rotations by pi/2^d for
+    /// d up to the cutoff. The smaller rotations change the result very
+    /// little, so dropping them saves time for wide ranges of qubits.
+    pub cutoff: Option<u32>,
+}
+
+impl Default for QftOptions {
+    fn default() -> QftOptions {
+        QftOptions {
+            swaps: true,
+            cutoff: None,
+        }
+    }
+}
+
+/// The lowest qubit the rotations of a layer of the quantum Fourier
+/// transform are controlled by, so rotations by pi/2^d are left out for
+/// d greater than the cutoff
+fn qft_layer_low(qubits: &Range<i32>, target: i32, options: QftOptions) -> i32 {
+    match options.cutoff {
+        Some(cutoff) => qubits.start.max(target - cutoff.min(64) as i32),
+        None => qubits.start,
+    }
+}
+
+/// A gate of the quantum Fourier transform, when it is applied or written
+/// out one gate at a time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum QftGate {
+    /// A Hadamard gate
+    H(i32),
+    /// A rotation `r(angle)` of the target, controlled by the control
+    Rotation { control: i32, target: i32, angle: Real },
+    /// A swap of two qubits
+    Swap(i32, i32),
+}
+
+/// The gates of the quantum Fourier transform of a range of qubits, or of
+/// its inverse.
+///
+/// Each qubit, from the highest down, has a Hadamard gate applied, followed
+/// by rotations controlled by the qubits below it. The swaps then reverse
+/// the order of the qubits.
+pub(crate) fn qft_sequence(qubits: &Range<i32>, options: QftOptions, inverse: bool) -> Vec<QftGate> {
+    // The Hadamard gates and swaps are their own inverses, so the inverse
+    // transform is the same gates in reverse order, with opposite angles
+    let sign = if inverse { -1.0 } else { 1.0 };
+    let mut gates = Vec::new();
+
+    for target in qubits.clone().rev() {
+        gates.push(QftGate::H(target));
+        for control in qft_layer_low(qubits, target, options)..target {
+            let angle = sign * PI / Real::powi(2.0, target - control);
+            gates.push(QftGate::Rotation {
+                control,
+                target,
+                angle,
+            });
+        }
+    }
+
+    if options.swaps {
+        for i in 0..(qubits.end - qubits.start) / 2 {
+            gates.push(QftGate::Swap(qubits.start + i, qubits.end - 1 - i));
+        }
+    }
+
+    if inverse {
+        gates.reverse();
+    }
+
+    gates
+}
+
 impl State {
+    /// The number of bytes needed to store the state vector of a register
+    /// with the given number of qubits.
+    ///
+    /// Backends check this against the memory available before allocating a
+    /// register, and return `Error::OutOfMemory` if it doesn't fit. Arithmetic
+    /// operations need twice as much while they are applied, for a second copy
+    /// of the state vector.
+    ///
+    /// ```rust
+    /// # extern crate qcgpu;
+    /// use qcgpu::{Complex, State};
+    /// use std::mem::size_of;
+    ///
+    /// assert_eq!(State::memory_required(30).unwrap(), (size_of::<Complex>() << 30) as u64);
+    /// ```
+    pub fn memory_required(num_qubits: u32) -> Result<u64> {
+        Ok((num_amps(num_qubits)? * size_of::<Complex>()) as u64)
+    }
+
     /// Create a new quantum register, with a given number of qubits.
     /// The backend is the OpenCL ID of the accelerator to use.
     ///
@@ -39,45 +186,60 @@ impl State {
     /// # extern crate qcgpu;
     /// let state = qcgpu::State::new(2,0);
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the OpenCL device is unavailable, or the register does not fit on it.
+    /// See `try_new` for a version that returns an error instead.
     pub fn new(num_qubits: u32, backend: usize) -> State {
-        let num_amps = 2_u32.pow(num_qubits) as usize;
-
-        let ocl_pq = ProQue::builder()
-            .src(KERNEL)
-            .device(backend)
-            .dims(num_amps)
-            .build()
-            .expect("Error Building ProQue");
-
-        // create a temporary vector with the source buffer
-        let source_buffer: Buffer<Complex32> = Buffer::builder()
-            .queue(ocl_pq.queue().clone())
-            .flags(MemFlags::new().read_write())
-            .len(num_amps)
-            .build()
-            .expect("Source Buffer");
-
-        let apply = ocl_pq
-            .kernel_builder("initalize_register")
-            .arg(&source_buffer)
-            .arg(0)
-            .build()
-            .unwrap();
-
-        unsafe {
-            apply.enq().unwrap();
-        }
+        State::try_new(num_qubits, backend).unwrap()
+    }
+
+    /// Create a new quantum register, with a given number of qubits.
+    /// The backend is the OpenCL ID of the accelerator to use.
+    ///
+    /// Returns an error if the device is unavailable, or the register does not fit on it.
+    pub fn try_new(num_qubits: u32, backend: usize) -> Result<State> {
+        State::try_with_backend(num_qubits, OpenCL::new(backend)?)
+    }
+
+    /// Create a new quantum register, with a given number of qubits,
+    /// stored on the given backend.
+    ///
+    /// The register will be initialized in the state |00...0>
+    ///
+    /// ```rust
+    /// # extern crate qcgpu;
+    /// use qcgpu::backends::Cpu;
+    ///
+    /// let state = qcgpu::State::with_backend(2, Cpu::new());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the register could not be allocated.
+    /// See `try_with_backend` for a version that returns an error instead.
+    pub fn with_backend<B: Backend + 'static>(num_qubits: u32, backend: B) -> State {
+        State::try_with_backend(num_qubits, backend).unwrap()
+    }
 
-        State {
-            buffer: source_buffer,
-            pro_que: ocl_pq,
+    /// Create a new quantum register, with a given number of qubits,
+    /// stored on the given backend.
+    ///
+    /// Returns an error if the register could not be allocated.
+    pub fn try_with_backend<B: Backend + 'static>(num_qubits: u32, mut backend: B) -> Result<State> {
+        let num_amps = num_amps(num_qubits)?;
+        backend.allocate(num_qubits, 0)?;
+
+        Ok(State {
+            backend: Box::new(backend),
+            rng: Box::new(random_rng()),
             num_amps,
             num_qubits,
-            backend,
 
             #[cfg(feature = "decoherence")]
             decoherence: 0.0,
-        }
+        })
     }
 
     /// Create a new quantum register, starting in the
@@ -88,220 +250,701 @@ impl State {
     /// # extern crate qcgpu;
     /// let state = qcgpu::State::from_bit_string("|00>", 1);
     /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bit string is invalid, the OpenCL device is unavailable,
+    /// or the register does not fit on it.
+    /// See `try_from_bit_string` for a version that returns an error instead.
     pub fn from_bit_string(bit_string: &str, backend: usize) -> State {
-        let bits = bit_string.to_string().replace("|", "").replace(">", "");
-        let num_amps = 2 << (bits.len() - 1);
-
-        let ocl_pq = ProQue::builder()
-            .src(KERNEL)
-            .device(backend)
-            .dims(num_amps)
-            .build()
-            .expect("Error Building ProQue");
-
-        let value = i32::from_str_radix(bits.as_str(), 2).unwrap();
-
-        // create a temporary vector with the source buffer
-        let source_buffer: Buffer<Complex32> = Buffer::builder()
-            .queue(ocl_pq.queue().clone())
-            .flags(MemFlags::new().read_write())
-            .len(num_amps)
-            .build()
-            .expect("Source Buffer");
-
-        let apply = ocl_pq
-            .kernel_builder("initalize_register")
-            .arg(&source_buffer)
-            .arg(value)
-            .build()
-            .unwrap();
-
-        unsafe {
-            apply.enq().unwrap();
-        }
+        State::try_from_bit_string(bit_string, backend).unwrap()
+    }
+
+    /// Create a new quantum register, starting in the
+    /// State given. The backend is the OpenCL ID of the
+    /// accelerator to use.
+    ///
+    /// Returns an error if the bit string is invalid, the OpenCL device is unavailable,
+    /// or the register does not fit on it.
+    pub fn try_from_bit_string(bit_string: &str, backend: usize) -> Result<State> {
+        State::try_from_bit_string_with_backend(bit_string, OpenCL::new(backend)?)
+    }
 
-        State {
-            buffer: source_buffer,
-            pro_que: ocl_pq,
+    /// Create a new quantum register, starting in the
+    /// State given, stored on the given backend.
+    ///
+    /// ```rust
+    /// # extern crate qcgpu;
+    /// use qcgpu::backends::Cpu;
+    ///
+    /// let state = qcgpu::State::from_bit_string_with_backend("|01>", Cpu::new());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bit string is invalid, or the register could not be allocated.
+    /// See `try_from_bit_string_with_backend` for a version that returns an error instead.
+    pub fn from_bit_string_with_backend<B: Backend + 'static>(bit_string: &str, backend: B) -> State {
+        State::try_from_bit_string_with_backend(bit_string, backend).unwrap()
+    }
+
+    /// Create a new quantum register, starting in the
+    /// State given, stored on the given backend.
+    ///
+    /// Returns an error if the bit string is invalid, or the register could not be allocated.
+    pub fn try_from_bit_string_with_backend<B: Backend + 'static>(
+        bit_string: &str,
+        mut backend: B,
+    ) -> Result<State> {
+        let (num_qubits, value) = parse_bit_string(bit_string)?;
+        let num_amps = num_amps(num_qubits)?;
+        backend.allocate(num_qubits, value)?;
+
+        Ok(State {
+            backend: Box::new(backend),
+            rng: Box::new(random_rng()),
             num_amps,
-            num_qubits: bits.len() as u32,
-            backend,
+            num_qubits,
 
             #[cfg(feature = "decoherence")]
             decoherence: 0.0,
+        })
+    }
+
+    /// Seed the register's random number generator, so that measurements and
+    /// decoherence give the same results every time the program is run.
+    ///
+    /// ```rust
+    /// # extern crate qcgpu;
+    /// use qcgpu::State;
+    /// use qcgpu::backends::Cpu;
+    ///
+    /// let mut first = State::with_backend(4, Cpu::new()).with_seed(42);
+    /// let mut second = State::with_backend(4, Cpu::new()).with_seed(42);
+    /// first.apply_all(qcgpu::gates::h());
+    /// second.apply_all(qcgpu::gates::h());
+    ///
+    /// assert_eq!(first.measure_many(100), second.measure_many(100));
+    /// ```
+    pub fn with_seed(mut self, seed: u64) -> State {
+        self.set_rng(Isaac64Rng::from_seed(&[seed][..]));
+        self
+    }
+
+    /// Replace the register's random number generator. The generator must be
+    /// `Send`, so that the register can still be moved between threads.
+    pub fn set_rng<R: Rng + Send + 'static>(&mut self, rng: R) {
+        self.rng = Box::new(rng);
+    }
+
+    /// Check that a qubit index is inside of the register
+    fn check_qubit(&self, qubit: i32) -> Result<()> {
+        if qubit < 0 || qubit as u32 >= self.num_qubits {
+            return Err(Error::InvalidQubit {
+                qubit,
+                num_qubits: self.num_qubits,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Check that every qubit index is inside of the register,
+    /// and that no qubit is given twice
+    fn check_distinct_qubits(&self, qubits: &[i32]) -> Result<()> {
+        for (i, &qubit) in qubits.iter().enumerate() {
+            self.check_qubit(qubit)?;
+
+            if qubits[..i].contains(&qubit) {
+                return Err(Error::DuplicateQubit(qubit));
+            }
         }
+
+        Ok(())
     }
 
     /// Apply a gate to the target qubit
+    ///
+    /// # Panics
+    ///
+    /// Panics if the target is outside of the register, or the backend fails.
+    /// See `try_apply_gate` for a version that returns an error instead.
     pub fn apply_gate(&mut self, target: i32, gate: Gate) {
-        // create a temporary vector with the source buffer
-        let result_buffer: Buffer<Complex32> = self.pro_que.create_buffer().unwrap();
-
-        let apply = self.pro_que
-            .kernel_builder("apply_gate")
-            .arg(&self.buffer)
-            .arg(&result_buffer)
-            .arg(target)
-            .arg(gate.a)
-            .arg(gate.b)
-            .arg(gate.c)
-            .arg(gate.d)
-            .build()
-            .unwrap();
-
-        unsafe {
-            apply.enq().unwrap();
-        }
+        self.try_apply_gate(target, gate).unwrap()
+    }
 
-        self.buffer = result_buffer;
+    /// Apply a gate to the target qubit
+    ///
+    /// Returns an error if the target is outside of the register, or the backend fails.
+    pub fn try_apply_gate(&mut self, target: i32, gate: Gate) -> Result<()> {
+        self.check_qubit(target)?;
+        self.backend.apply_gate(target, gate)
     }
 
     /// Apply a gate to every qubit in the register
     pub fn apply_all(&mut self, gate: Gate) {
+        self.try_apply_all(gate).unwrap()
+    }
+
+    /// Apply a gate to every qubit in the register
+    ///
+    /// Returns an error if the backend fails.
+    pub fn try_apply_all(&mut self, gate: Gate) -> Result<()> {
         for i in 0..self.num_qubits as i32 {
-            self.apply_gate(i, gate);
+            self.try_apply_gate(i, gate)?;
         }
+
+        Ok(())
     }
 
     /// Apply a gate to the register if the control qubit is 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either qubit is outside of the register, the qubits are the same,
+    /// or the backend fails.
+    /// See `try_apply_controlled_gate` for a version that returns an error instead.
     pub fn apply_controlled_gate(&mut self, control: i32, target: i32, gate: Gate) {
-        let result_buffer: Buffer<Complex32> = self.pro_que.create_buffer().unwrap();
-
-        let apply = self.pro_que
-            .kernel_builder("apply_controlled_gate")
-            .arg(&self.buffer)
-            .arg(&result_buffer)
-            .arg(control)
-            .arg(target)
-            .arg(gate.a)
-            .arg(gate.b)
-            .arg(gate.c)
-            .arg(gate.d)
-            .build()
-            .unwrap();
-
-        unsafe {
-            apply.enq().unwrap();
+        self.try_apply_controlled_gate(control, target, gate).unwrap()
+    }
+
+    /// Apply a gate to the register if the control qubit is 1.
+    ///
+    /// Returns an error if either qubit is outside of the register, the qubits are the same,
+    /// or the backend fails.
+    pub fn try_apply_controlled_gate(&mut self, control: i32, target: i32, gate: Gate) -> Result<()> {
+        self.try_apply_multi_controlled_gate(&[Control::Positive(control)], target, gate)
+    }
+
+    /// Apply a gate to the target qubit, for the states where every control
+    /// qubit has its required value. `Control::Positive` controls must be 1,
+    /// and `Control::Negative` controls must be 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any qubit is outside of the register, a qubit is given twice,
+    /// or the backend fails.
+    /// See `try_apply_multi_controlled_gate` for a version that returns an error instead.
+    pub fn apply_multi_controlled_gate(&mut self, controls: &[Control], target: i32, gate: Gate) {
+        self.try_apply_multi_controlled_gate(controls, target, gate)
+            .unwrap()
+    }
+
+    /// Apply a gate to the target qubit, for the states where every control
+    /// qubit has its required value. `Control::Positive` controls must be 1,
+    /// and `Control::Negative` controls must be 0.
+    ///
+    /// Returns an error if any qubit is outside of the register, a qubit is given twice,
+    /// or the backend fails.
+    pub fn try_apply_multi_controlled_gate(
+        &mut self,
+        controls: &[Control],
+        target: i32,
+        gate: Gate,
+    ) -> Result<()> {
+        let mut qubits: Vec<i32> = controls.iter().map(Control::qubit).collect();
+        qubits.push(target);
+        self.check_distinct_qubits(&qubits)?;
+
+        let mut control_mask = 0;
+        let mut control_value = 0;
+        for control in controls {
+            control_mask |= 1 << control.qubit();
+            if control.value() {
+                control_value |= 1 << control.qubit();
+            }
         }
 
-        self.buffer = result_buffer;
+        self.backend
+            .apply_multi_controlled_gate(control_mask, control_value, target, gate)?;
 
         #[cfg(feature = "decoherence")]
-        self.decohere();
+        self.try_decohere()?;
+
+        Ok(())
+    }
+
+    /// Apply a two qubit gate to the register. `qubit0` is the low bit of the
+    /// basis states the gate's matrix is written in, and `qubit1` the high bit.
+    ///
+    /// ```rust
+    /// # extern crate qcgpu;
+    /// use qcgpu::State;
+    /// use qcgpu::backends::Cpu;
+    /// use qcgpu::gates::iswap;
+    ///
+    /// let mut state = State::from_bit_string_with_backend("|01>", Cpu::new());
+    /// state.apply_two_qubit_gate(0, 1, iswap());
+    ///
+    /// assert_eq!(state.measure(), 0b10);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if either qubit is outside of the register, the qubits are the same,
+    /// or the backend fails.
+    /// See `try_apply_two_qubit_gate` for a version that returns an error instead.
+    pub fn apply_two_qubit_gate(&mut self, qubit0: i32, qubit1: i32, gate: TwoQubitGate) {
+        self.try_apply_two_qubit_gate(qubit0, qubit1, gate).unwrap()
+    }
+
+    /// Apply a two qubit gate to the register. `qubit0` is the low bit of the
+    /// basis states the gate's matrix is written in, and `qubit1` the high bit.
+    ///
+    /// Returns an error if either qubit is outside of the register, the qubits are the same,
+    /// or the backend fails.
+    pub fn try_apply_two_qubit_gate(
+        &mut self,
+        qubit0: i32,
+        qubit1: i32,
+        gate: TwoQubitGate,
+    ) -> Result<()> {
+        self.check_distinct_qubits(&[qubit0, qubit1])?;
+        self.backend.apply_two_qubit_gate(qubit0, qubit1, gate)?;
+
+        #[cfg(feature = "decoherence")]
+        self.try_decohere()?;
+
+        Ok(())
+    }
+
+    /// Apply a gate on several qubits to the register. The first qubit given
+    /// is the lowest bit of the basis states the gate's matrix is written in.
+    ///
+    /// ```rust
+    /// # extern crate qcgpu;
+    /// use qcgpu::State;
+    /// use qcgpu::backends::Cpu;
+    /// use qcgpu::gates::{iswap, MatrixGate};
+    ///
+    /// let mut state = State::from_bit_string_with_backend("|001>", Cpu::new());
+    /// state.apply_matrix(&[0, 2], &MatrixGate::from(iswap()));
+    ///
+    /// assert_eq!(state.measure(), 0b100);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if any qubit is outside of the register, a qubit is given twice,
+    /// the number of qubits doesn't match the gate, or the backend fails.
+    /// See `try_apply_matrix` for a version that returns an error instead.
+    pub fn apply_matrix(&mut self, qubits: &[i32], gate: &MatrixGate) {
+        self.try_apply_matrix(qubits, gate).unwrap()
+    }
+
+    /// Apply a gate on several qubits to the register. The first qubit given
+    /// is the lowest bit of the basis states the gate's matrix is written in.
+    ///
+    /// Returns an error if any qubit is outside of the register, a qubit is given twice,
+    /// the number of qubits doesn't match the gate, or the backend fails.
+    pub fn try_apply_matrix(&mut self, qubits: &[i32], gate: &MatrixGate) -> Result<()> {
+        if qubits.len() != gate.num_qubits() as usize {
+            return Err(Error::WrongNumberOfQubits {
+                expected: gate.num_qubits(),
+                found: qubits.len(),
+            });
+        }
+        self.check_distinct_qubits(qubits)?;
+        self.backend.apply_matrix(qubits, gate)?;
+
+        #[cfg(feature = "decoherence")]
+        self.try_decohere()?;
+
+        Ok(())
+    }
+
+    /// Apply the quantum Fourier transform to a range of qubits, where the
+    /// first qubit of the range is the least significant bit.
+    ///
+    /// The value `x` of the qubits is mapped to the superposition of every value
+    /// `y` with amplitude e^(2 pi i xy/2^n)/sqrt(2^n). This is the same as
+    /// applying a Hadamard gate and controlled rotations `r(angle)` to each qubit,
+    /// from the highest down, and then reversing the order of the qubits,
+    /// but each qubit's gates are applied in a single pass over the state vector.
+    /// Use `qft_gates` to apply the individual gates instead.
+    ///
+    /// ```rust
+    /// # extern crate qcgpu;
+    /// use qcgpu::State;
+    /// use qcgpu::backends::Cpu;
+    ///
+    /// let mut state = State::from_bit_string_with_backend("|0110>", Cpu::new());
+    /// state.qft(0..4);
+    /// state.inverse_qft(0..4);
+    ///
+    /// assert_eq!(state.measure(), 0b0110);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is outside of the register, or the backend fails.
+    /// See `try_qft` for a version that returns an error instead.
+    pub fn qft(&mut self, qubits: Range<i32>) {
+        self.try_qft(qubits).unwrap()
+    }
+
+    /// Apply the quantum Fourier transform to a range of qubits
+    ///
+    /// Returns an error if the range is outside of the register, or the backend fails.
+    pub fn try_qft(&mut self, qubits: Range<i32>) -> Result<()> {
+        self.try_qft_with(qubits, QftOptions::default())
+    }
+
+    /// Apply the inverse quantum Fourier transform to a range of qubits,
+    /// undoing `qft`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is outside of the register, or the backend fails.
+    /// See `try_inverse_qft` for a version that returns an error instead.
+    pub fn inverse_qft(&mut self, qubits: Range<i32>) {
+        self.try_inverse_qft(qubits).unwrap()
+    }
+
+    /// Apply the inverse quantum Fourier transform to a range of qubits
+    ///
+    /// Returns an error if the range is outside of the register, or the backend fails.
+    pub fn try_inverse_qft(&mut self, qubits: Range<i32>) -> Result<()> {
+        self.try_inverse_qft_with(qubits, QftOptions::default())
+    }
+
+    /// Apply the quantum Fourier transform to a range of qubits, with the swaps
+    /// or the smallest rotations left out as given by `options`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is outside of the register, or the backend fails.
+    /// See `try_qft_with` for a version that returns an error instead.
+    pub fn qft_with(&mut self, qubits: Range<i32>, options: QftOptions) {
+        self.try_qft_with(qubits, options).unwrap()
+    }
+
+    /// Apply the quantum Fourier transform to a range of qubits, with the swaps
+    /// or the smallest rotations left out as given by `options`
+    ///
+    /// Returns an error if the range is outside of the register, or the backend fails.
+    pub fn try_qft_with(&mut self, qubits: Range<i32>, options: QftOptions) -> Result<()> {
+        self.check_range(&qubits)?;
+
+        for target in qubits.clone().rev() {
+            let low = qft_layer_low(&qubits, target, options);
+            self.backend.apply_qft_layer(target, low, false)?;
+        }
+
+        if options.swaps {
+            self.reverse_range(&qubits)?;
+        }
+
+        #[cfg(feature = "decoherence")]
+        self.try_decohere()?;
+
+        Ok(())
+    }
+
+    /// Apply the inverse quantum Fourier transform to a range of qubits, undoing
+    /// `qft_with` with the same options
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is outside of the register, or the backend fails.
+    /// See `try_inverse_qft_with` for a version that returns an error instead.
+    pub fn inverse_qft_with(&mut self, qubits: Range<i32>, options: QftOptions) {
+        self.try_inverse_qft_with(qubits, options).unwrap()
+    }
+
+    /// Apply the inverse quantum Fourier transform to a range of qubits, undoing
+    /// `qft_with` with the same options
+    ///
+    /// Returns an error if the range is outside of the register, or the backend fails.
+    pub fn try_inverse_qft_with(&mut self, qubits: Range<i32>, options: QftOptions) -> Result<()> {
+        self.check_range(&qubits)?;
+
+        if options.swaps {
+            self.reverse_range(&qubits)?;
+        }
+
+        for target in qubits.clone() {
+            let low = qft_layer_low(&qubits, target, options);
+            self.backend.apply_qft_layer(target, low, true)?;
+        }
+
+        #[cfg(feature = "decoherence")]
+        self.try_decohere()?;
+
+        Ok(())
+    }
+
+    /// Apply the quantum Fourier transform, or its inverse, to a range of qubits
+    /// as individual gates: Hadamard gates, rotations `r(angle)` applied with
+    /// `apply_controlled_gate`, and swaps.
+    ///
+    /// The result is the same as `qft_with` or `inverse_qft_with`, which are
+    /// faster as they apply each qubit's gates in a single pass.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is outside of the register, or the backend fails.
+    /// See `try_qft_gates` for a version that returns an error instead.
+    pub fn qft_gates(&mut self, qubits: Range<i32>, options: QftOptions, inverse: bool) {
+        self.try_qft_gates(qubits, options, inverse).unwrap()
+    }
+
+    /// Apply the quantum Fourier transform, or its inverse, to a range of qubits
+    /// as individual gates
+    ///
+    /// Returns an error if the range is outside of the register, or the backend fails.
+    pub fn try_qft_gates(&mut self, qubits: Range<i32>, options: QftOptions, inverse: bool) -> Result<()> {
+        self.check_range(&qubits)?;
+
+        for gate in qft_sequence(&qubits, options, inverse) {
+            match gate {
+                QftGate::H(target) => self.try_apply_gate(target, h())?,
+                QftGate::Rotation {
+                    control,
+                    target,
+                    angle,
+                } => self.try_apply_controlled_gate(control, target, r(angle))?,
+                QftGate::Swap(first, second) => self.try_swap(first, second)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that every qubit of a range is inside of the register
+    fn check_range(&self, qubits: &Range<i32>) -> Result<()> {
+        if qubits.start < qubits.end {
+            self.check_qubit(qubits.start)?;
+            self.check_qubit(qubits.end - 1)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reverse the order of a range of qubits
+    fn reverse_range(&mut self, qubits: &Range<i32>) -> Result<()> {
+        for i in 0..(qubits.end - qubits.start) / 2 {
+            self.backend.swap(qubits.start + i, qubits.end - 1 - i)?;
+        }
+
+        Ok(())
     }
 
     /// Return the probabilities of each outcome.
     ///
     /// The probabilitity of a state a|x> being measured
     /// is |a|^2.
-    pub fn get_probabilities(&mut self) -> Vec<f32> {
-        let result_buffer: Buffer<f32> = self.pro_que.create_buffer().unwrap();
-
-        let apply = self.pro_que
-            .kernel_builder("calculate_probabilities")
-            .arg(&self.buffer)
-            .arg(&result_buffer)
-            .build()
-            .unwrap();
-
-        unsafe {
-            apply.enq().unwrap();
-        }
+    pub fn get_probabilities(&mut self) -> Vec<Real> {
+        self.try_get_probabilities().unwrap()
+    }
 
-        let mut vec_result = vec![0.0f32; self.num_amps];
-        result_buffer.read(&mut vec_result).enq().unwrap();
+    /// Return the probabilities of each outcome.
+    ///
+    /// Returns an error if the probabilities could not be read from the backend.
+    pub fn try_get_probabilities(&mut self) -> Result<Vec<Real>> {
+        self.backend.probabilities()
+    }
 
-        vec_result
+    /// Return the state vector of the quantum register
+    pub fn get_amplitudes(&mut self) -> Vec<Complex> {
+        self.try_get_amplitudes().unwrap()
     }
 
     /// Return the state vector of the quantum register
-    pub fn get_amplitudes(&mut self) -> Vec<Complex32> {
-        let mut vec_result = vec![Complex32::new(0.0, 0.0); self.num_amps];
-        self.buffer.read(&mut vec_result).enq().unwrap();
+    ///
+    /// Returns an error if the state vector could not be read from the backend.
+    pub fn try_get_amplitudes(&mut self) -> Result<Vec<Complex>> {
+        self.backend.amplitudes()
+    }
+
+    /// Measure the quantum register, returning the measured result.
+    ///
+    /// The register collapses to the measured basis state.
+    pub fn measure(&mut self) -> u64 {
+        self.try_measure().unwrap()
+    }
+
+    /// Measure the quantum register, returning the measured result.
+    ///
+    /// The register collapses to the measured basis state.
+    ///
+    /// Returns an error if the probabilities could not be read from the backend.
+    pub fn try_measure(&mut self) -> Result<u64> {
+        let cumulative = self.backend.cumulative_probabilities()?;
+        let state = self.sample(&cumulative);
+        self.backend.allocate(self.num_qubits, state)?;
 
-        vec_result
+        Ok(state)
     }
 
-    /// Measure the quantum register, returning the measured result
-    pub fn measure(&mut self) -> i32 {
-        let probabilities = self.get_probabilities();
+    /// Draw a basis state from the cumulative probabilities of the register,
+    /// by binary search for the first state whose cumulative probability
+    /// exceeds a uniformly distributed key.
+    fn sample(&mut self, cumulative: &[Real]) -> u64 {
+        // Scale the key by the total, so rounding errors in the
+        // normalisation of the state don't bias the result
+        let total = cumulative[cumulative.len() - 1];
+        let key = self.rng.gen::<Real>() * total;
+
+        let state = cumulative.partition_point(|&probability| probability <= key);
+        state.min(cumulative.len() - 1) as u64
+    }
 
-        let mut key = random::<f32>();
-        if key > 1.0 {
-            key %= 1.0;
-        }
+    /// Measure a single qubit, returning true if it was measured as 1.
+    ///
+    /// The register collapses to the states consistent with the outcome,
+    /// and is renormalised.
+    ///
+    /// ```rust
+    /// # extern crate qcgpu;
+    /// use qcgpu::State;
+    /// use qcgpu::backends::Cpu;
+    ///
+    /// let mut state = State::with_backend(2, Cpu::new());
+    /// state.h(0);
+    /// state.cx(0, 1);
+    ///
+    /// // The qubits are entangled, so they are always measured as the same value
+    /// let first = state.measure_qubit(0);
+    /// assert_eq!(state.measure_qubit(1), first);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the qubit is outside of the register, or the backend fails.
+    /// See `try_measure_qubit` for a version that returns an error instead.
+    pub fn measure_qubit(&mut self, qubit: i32) -> bool {
+        self.try_measure_qubit(qubit).unwrap()
+    }
+
+    /// Measure a single qubit, returning true if it was measured as 1.
+    ///
+    /// The register collapses to the states consistent with the outcome,
+    /// and is renormalised.
+    ///
+    /// Returns an error if the qubit is outside of the register, or the backend fails.
+    pub fn try_measure_qubit(&mut self, qubit: i32) -> Result<bool> {
+        self.check_qubit(qubit)?;
+
+        let probability = self.backend.qubit_probability(qubit)?;
+        let sample = self.rng.gen::<Real>();
+
+        // An outcome less likely than the rounding error of the probability is
+        // never chosen, as renormalising onto it would divide by about zero
+        let outcome = if probability < Real::EPSILON {
+            false
+        } else {
+            probability > 1.0 - Real::EPSILON || sample < probability
+        };
+
+        let outcome_probability = if outcome {
+            probability
+        } else {
+            1.0 - probability
+        };
+        self.backend
+            .collapse(qubit, outcome, 1.0 / outcome_probability.sqrt())?;
+
+        Ok(outcome)
+    }
+
+    /// Measure several qubits, returning the outcomes as an integer whose
+    /// `i`th bit is the value measured for `qubits[i]`.
+    ///
+    /// The register collapses to the states consistent with the outcomes,
+    /// and is renormalised.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any qubit is outside of the register, a qubit is given twice,
+    /// or the backend fails.
+    /// See `try_measure_qubits` for a version that returns an error instead.
+    pub fn measure_qubits(&mut self, qubits: &[i32]) -> u64 {
+        self.try_measure_qubits(qubits).unwrap()
+    }
 
-        let mut i = 0;
-        while i < probabilities.len() {
-            key -= probabilities[i];
-            if key <= 0.0 {
-                break;
+    /// Measure several qubits, returning the outcomes as an integer whose
+    /// `i`th bit is the value measured for `qubits[i]`.
+    ///
+    /// The register collapses to the states consistent with the outcomes,
+    /// and is renormalised.
+    ///
+    /// Returns an error if any qubit is outside of the register, a qubit is given twice,
+    /// or the backend fails.
+    pub fn try_measure_qubits(&mut self, qubits: &[i32]) -> Result<u64> {
+        self.check_distinct_qubits(qubits)?;
+
+        let mut result = 0;
+        for (i, &qubit) in qubits.iter().enumerate() {
+            if self.try_measure_qubit(qubit)? {
+                result |= 1 << i;
             }
-            i += 1;
         }
 
-        i as i32
+        Ok(result)
+    }
+
+    /// Preform multiple measurements, returning the results
+    /// as a HashMap, with the key as the measured state and the value as the
+    /// number of times that result was measured.
+    ///
+    /// Each measurement samples the current state, which is not collapsed.
+    pub fn measure_many(&mut self, num_iterations: i32) -> HashMap<u64, i32> {
+        self.try_measure_many(num_iterations).unwrap()
     }
 
     /// Preform multiple measurements, returning the results
-    /// as a HashMap, with the key as the result and the value as the
-    /// number of times that result was measured
-    pub fn measure_many(&mut self, num_iterations: i32) -> HashMap<String, i32> {
-        let probabilities = self.get_probabilities();
+    /// as a HashMap, with the key as the measured state and the value as the
+    /// number of times that result was measured.
+    ///
+    /// Each measurement samples the current state, which is not collapsed.
+    ///
+    /// Returns an error if the probabilities could not be read from the backend.
+    pub fn try_measure_many(&mut self, num_iterations: i32) -> Result<HashMap<u64, i32>> {
+        self.sample_many(num_iterations, !0)
+    }
+
+    /// Take `num_iterations` samples of the register, counting the results
+    /// after masking them with `mask`.
+    ///
+    /// The cumulative probabilities are calculated once, so each sample is
+    /// a binary search rather than a walk through every state.
+    fn sample_many(&mut self, num_iterations: i32, mask: u64) -> Result<HashMap<u64, i32>> {
+        let cumulative = self.backend.cumulative_probabilities()?;
         let mut num_results = HashMap::new();
 
         for _ in 0..num_iterations {
-            let mut key = random::<f32>();
-            if key > 1.0 {
-                key %= 1.0;
-            }
-
-            let mut i = 0;
-            while i < probabilities.len() {
-                key -= probabilities[i];
-                if key <= 0.0 {
-                    break;
-                }
-                i += 1;
-            }
-            let state = format!("{:0width$b}", i, width = self.num_qubits as usize);
+            let state = self.sample(&cumulative) & mask;
             let count = num_results.entry(state).or_insert(0);
             *count += 1;
         }
 
-        num_results
+        Ok(num_results)
     }
 
     /// Add qubits to the register. The qubits are initialized to zero.
     /// This should be used as scratch space.
     pub fn add_scratch(&mut self, num_scratch: u32) {
-        let num_amps = 2_u32.pow(self.num_qubits + num_scratch) as usize;
-        let ocl_pq = ProQue::builder()
-            .src(KERNEL)
-            .device(self.backend)
-            .dims(num_amps)
-            .build()
-            .expect("Error Building ProQue");
-
-        let mut amps = self.get_amplitudes();
-        amps.extend(vec![
-            Complex32::new(0.0, 0.0);
-            ocl_pq.dims().to_len() - self.pro_que.dims().to_len()
-        ]);
-
-        // create a temporary vector with the source buffer
-        let source_buffer = Buffer::builder()
-            .queue(ocl_pq.queue().clone())
-            .flags(MemFlags::new().read_write().copy_host_ptr())
-            .len(num_amps)
-            .copy_host_slice(&amps)
-            .build()
-            .expect("Source Buffer");
-
-        self.buffer = source_buffer;
-        self.pro_que = ocl_pq;
+        self.try_add_scratch(num_scratch).unwrap()
+    }
+
+    /// Add qubits to the register. The qubits are initialized to zero.
+    ///
+    /// Returns an error if the larger register could not be allocated.
+    pub fn try_add_scratch(&mut self, num_scratch: u32) -> Result<()> {
+        let num_qubits = self.num_qubits
+            .checked_add(num_scratch)
+            .ok_or(Error::WidthOverflow(u32::MAX))?;
+        let num_amps = num_amps(num_qubits)?;
+
+        let mut amps = self.try_get_amplitudes()?;
+        amps.resize(num_amps, Complex::new(0.0, 0.0));
+
+        self.backend.load(&amps)?;
         self.num_amps = num_amps;
-        self.num_qubits += num_scratch;
+        self.num_qubits = num_qubits;
+
+        Ok(())
     }
 
     /// This method allows you to simulate the effects of decoherence on
@@ -321,93 +964,109 @@ impl State {
     /// This method does not have a custom OpenCL kernel, but that will be changed in later updates.
     #[cfg(feature = "decoherence")]
     pub fn decohere(&mut self) {
+        self.try_decohere().unwrap()
+    }
+
+    /// Preforms the actual decoherence of a quantum register based on the parameter `self.decoherence`
+    ///
+    /// Returns an error if the backend fails.
+    #[cfg(feature = "decoherence")]
+    pub fn try_decohere(&mut self) -> Result<()> {
         if self.decoherence != 0.0 {
             let mut normal = Normal::new(0.0, self.decoherence as f64);
 
             for i in 0..1 {
-                let angle = normal.sample(&mut rand::thread_rng());
+                let angle = normal.sample(&mut self.rng);
 
                 // Apply a phase shift according to the normally distributed angle
-                let gate = r(angle as f32);
-                self.apply_gate(i, gate);
+                #[allow(trivial_numeric_casts)]
+                let gate = r(angle as Real);
+                self.try_apply_gate(i, gate)?;
             }
         }
+
+        Ok(())
     }
 
-    /// Measure the scratch qubits. The measurement is discarded, and
-    /// the register size is reduced by `num_to_measure` qubits.
+    /// Measure the scratch qubits, which are the highest `num_to_measure` qubits
+    /// of the register. The measurement is discarded, and the measured qubits
+    /// are removed from the register.
     pub fn measure_scratch(&mut self, num_to_measure: u32) {
-        let num_amps = 2_u32.pow(self.num_qubits - num_to_measure) as usize;
-        let ocl_pq = ProQue::builder()
-            .src(KERNEL)
-            .device(self.backend)
-            .dims(num_amps)
-            .build()
-            .expect("Error Building ProQue");
-
-        let mut amps = self.get_amplitudes();
-        amps.truncate(num_amps);
-
-        // create a temporary vector with the source buffer
-        let source_buffer = Buffer::builder()
-            .queue(ocl_pq.queue().clone())
-            .flags(MemFlags::new().read_write().copy_host_ptr())
-            .len(num_amps)
-            .copy_host_slice(&amps)
-            .build()
-            .expect("Source Buffer");
-
-        self.buffer = source_buffer;
-        self.pro_que = ocl_pq;
+        self.try_measure_scratch(num_to_measure).unwrap()
+    }
+
+    /// Measure the scratch qubits, which are the highest `num_to_measure` qubits
+    /// of the register. The measurement is discarded, and the measured qubits
+    /// are removed from the register.
+    ///
+    /// Returns an error if the register has fewer than `num_to_measure` qubits.
+    pub fn try_measure_scratch(&mut self, num_to_measure: u32) -> Result<()> {
+        if num_to_measure > self.num_qubits {
+            return Err(Error::NotEnoughQubits {
+                requested: num_to_measure,
+                num_qubits: self.num_qubits,
+            });
+        }
+
+        let remaining = self.num_qubits - num_to_measure;
+        let scratch: Vec<i32> = (remaining..self.num_qubits).map(|q| q as i32).collect();
+        let outcome = self.try_measure_qubits(&scratch)? as usize;
+
+        // After the measurement the scratch qubits are in a definite state, so the
+        // remaining qubits are described by the amplitudes consistent with it.
+        let num_amps = 1 << remaining;
+        let amps = self.try_get_amplitudes()?;
+
+        self.backend
+            .load(&amps[outcome * num_amps..(outcome + 1) * num_amps])?;
         self.num_amps = num_amps;
-        self.num_qubits -= num_to_measure;
+        self.num_qubits = remaining;
+
+        Ok(())
+    }
+
+    /// Preform multiple measurements of the first `num_to_measure` qubits, returning the
+    /// results as a HashMap, with the key as the measured value of those qubits and the
+    /// value as the number of times that result was measured
+    pub fn measure_first(&mut self, num_to_measure: i32, num_iterations: i32) -> HashMap<u64, i32> {
+        self.try_measure_first(num_to_measure, num_iterations)
+            .unwrap()
     }
 
-    /// Preform multiple measurements of the first `num_to_measure`, returning the results
-    /// as a HashMap, with the key as the result and the value as the
-    /// number of times that result was measured
-    pub fn measure_first(
+    /// Preform multiple measurements of the first `num_to_measure` qubits, returning the
+    /// results as a HashMap, with the key as the measured value of those qubits and the
+    /// value as the number of times that result was measured
+    ///
+    /// Returns an error if the register has fewer than `num_to_measure` qubits.
+    pub fn try_measure_first(
         &mut self,
         num_to_measure: i32,
         num_iterations: i32,
-    ) -> HashMap<String, i32> {
-        let probabilities = self.get_probabilities();
-        let mut num_results = HashMap::new();
-
-        for _ in 0..num_iterations {
-            let mut key = random::<f32>();
-            if key > 1.0 {
-                key %= 1.0;
-            }
-
-            let mut i = 0;
-            while i < probabilities.len() {
-                key -= probabilities[i];
-                if key <= 0.0 {
-                    break;
-                }
-                i += 1;
-            }
-            let state = format!("{:0width$b}", i, width = self.num_qubits as usize);
-            let num_chars = state.len();
-            let result = state
-                .chars()
-                .skip(num_chars - num_to_measure as usize)
-                .take(num_to_measure as usize)
-                .collect();
-            let count = num_results.entry(result).or_insert(0);
-            *count += 1;
+    ) -> Result<HashMap<u64, i32>> {
+        if num_to_measure < 0 || num_to_measure as u32 > self.num_qubits {
+            return Err(Error::NotEnoughQubits {
+                requested: num_to_measure as u32,
+                num_qubits: self.num_qubits,
+            });
         }
 
-        num_results
+        let mask = 1_u64
+            .checked_shl(num_to_measure as u32)
+            .map_or(!0, |bit| bit - 1);
+        self.sample_many(num_iterations, mask)
     }
 
     /// Print Information About The Device
     pub fn info(&self) {
-        println!(
-            "Device type: {:?}",
-            self.pro_que.device().info(Type).unwrap()
-        )
+        println!("{}", self.backend.info())
+    }
+
+    /// Information about the OpenCL device the register is stored on.
+    ///
+    /// Returns `None` if the register is not stored on an OpenCL device,
+    /// for example when using the `Cpu` backend.
+    pub fn device(&self) -> Option<Device> {
+        self.backend.device()
     }
 
     /* Ease Of Access / shorthand Functions*/
@@ -472,6 +1131,76 @@ impl State {
         self.decohere();
     }
 
+    /// S† Gate
+    /// Shorthand Method
+    ///
+    /// Equivilent to `state.apply_gate(target, sdg());`
+    pub fn sdg(&mut self, target: i32) {
+        self.apply_gate(target, sdg());
+        #[cfg(feature = "decoherence")]
+        self.decohere();
+    }
+
+    /// T† Gate
+    /// Shorthand Method
+    ///
+    /// Equivilent to `state.apply_gate(target, tdg());`
+    pub fn tdg(&mut self, target: i32) {
+        self.apply_gate(target, tdg());
+        #[cfg(feature = "decoherence")]
+        self.decohere();
+    }
+
+    /// Square Root of NOT Gate
+    /// Shorthand Method
+    ///
+    /// Equivilent to `state.apply_gate(target, sqrt_x());`
+    pub fn sqrt_x(&mut self, target: i32) {
+        self.apply_gate(target, sqrt_x());
+        #[cfg(feature = "decoherence")]
+        self.decohere();
+    }
+
+    /// Rotation About the X Axis
+    /// Shorthand Method
+    ///
+    /// Equivilent to `state.apply_gate(target, rx(angle));`
+    pub fn rx(&mut self, target: i32, angle: Real) {
+        self.apply_gate(target, rx(angle));
+        #[cfg(feature = "decoherence")]
+        self.decohere();
+    }
+
+    /// Rotation About the Y Axis
+    /// Shorthand Method
+    ///
+    /// Equivilent to `state.apply_gate(target, ry(angle));`
+    pub fn ry(&mut self, target: i32, angle: Real) {
+        self.apply_gate(target, ry(angle));
+        #[cfg(feature = "decoherence")]
+        self.decohere();
+    }
+
+    /// Rotation About the Z Axis
+    /// Shorthand Method
+    ///
+    /// Equivilent to `state.apply_gate(target, rz(angle));`
+    pub fn rz(&mut self, target: i32, angle: Real) {
+        self.apply_gate(target, rz(angle));
+        #[cfg(feature = "decoherence")]
+        self.decohere();
+    }
+
+    /// Universal Single Qubit Gate
+    /// Shorthand Method
+    ///
+    /// Equivilent to `state.apply_gate(target, u3(theta, phi, lambda));`
+    pub fn u3(&mut self, target: i32, theta: Real, phi: Real, lambda: Real) {
+        self.apply_gate(target, u3(theta, phi, lambda));
+        #[cfg(feature = "decoherence")]
+        self.decohere();
+    }
+
     /// Controlled Not Gate
     /// Shorthand method
     ///
@@ -483,77 +1212,392 @@ impl State {
     /// Toffoli (Controlled-Controlled-NOT gate)
     /// Shorthand method
     pub fn toffoli(&mut self, control1: i32, control2: i32, target: i32) {
-        let result_buffer: Buffer<Complex32> = self.pro_que.create_buffer().unwrap();
-
-        let gate = x();
-
-        let apply = self.pro_que
-            .kernel_builder("apply_controlled_controlled_gate")
-            .arg(&self.buffer)
-            .arg(&result_buffer)
-            .arg(control1)
-            .arg(control2)
-            .arg(target)
-            .arg(gate.a)
-            .arg(gate.b)
-            .arg(gate.c)
-            .arg(gate.d)
-            .build()
-            .unwrap();
-
-        unsafe {
-            apply.enq().unwrap();
-        }
-
-        self.buffer = result_buffer;
+        self.try_toffoli(control1, control2, target).unwrap()
+    }
 
-        #[cfg(feature = "decoherence")]
-        self.decohere();
+    /// Toffoli (Controlled-Controlled-NOT gate)
+    ///
+    /// Returns an error if any qubit is outside of the register, the qubits are
+    /// not distinct, or the backend fails.
+    pub fn try_toffoli(&mut self, control1: i32, control2: i32, target: i32) -> Result<()> {
+        self.try_apply_multi_controlled_gate(
+            &[Control::Positive(control1), Control::Positive(control2)],
+            target,
+            x(),
+        )
     }
 
     /// Swap two qubits in the register
     pub fn swap(&mut self, first_qubit: i32, second_qubit: i32) {
-        let result_buffer: Buffer<Complex32> = self.pro_que.create_buffer().unwrap();
-
-        let apply = self.pro_que
-            .kernel_builder("swap")
-            .arg(&self.buffer)
-            .arg(&result_buffer)
-            .arg(first_qubit)
-            .arg(second_qubit)
-            .build()
-            .unwrap();
-
-        unsafe {
-            apply.enq().unwrap();
-        }
+        self.try_swap(first_qubit, second_qubit).unwrap()
+    }
+
+    /// Swap two qubits in the register
+    ///
+    /// Returns an error if either qubit is outside of the register, the qubits are
+    /// the same, or the backend fails.
+    pub fn try_swap(&mut self, first_qubit: i32, second_qubit: i32) -> Result<()> {
+        self.check_distinct_qubits(&[first_qubit, second_qubit])?;
+        self.backend.swap(first_qubit, second_qubit)
+    }
+
+    /// Apply an arithmetic operation to the registers held in ranges of qubits
+    ///
+    /// ```rust
+    /// # extern crate qcgpu;
+    /// use qcgpu::State;
+    /// use qcgpu::arithmetic::Arithmetic;
+    /// use qcgpu::backends::Cpu;
+    ///
+    /// let mut state = State::from_bit_string_with_backend("|0111>", Cpu::new());
+    /// state.apply_arithmetic(&Arithmetic::MultiplyMod { target: 0..4, factor: 2, modulus: 15 });
+    ///
+    /// assert_eq!(state.measure(), 14);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if any qubit is outside of the register, the ranges overlap, the
+    /// modulus or factor of a modular operation is invalid, or the backend fails.
+    /// See `try_apply_arithmetic` for a version that returns an error instead.
+    pub fn apply_arithmetic(&mut self, operation: &Arithmetic) {
+        self.try_apply_arithmetic(operation).unwrap()
+    }
+
+    /// Apply an arithmetic operation to the registers held in ranges of qubits
+    ///
+    /// Returns an error if any qubit is outside of the register, the ranges overlap, the
+    /// modulus or factor of a modular operation is invalid, or the backend fails.
+    pub fn try_apply_arithmetic(&mut self, operation: &Arithmetic) -> Result<()> {
+        self.try_apply_controlled_arithmetic(&[], operation)
+    }
+
+    /// Apply an arithmetic operation, for the states where every control qubit
+    /// has its required value. `Control::Positive` controls must be 1, and
+    /// `Control::Negative` controls must be 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any qubit is outside of the register, a qubit is given twice, the
+    /// modulus or factor of a modular operation is invalid, or the backend fails.
+    /// See `try_apply_controlled_arithmetic` for a version that returns an error instead.
+    pub fn apply_controlled_arithmetic(&mut self, controls: &[Control], operation: &Arithmetic) {
+        self.try_apply_controlled_arithmetic(controls, operation)
+            .unwrap()
+    }
+
+    /// Apply an arithmetic operation, for the states where every control qubit
+    /// has its required value. `Control::Positive` controls must be 1, and
+    /// `Control::Negative` controls must be 0.
+    ///
+    /// Returns an error if any qubit is outside of the register, a qubit is given twice, the
+    /// modulus or factor of a modular operation is invalid, or the backend fails.
+    pub fn try_apply_controlled_arithmetic(
+        &mut self,
+        controls: &[Control],
+        operation: &Arithmetic,
+    ) -> Result<()> {
+        let mut qubits = operation.qubits();
+        qubits.extend(controls.iter().map(Control::qubit));
+        self.check_distinct_qubits(&qubits)?;
+
+        let permutation = Permutation::new(operation, controls)?;
+        self.backend.apply_permutation(&permutation)?;
+
+        #[cfg(feature = "decoherence")]
+        self.try_decohere()?;
 
-        self.buffer = result_buffer;
+        Ok(())
     }
 
     /// Caclulates f(a) = x^a mod n.
+    ///
+    /// The result is XORed into the lowest `output_width` qubits, where `a` is the
+    /// value of the `input_width` qubits above them. This is
+    /// `Arithmetic::PowModXor` on those ranges.
     pub fn pow_mod(&mut self, x: i32, n: i32, input_width: i32, output_width: i32) {
-        let result_buffer: Buffer<Complex32> = self.pro_que.create_buffer().unwrap();
-
-        let apply = self.pro_que
-            .kernel_builder("apply_pow_mod")
-            .arg(&self.buffer)
-            .arg(&result_buffer)
-            .arg(x)
-            .arg(n)
-            .arg(input_width)
-            .arg(output_width)
-            .build()
-            .unwrap();
-
-        unsafe {
-            apply.enq().unwrap();
+        self.try_pow_mod(x, n, input_width, output_width).unwrap()
+    }
+
+    /// Caclulates f(a) = x^a mod n.
+    ///
+    /// Returns an error if the input and output registers don't fit in the register,
+    /// `n` doesn't fit in the output register, or the backend fails.
+    pub fn try_pow_mod(&mut self, x: i32, n: i32, input_width: i32, output_width: i32) -> Result<()> {
+        if input_width < 0 || output_width < 0 {
+            return Err(Error::InvalidQubit {
+                qubit: input_width.min(output_width),
+                num_qubits: self.num_qubits,
+            });
+        }
+
+        self.try_apply_arithmetic(&Arithmetic::PowModXor {
+            input: output_width..output_width + input_width,
+            output: 0..output_width,
+            base: x.rem_euclid(n.max(1)) as u64,
+            modulus: n.max(0) as u64,
+        })
+    }
+
+    /// Apply every operation of a circuit to the register, in order
+    ///
+    /// Returns the values of the circuit's classical bits, which start out
+    /// false and are set by its measurements.
+    ///
+    /// ```rust
+    /// # extern crate qcgpu;
+    /// use qcgpu::{Circuit, State};
+    /// use qcgpu::backends::Cpu;
+    ///
+    /// let mut circuit = Circuit::new(3);
+    /// circuit.x(0).cx(0, 2).measure_all();
+    ///
+    /// let mut state = State::with_backend(3, Cpu::new());
+    /// assert_eq!(state.run(&circuit), vec![true, false, true]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if an operation fails. See `try_run` for a version that returns an error instead.
+    pub fn run(&mut self, circuit: &Circuit) -> Vec<bool> {
+        self.try_run(circuit).unwrap()
+    }
+
+    /// Apply every operation of a circuit to the register, in order
+    ///
+    /// Returns the values of the circuit's classical bits, which start out
+    /// false and are set by its measurements.
+    ///
+    /// Every operation's qubits are checked before any is applied, so an error
+    /// for a qubit which is outside of the register or given twice leaves the
+    /// register unchanged, as does an error for a condition whose bits are
+    /// reversed, outside of the circuit's bits, or more than 64 bits wide.
+    /// Returns an error if an operation fails, or the backend fails.
+    pub fn try_run(&mut self, circuit: &Circuit) -> Result<Vec<bool>> {
+        for operation in circuit.operations() {
+            self.check_operation(operation, circuit.num_bits())?;
+        }
+
+        let mut bits = vec![false; circuit.num_bits()];
+        for operation in circuit.operations() {
+            self.try_apply_operation(operation, &mut bits)?;
+        }
+
+        Ok(bits)
+    }
+
+    /// Check that the qubits of an operation are inside of the register and distinct,
+    /// and that the bits of a condition can be read as a value
+    fn check_operation(&self, operation: &Operation, num_bits: usize) -> Result<()> {
+        match *operation {
+            Operation::Conditional {
+                ref bits,
+                ref operations,
+                ..
+            } => {
+                if bits.start > bits.end || bits.end > num_bits || bits.end - bits.start > 64 {
+                    return Err(Error::InvalidCondition {
+                        bits: bits.clone(),
+                        num_bits,
+                    });
+                }
+
+                operations
+                    .iter()
+                    .try_for_each(|operation| self.check_operation(operation, num_bits))
+            }
+            _ => self.check_distinct_qubits(&operation.qubits()),
         }
+    }
+
+    /// Apply an operation of a circuit, writing measurement outcomes to `bits`
+    fn try_apply_operation(&mut self, operation: &Operation, bits: &mut [bool]) -> Result<()> {
+        match *operation {
+            Operation::Gate { target, gate } => self.try_apply_gate(target, gate),
+            Operation::ControlledGate {
+                control,
+                target,
+                gate,
+            } => self.try_apply_controlled_gate(control, target, gate),
+            Operation::MultiControlledGate {
+                ref controls,
+                target,
+                gate,
+            } => self.try_apply_multi_controlled_gate(controls, target, gate),
+            Operation::Toffoli {
+                control1,
+                control2,
+                target,
+            } => self.try_toffoli(control1, control2, target),
+            Operation::TwoQubitGate {
+                qubit0,
+                qubit1,
+                gate,
+            } => self.try_apply_two_qubit_gate(qubit0, qubit1, gate),
+            Operation::Matrix {
+                ref qubits,
+                ref gate,
+            } => self.try_apply_matrix(qubits, gate),
+            Operation::Swap { first, second } => self.try_swap(first, second),
+            Operation::Qft {
+                ref qubits,
+                options,
+                inverse: false,
+            } => self.try_qft_with(qubits.clone(), options),
+            Operation::Qft {
+                ref qubits,
+                options,
+                inverse: true,
+            } => self.try_inverse_qft_with(qubits.clone(), options),
+            Operation::Arithmetic {
+                ref controls,
+                ref operation,
+            } => self.try_apply_controlled_arithmetic(controls, operation),
+            Operation::PowMod {
+                x,
+                n,
+                input_width,
+                output_width,
+            } => self.try_pow_mod(x, n, input_width, output_width),
+            Operation::Measure { qubit, bit } => {
+                bits[bit] = self.try_measure_qubit(qubit)?;
+                Ok(())
+            }
+            Operation::Conditional {
+                bits: ref register,
+                value,
+                ref operations,
+            } => {
+                let current = bits[register.clone()]
+                    .iter()
+                    .rev()
+                    .fold(0, |value, &bit| (value << 1) | u64::from(bit));
+
+                if current == value {
+                    for operation in operations {
+                        self.try_apply_operation(operation, bits)?;
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Negate the amplitude of each basis state where `f` is true for the value of
+    /// the qubits, where the first qubit is the lowest bit.
+    ///
+    /// `f` is evaluated once for every value of the qubits.
+    ///
+    /// ```rust
+    /// # extern crate qcgpu;
+    /// use qcgpu::State;
+    /// use qcgpu::backends::Cpu;
+    ///
+    /// let mut state = State::with_backend(3, Cpu::new());
+    /// state.h(0);
+    /// state.h(2);
+    ///
+    /// // Mark the states where qubits 2 and 0 are both 1
+    /// state.apply_phase_oracle(&[2, 0], |x| x == 0b11);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if any qubit is outside of the register, a qubit is given twice, or the
+    /// backend fails. See `try_apply_phase_oracle` for a version that returns an error instead.
+    pub fn apply_phase_oracle<F>(&mut self, qubits: &[i32], f: F)
+    where
+        F: Fn(u64) -> bool,
+    {
+        self.try_apply_phase_oracle(qubits, f).unwrap()
+    }
+
+    /// Negate the amplitude of each basis state where `f` is true for the value of
+    /// the qubits, where the first qubit is the lowest bit.
+    ///
+    /// Returns an error if any qubit is outside of the register, a qubit is given twice,
+    /// or the backend fails.
+    pub fn try_apply_phase_oracle<F>(&mut self, qubits: &[i32], f: F) -> Result<()>
+    where
+        F: Fn(u64) -> bool,
+    {
+        self.check_distinct_qubits(qubits)?;
 
-        self.buffer = result_buffer;
+        let table = TruthTable::new(qubits.len() as u32, f);
+        self.backend.apply_phase_oracle(qubits, &table)?;
 
         #[cfg(feature = "decoherence")]
-        self.decohere();
+        self.try_decohere()?;
+
+        Ok(())
+    }
+
+    /// Flip the output qubit of each basis state where `f` is true for the value of the
+    /// input qubits, where the first qubit of the range is the lowest bit.
+    ///
+    /// `f` is evaluated once for every value of the input qubits.
+    ///
+    /// ```rust
+    /// # extern crate qcgpu;
+    /// use qcgpu::State;
+    /// use qcgpu::backends::Cpu;
+    ///
+    /// let mut state = State::from_bit_string_with_backend("|0101>", Cpu::new());
+    ///
+    /// // Flip qubit 3 if qubits 0..3 hold a prime
+    /// state.apply_bit_oracle(0..3, 3, |x| x == 2 || x == 3 || x == 5 || x == 7);
+    /// assert_eq!(state.measure(), 0b1101);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if any qubit is outside of the register, the output is one of the inputs,
+    /// or the backend fails. See `try_apply_bit_oracle` for a version that returns an
+    /// error instead.
+    pub fn apply_bit_oracle<F>(&mut self, inputs: Range<i32>, output: i32, f: F)
+    where
+        F: Fn(u64) -> bool,
+    {
+        self.try_apply_bit_oracle(inputs, output, f).unwrap()
+    }
+
+    /// Flip the output qubit of each basis state where `f` is true for the value of the
+    /// input qubits, where the first qubit of the range is the lowest bit.
+    ///
+    /// Returns an error if any qubit is outside of the register, the output is one of the
+    /// inputs, or the backend fails.
+    pub fn try_apply_bit_oracle<F>(&mut self, inputs: Range<i32>, output: i32, f: F) -> Result<()>
+    where
+        F: Fn(u64) -> bool,
+    {
+        let mut qubits: Vec<i32> = inputs.collect();
+        qubits.push(output);
+        self.check_distinct_qubits(&qubits)?;
+
+        let inputs = &qubits[..qubits.len() - 1];
+        let table = TruthTable::new(inputs.len() as u32, f);
+        self.backend.apply_bit_oracle(inputs, output, &table)?;
+
+        #[cfg(feature = "decoherence")]
+        self.try_decohere()?;
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("State");
+        let _ = debug
+            .field("backend", &self.backend)
+            .field("num_amps", &self.num_amps)
+            .field("num_qubits", &self.num_qubits);
+
+        #[cfg(feature = "decoherence")]
+        let _ = debug.field("decoherence", &self.decoherence);
+
+        debug.finish()
     }
 }
 
@@ -561,10 +1605,9 @@ impl fmt::Display for State {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut first = true;
 
-        let mut vec_result = vec![Complex32::new(0.0, 0.0); self.num_amps];
-        self.buffer.read(&mut vec_result).enq().unwrap();
+        let amplitudes = self.backend.amplitudes().map_err(|_| fmt::Error)?;
 
-        for (idx, item) in vec_result.iter().enumerate() {
+        for (idx, item) in amplitudes.iter().enumerate() {
             if first {
                 write!(f, "[{idx}]: {}", item, idx = idx).unwrap();
                 first = false;
@@ -0,0 +1,164 @@
+extern crate qcgpu;
+
+mod common;
+
+use qcgpu::gates::{global_phase, h, id, r, rz, s, sdg, sqrt_x, t, tdg, u3, x, y, z, MatrixGate};
+use qcgpu::{Complex, Error, Gate};
+use common::{assert_close, prepare, TOLERANCE};
+
+fn entries(gate: Gate) -> [Complex; 4] {
+    [gate.a, gate.b, gate.c, gate.d]
+}
+
+/// Gates with no special structure, some with a global phase
+fn gates() -> Vec<Gate> {
+    vec![
+        h(),
+        x(),
+        y(),
+        z(),
+        s(),
+        t(),
+        sqrt_x(),
+        global_phase(0.3),
+        u3(0.4, 1.1, -0.6),
+        u3(2.9, -2.0, 0.2) * global_phase(1.7),
+    ]
+}
+
+#[test]
+fn adjoint() {
+    assert_close(&entries(s().adjoint()), &entries(sdg()));
+    assert_close(&entries(t().adjoint()), &entries(tdg()));
+
+    for gate in gates() {
+        assert_close(&entries(gate * gate.adjoint()), &entries(id()));
+        assert_close(&entries(gate.adjoint() * gate), &entries(id()));
+    }
+}
+
+#[test]
+fn product() {
+    let (first, second) = (u3(0.4, 1.1, -0.6), h() * t());
+
+    let mut expected = prepare(2);
+    expected.apply_gate(1, first);
+    expected.apply_gate(1, second);
+
+    let mut state = prepare(2);
+    state.apply_gate(1, second * first);
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn power() {
+    assert_close(&entries(x().pow(0.5)), &entries(sqrt_x()));
+    assert_close(&entries(z().pow(0.5)), &entries(s()));
+    assert_close(&entries(s().pow(0.5)), &entries(t()));
+    assert_close(&entries(t().pow(-2.0)), &entries(sdg()));
+
+    for gate in gates() {
+        assert_close(&entries(gate.pow(0.0)), &entries(id()));
+        assert_close(&entries(gate.pow(1.0)), &entries(gate));
+        assert_close(&entries(gate.pow(2.0)), &entries(gate * gate));
+        assert_close(&entries(gate.pow(-1.0)), &entries(gate.adjoint()));
+
+        let root = gate.pow(1.0 / 3.0);
+        assert_close(&entries(root * root * root), &entries(gate));
+    }
+}
+
+#[test]
+fn unitarity() {
+    for gate in gates() {
+        assert!(gate.is_unitary(TOLERANCE));
+        assert!(gate.pow(0.7).is_unitary(TOLERANCE));
+    }
+
+    let half = Gate {
+        a: Complex::new(0.5, 0.0),
+        b: Complex::new(0.0, 0.0),
+        c: Complex::new(0.0, 0.0),
+        d: Complex::new(0.5, 0.0),
+    };
+    assert!(!half.is_unitary(TOLERANCE));
+
+    let projector = Gate {
+        a: Complex::new(1.0, 0.0),
+        ..half
+    };
+    assert!(!projector.is_unitary(TOLERANCE));
+}
+
+#[test]
+fn global_phase_equality() {
+    let angle = 0.8;
+    assert!(rz(angle).approx_eq_up_to_phase(&r(angle), TOLERANCE));
+    assert!(!rz(angle).approx_eq_up_to_phase(&r(-angle), TOLERANCE));
+
+    for gate in gates() {
+        let phased = gate * global_phase(2.5);
+        assert!(phased.approx_eq_up_to_phase(&gate, TOLERANCE));
+        assert!(gate.approx_eq_up_to_phase(&phased, TOLERANCE));
+    }
+
+    assert!(!x().approx_eq_up_to_phase(&z(), TOLERANCE));
+    assert!(!s().approx_eq_up_to_phase(&sdg(), TOLERANCE));
+}
+
+#[test]
+fn controlled() {
+    for &(control, target) in &[(0, 1), (2, 0)] {
+        let mut expected = prepare(3);
+        expected.cx(control, target);
+
+        let mut state = prepare(3);
+        state.apply_two_qubit_gate(control, target, x().controlled());
+
+        assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+
+        // A controlled global phase is a phase shift on the control
+        let gate = u3(0.4, 1.1, -0.6) * global_phase(0.9);
+
+        let mut expected = prepare(3);
+        expected.apply_controlled_gate(control, target, gate);
+
+        let mut state = prepare(3);
+        state.apply_two_qubit_gate(control, target, gate.controlled());
+
+        assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+    }
+}
+
+#[test]
+fn kronecker_product() {
+    let (first, second, third) = (u3(0.4, 1.1, -0.6), h(), t() * sqrt_x());
+
+    let mut expected = prepare(4);
+    expected.apply_gate(3, first);
+    expected.apply_gate(0, second);
+
+    let mut state = prepare(4);
+    state.apply_two_qubit_gate(3, 0, second.kron(&first));
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+
+    expected.apply_gate(2, third);
+
+    let gate = MatrixGate::from(third)
+        .kron(&MatrixGate::from(second.kron(&first)))
+        .unwrap();
+    assert_eq!(gate.num_qubits(), 3);
+    assert!(gate.is_unitary());
+
+    let mut state = prepare(4);
+    state.apply_matrix(&[3, 0, 2], &gate);
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+
+    match gate.kron(&gate) {
+        Err(Error::InvalidMatrix(4096)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
@@ -0,0 +1,361 @@
+extern crate qcgpu;
+
+mod common;
+
+use std::ops::Range;
+
+use qcgpu::arithmetic::{Arithmetic, Permutation};
+use qcgpu::backends::Cpu;
+use qcgpu::gates::Control;
+use qcgpu::{Complex, Error, State};
+use common::{prepare, TOLERANCE};
+
+/// The value of `width` qubits starting at `start`
+fn field(state: usize, start: u32, width: u32) -> usize {
+    (state >> start) & ((1 << width) - 1)
+}
+
+/// Replace the value of `width` qubits starting at `start`
+fn set_field(state: usize, start: u32, width: u32, value: usize) -> usize {
+    let mask = ((1 << width) - 1) << start;
+    (state & !mask) | ((value << start) & mask)
+}
+
+/// Check, for every basis state, that applying the operation moves its
+/// amplitude to `forward(state)`, and that the result is a permutation
+fn check<F>(num_qubits: u32, controls: &[Control], operation: Arithmetic, forward: F)
+where
+    F: Fn(usize) -> usize,
+{
+    let mut state = prepare(num_qubits);
+    let before = state.get_amplitudes();
+
+    state.apply_controlled_arithmetic(controls, &operation);
+    let after = state.get_amplitudes();
+
+    let mut seen = vec![false; before.len()];
+    for (s, amplitude) in before.iter().enumerate() {
+        let image = forward(s);
+        assert!(!seen[image], "{:?} is not a permutation", operation);
+        seen[image] = true;
+
+        let difference: Complex = after[image] - amplitude;
+        assert!(
+            difference.norm() < TOLERANCE,
+            "{:?} moved state {} to {}, not {}",
+            operation,
+            s,
+            after
+                .iter()
+                .position(|a| (a - amplitude).norm() < TOLERANCE)
+                .unwrap_or(s),
+            image
+        );
+    }
+}
+
+fn pow_mod(base: usize, exponent: usize, modulus: usize) -> usize {
+    (0..exponent).fold(1 % modulus, |r, _| r * base % modulus)
+}
+
+#[test]
+fn addition() {
+    for constant in 0..9 {
+        check(
+            4,
+            &[],
+            Arithmetic::AddConstant {
+                target: 1..4,
+                constant,
+            },
+            |s| set_field(s, 1, 3, field(s, 1, 3) + constant as usize),
+        );
+        check(
+            4,
+            &[],
+            Arithmetic::SubtractConstant {
+                target: 0..3,
+                constant,
+            },
+            |s| set_field(s, 0, 3, field(s, 0, 3).wrapping_sub(constant as usize)),
+        );
+    }
+
+    check(
+        6,
+        &[],
+        Arithmetic::AddRegister {
+            source: 4..6,
+            target: 0..4,
+        },
+        |s| set_field(s, 0, 4, field(s, 0, 4) + field(s, 4, 2)),
+    );
+    check(
+        6,
+        &[],
+        Arithmetic::SubtractRegister {
+            source: 0..3,
+            target: 3..6,
+        },
+        |s| set_field(s, 3, 3, field(s, 3, 3).wrapping_sub(field(s, 0, 3))),
+    );
+
+    // A source wider than the target is reduced modulo the target
+    check(
+        5,
+        &[],
+        Arithmetic::AddRegister {
+            source: 0..3,
+            target: 3..5,
+        },
+        |s| set_field(s, 3, 2, field(s, 3, 2) + field(s, 0, 3)),
+    );
+}
+
+#[test]
+fn modular_multiplication() {
+    for &(factor, modulus) in &[(2, 15), (7, 15), (4, 9), (3, 16), (1, 1), (5, 11)] {
+        check(
+            5,
+            &[],
+            Arithmetic::MultiplyMod {
+                target: 1..5,
+                factor,
+                modulus,
+            },
+            |s| {
+                let target = field(s, 1, 4);
+                if target < modulus as usize {
+                    set_field(s, 1, 4, target * factor as usize % modulus as usize)
+                } else {
+                    s
+                }
+            },
+        );
+    }
+}
+
+#[test]
+fn controlled_multiplication() {
+    let controls = [Control::Positive(0), Control::Negative(5)];
+
+    check(
+        6,
+        &controls,
+        Arithmetic::MultiplyMod {
+            target: 1..5,
+            factor: 7,
+            modulus: 13,
+        },
+        |s| {
+            let target = field(s, 1, 4);
+            if s & 1 == 1 && s & (1 << 5) == 0 && target < 13 {
+                set_field(s, 1, 4, target * 7 % 13)
+            } else {
+                s
+            }
+        },
+    );
+}
+
+#[test]
+fn modular_exponentiation() {
+    check(
+        7,
+        &[],
+        Arithmetic::ExpMod {
+            exponent: 4..7,
+            target: 0..4,
+            base: 7,
+            modulus: 15,
+        },
+        |s| {
+            let target = field(s, 0, 4);
+            if target < 15 {
+                set_field(s, 0, 4, target * pow_mod(7, field(s, 4, 3), 15) % 15)
+            } else {
+                s
+            }
+        },
+    );
+
+    // From a target of 1, this leaves the power in the target
+    let mut state = State::from_bit_string_with_backend("|1010001>", Cpu::new());
+    state.apply_arithmetic(&Arithmetic::ExpMod {
+        exponent: 4..7,
+        target: 0..4,
+        base: 7,
+        modulus: 15,
+    });
+    // 7^5 = 7 (mod 15)
+    assert_eq!(state.measure(), (5 << 4) | 7);
+
+    for &(base, modulus) in &[(2, 15), (3, 15), (6, 7), (0, 1)] {
+        check(
+            7,
+            &[],
+            Arithmetic::PowModXor {
+                input: 3..7,
+                output: 0..3,
+                base,
+                modulus: modulus.min(8),
+            },
+            |s| {
+                let power = pow_mod(base as usize, field(s, 3, 4), modulus.min(8) as usize);
+                s ^ (power & 0b111)
+            },
+        );
+    }
+}
+
+#[test]
+fn pow_mod_matches_arithmetic() {
+    let mut expected = prepare(7);
+    expected.apply_arithmetic(&Arithmetic::PowModXor {
+        input: 4..7,
+        output: 0..4,
+        base: 7,
+        modulus: 15,
+    });
+
+    let mut state = prepare(7);
+    state.pow_mod(7, 15, 3, 4);
+
+    let (state, expected) = (state.get_amplitudes(), expected.get_amplitudes());
+    for (l, r) in state.iter().zip(&expected) {
+        assert!((l - r).norm() < TOLERANCE);
+    }
+}
+
+#[test]
+fn comparison() {
+    check(
+        7,
+        &[],
+        Arithmetic::LessThan {
+            left: 0..3,
+            right: 4..7,
+            result: 3,
+        },
+        |s| {
+            if field(s, 0, 3) < field(s, 4, 3) {
+                s ^ (1 << 3)
+            } else {
+                s
+            }
+        },
+    );
+
+    for constant in 0..10 {
+        check(
+            5,
+            &[Control::Positive(4)],
+            Arithmetic::LessThanConstant {
+                register: 1..4,
+                constant,
+                result: 0,
+            },
+            |s| {
+                if s & (1 << 4) != 0 && field(s, 1, 3) < constant as usize {
+                    s ^ 1
+                } else {
+                    s
+                }
+            },
+        );
+    }
+}
+
+#[test]
+fn large_modulus() {
+    let modulus = (1 << 61) - 1;
+    let factor = 0x0123_4567_89ab_cdef;
+    let permutation = Permutation::new(
+        &Arithmetic::MultiplyMod {
+            target: 0..61,
+            factor,
+            modulus,
+        },
+        &[],
+    )
+    .unwrap();
+
+    for &value in &[1, 2, 12345, modulus - 1, 1 << 60] {
+        let image = (u128::from(value) * u128::from(factor) % u128::from(modulus)) as u64;
+        assert_eq!(permutation.preimage(image), value);
+    }
+
+    // Values not less than the modulus are unchanged
+    assert_eq!(permutation.preimage(modulus), modulus);
+}
+
+#[test]
+fn invalid_operations() {
+    let mut state = State::with_backend(6, Cpu::new());
+
+    match state.try_apply_arithmetic(&Arithmetic::MultiplyMod {
+        target: 0..4,
+        factor: 3,
+        modulus: 15,
+    }) {
+        Err(Error::NotInvertible {
+            value: 3,
+            modulus: 15,
+        }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    for &modulus in &[0, 17] {
+        match state.try_apply_arithmetic(&Arithmetic::ExpMod {
+            exponent: 4..6,
+            target: 0..4,
+            base: 2,
+            modulus,
+        }) {
+            Err(Error::InvalidModulus { width: 4, .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    match state.try_apply_arithmetic(&Arithmetic::AddRegister {
+        source: 0..3,
+        target: 2..5,
+    }) {
+        Err(Error::DuplicateQubit(2)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    match state.try_apply_controlled_arithmetic(
+        &[Control::Positive(1)],
+        &Arithmetic::AddConstant {
+            target: 0..3,
+            constant: 1,
+        },
+    ) {
+        Err(Error::DuplicateQubit(1)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    match state.try_apply_arithmetic(&Arithmetic::AddConstant {
+        target: 4..7,
+        constant: 1,
+    }) {
+        Err(Error::InvalidQubit {
+            qubit: 6,
+            num_qubits: 6,
+        }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // A reversed range is not an empty register
+    match state.try_apply_arithmetic(&Arithmetic::AddConstant {
+        target: Range { start: 3, end: 1 },
+        constant: 1,
+    }) {
+        Err(Error::InvalidQubit { qubit: 1, .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // The register is unchanged by the failed operations
+    assert_eq!(state.measure(), 0);
+}
@@ -0,0 +1,250 @@
+extern crate qcgpu;
+
+mod common;
+
+use std::ops::Range;
+
+use qcgpu::arithmetic::Arithmetic;
+use qcgpu::backends::Cpu;
+use qcgpu::circuit::Operation;
+use qcgpu::gates::{cz, h, rx, x, Control, MatrixGate};
+use qcgpu::{Circuit, Complex, Error, QftOptions, State};
+use common::{assert_close, prepare};
+
+#[test]
+fn matches_state() {
+    let toffoli = MatrixGate::new(
+        (0..64)
+            .map(|i| {
+                let (row, col) = (i / 8, i % 8);
+                let col = if col >= 6 { col ^ 1 } else { col };
+                Complex::new(if row == col { 1.0 } else { 0.0 }, 0.0)
+            })
+            .collect(),
+    )
+    .unwrap();
+    let options = QftOptions {
+        swaps: false,
+        cutoff: Some(2),
+    };
+    let controls = [Control::Positive(0), Control::Negative(5)];
+    let multiply = Arithmetic::MultiplyMod {
+        target: 1..5,
+        factor: 7,
+        modulus: 15,
+    };
+
+    let mut circuit = Circuit::new(6);
+    circuit
+        .h(0)
+        .s(1)
+        .t(2)
+        .x(3)
+        .y(4)
+        .z(5)
+        .sdg(0)
+        .tdg(1)
+        .sqrt_x(2)
+        .rx(3, 0.1)
+        .ry(4, 0.2)
+        .rz(5, 0.3)
+        .u3(0, 0.4, 0.5, 0.6)
+        .cx(1, 2)
+        .toffoli(3, 4, 5)
+        .swap(0, 5)
+        .apply_controlled_gate(2, 0, rx(0.7))
+        .apply_multi_controlled_gate(&controls, 3, h())
+        .apply_two_qubit_gate(4, 1, cz())
+        .apply_matrix(&[5, 3, 1], &toffoli)
+        .qft(1..5)
+        .inverse_qft_with(0..6, options)
+        .apply_controlled_arithmetic(&controls, &multiply)
+        .apply_arithmetic(&Arithmetic::AddConstant {
+            target: 0..3,
+            constant: 5,
+        })
+        .pow_mod(2, 7, 3, 3)
+        .apply_all(h());
+
+    let mut expected = prepare(6);
+    expected.h(0);
+    expected.s(1);
+    expected.t(2);
+    expected.x(3);
+    expected.y(4);
+    expected.z(5);
+    expected.sdg(0);
+    expected.tdg(1);
+    expected.sqrt_x(2);
+    expected.rx(3, 0.1);
+    expected.ry(4, 0.2);
+    expected.rz(5, 0.3);
+    expected.u3(0, 0.4, 0.5, 0.6);
+    expected.cx(1, 2);
+    expected.toffoli(3, 4, 5);
+    expected.swap(0, 5);
+    expected.apply_controlled_gate(2, 0, rx(0.7));
+    expected.apply_multi_controlled_gate(&controls, 3, h());
+    expected.apply_two_qubit_gate(4, 1, cz());
+    expected.apply_matrix(&[5, 3, 1], &toffoli);
+    expected.qft(1..5);
+    expected.inverse_qft_with(0..6, options);
+    expected.apply_controlled_arithmetic(&controls, &multiply);
+    expected.apply_arithmetic(&Arithmetic::AddConstant {
+        target: 0..3,
+        constant: 5,
+    });
+    expected.pow_mod(2, 7, 3, 3);
+    expected.apply_all(h());
+
+    let mut state = prepare(6);
+    assert!(state.run(&circuit).is_empty());
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+
+    // Running it again applies every operation again
+    state.run(&circuit);
+    expected.run(&circuit);
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn builder() {
+    let mut circuit = Circuit::new(3);
+    circuit.h(0).cx(0, 1).measure(1, 4);
+
+    assert_eq!(circuit.num_qubits(), 3);
+    assert_eq!(circuit.num_bits(), 5);
+    assert_eq!(
+        circuit.operations(),
+        &[
+            Operation::Gate {
+                target: 0,
+                gate: h(),
+            },
+            Operation::ControlledGate {
+                control: 0,
+                target: 1,
+                gate: x(),
+            },
+            Operation::Measure { qubit: 1, bit: 4 },
+        ][..]
+    );
+
+    let circuit = circuit.with_bits(8);
+    assert_eq!(circuit.num_bits(), 8);
+    assert_eq!(circuit.clone().with_bits(2).num_bits(), 8);
+
+    let mut circuit = Circuit::new(4);
+    circuit.apply_all(x()).measure_all();
+    assert_eq!(circuit.operations().len(), 8);
+    assert_eq!(circuit.num_bits(), 4);
+}
+
+#[test]
+fn measurement() {
+    let mut circuit = Circuit::new(3);
+    circuit.x(0).x(2).measure_all();
+
+    let (mut state, bits) = circuit.execute(Cpu::new()).unwrap();
+    assert_eq!(bits, vec![true, false, true]);
+    assert_eq!(state.num_qubits, 3);
+    assert_eq!(state.measure(), 0b101);
+
+    // The outcomes of a Bell pair agree
+    let mut bell = Circuit::new(2);
+    bell.h(0).cx(0, 1).measure(0, 1).measure(1, 0);
+
+    let mut outcomes = [0; 2];
+    for seed in 0..20 {
+        let mut state = State::with_backend(2, Cpu::new()).with_seed(seed);
+        let bits = state.run(&bell);
+
+        assert_eq!(bits[0], bits[1]);
+        outcomes[bits[0] as usize] += 1;
+    }
+    assert!(outcomes[0] > 0 && outcomes[1] > 0);
+
+    // Measurements collapse the register, and unmeasured bits stay false
+    let mut circuit = Circuit::new(2).with_bits(3);
+    circuit.h(0).measure(0, 2).cx(0, 1).measure(1, 0);
+
+    let (_, bits) = circuit.execute(Cpu::new()).unwrap();
+    assert_eq!(bits[0], bits[2]);
+    assert!(!bits[1]);
+}
+
+#[test]
+fn invalid_operations() {
+    let mut circuit = Circuit::new(3);
+    circuit.x(0).cx(1, 3);
+
+    let mut state = State::with_backend(3, Cpu::new());
+    match state.try_run(&circuit) {
+        Err(Error::InvalidQubit {
+            qubit: 3,
+            num_qubits: 3,
+        }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let mut circuit = Circuit::new(3);
+    circuit.x(0).toffoli(1, 2, 1);
+    match state.try_run(&circuit) {
+        Err(Error::DuplicateQubit(1)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // As with `State::swap`, a qubit can't be swapped with itself
+    let mut circuit = Circuit::new(3);
+    circuit.x(0).swap(2, 2);
+    match state.try_run(&circuit) {
+        Err(Error::DuplicateQubit(2)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // Nothing was applied
+    assert_eq!(state.measure(), 0);
+
+    assert!(Circuit::new(40).execute(Cpu::with_threads(1).unwrap()).is_err());
+}
+
+#[test]
+fn invalid_conditions() {
+    let condition = |bits| Operation::Conditional {
+        bits,
+        value: 0,
+        operations: vec![Operation::Gate {
+            target: 0,
+            gate: x(),
+        }],
+    };
+
+    // A reversed range of bits
+    let mut circuit = Circuit::new(1).with_bits(4);
+    circuit.x(0).push(condition(Range { start: 3, end: 1 }));
+
+    let mut state = State::with_backend(1, Cpu::new());
+    match state.try_run(&circuit) {
+        Err(Error::InvalidCondition { bits, num_bits: 4 }) => {
+            assert_eq!((bits.start, bits.end), (3, 1))
+        }
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // Too many bits to compare with the value
+    let mut circuit = Circuit::new(1);
+    circuit.x(0).push(condition(0..65));
+    match state.try_run(&circuit) {
+        Err(Error::InvalidCondition { bits, num_bits: 65 }) => assert_eq!(bits, 0..65),
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // Nothing was applied
+    assert_eq!(state.measure(), 0);
+
+    // All 64 bits can be compared
+    let mut circuit = Circuit::new(1);
+    circuit.push(condition(0..64));
+    assert_eq!(state.run(&circuit).len(), 64);
+    assert_eq!(state.measure(), 1);
+}
@@ -0,0 +1,28 @@
+//! Fixtures shared by the test suites
+
+#![allow(dead_code)]
+
+use qcgpu::backends::Cpu;
+use qcgpu::gates::r;
+use qcgpu::{Complex, Real, State};
+
+/// How close amplitudes must be to be treated as equal
+pub const TOLERANCE: Real = 1e-4;
+
+pub fn assert_close(left: &[Complex], right: &[Complex]) {
+    assert_eq!(left.len(), right.len());
+    for (l, r) in left.iter().zip(right) {
+        assert!((l - r).norm() < TOLERANCE, "{:?} != {:?}", left, right);
+    }
+}
+
+/// A register in an uneven superposition, so every amplitude is distinct
+pub fn prepare(num_qubits: u32) -> State {
+    let mut state = State::with_backend(num_qubits, Cpu::new());
+    for i in 0..num_qubits as i32 {
+        state.h(i);
+        state.apply_gate(i, r(0.3 * (i + 1) as Real));
+        state.t(i);
+    }
+    state
+}
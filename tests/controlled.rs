@@ -0,0 +1,103 @@
+extern crate qcgpu;
+
+mod common;
+
+use qcgpu::backends::Cpu;
+use qcgpu::gates::{h, x, Control};
+use qcgpu::{Error, State};
+use common::{assert_close, prepare};
+
+#[test]
+fn positive_controls() {
+    // Only |1111> has all three controls set, and its target is flipped
+    for value in 0..16 {
+        let bits = format!("|{:04b}>", value);
+        let mut state = State::from_bit_string_with_backend(&bits, Cpu::new());
+
+        let controls = [
+            Control::Positive(0),
+            Control::Positive(1),
+            Control::Positive(3),
+        ];
+        state.apply_multi_controlled_gate(&controls, 2, x());
+
+        let expected = if value & 0b1011 == 0b1011 {
+            value ^ 0b100
+        } else {
+            value
+        };
+        assert_eq!(state.measure(), expected);
+    }
+}
+
+#[test]
+fn negative_controls() {
+    for value in 0..8 {
+        let bits = format!("|{:03b}>", value);
+        let mut state = State::from_bit_string_with_backend(&bits, Cpu::new());
+
+        let controls = [Control::Negative(0), Control::Positive(2)];
+        state.apply_multi_controlled_gate(&controls, 1, x());
+
+        let expected = if value & 0b101 == 0b100 {
+            value ^ 0b010
+        } else {
+            value
+        };
+        assert_eq!(state.measure(), expected);
+    }
+
+    // A negative control is a positive control conjugated by X
+    let mut expected = prepare(3);
+    expected.x(0);
+    expected.apply_controlled_gate(0, 2, h());
+    expected.x(0);
+
+    let mut state = prepare(3);
+    state.apply_multi_controlled_gate(&[Control::Negative(0)], 2, h());
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn equivalent_gates() {
+    let mut expected = prepare(4);
+    expected.toffoli(3, 0, 1);
+    expected.cx(2, 0);
+
+    let mut state = prepare(4);
+    state.apply_multi_controlled_gate(&[3.into(), 0.into()], 1, x());
+    state.apply_multi_controlled_gate(&[2.into()], 0, x());
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+
+    // With no controls, the gate is always applied
+    let mut expected = prepare(2);
+    expected.h(1);
+
+    let mut state = prepare(2);
+    state.apply_multi_controlled_gate(&[], 1, h());
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn invalid_controls() {
+    let mut state = State::with_backend(3, Cpu::new());
+
+    match state.try_apply_multi_controlled_gate(
+        &[Control::Positive(1), Control::Negative(1)],
+        0,
+        x(),
+    ) {
+        Err(Error::DuplicateQubit(1)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    assert!(state
+        .try_apply_multi_controlled_gate(&[Control::Negative(0)], 0, x())
+        .is_err());
+    assert!(state
+        .try_apply_multi_controlled_gate(&[Control::Negative(3)], 0, x())
+        .is_err());
+}
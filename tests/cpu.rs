@@ -0,0 +1,268 @@
+extern crate qcgpu;
+extern crate rand;
+
+use std::thread;
+
+use qcgpu::{Real, State};
+use qcgpu::backends::Cpu;
+use qcgpu::gates::{h, ry, x};
+use rand::Rng;
+
+#[test]
+fn register_creation() {
+    for i in 1..18 {
+        let mut state = State::with_backend(i, Cpu::new());
+        assert_eq!(state.measure(), 0);
+    }
+}
+
+#[test]
+fn register_from_bitstring() {
+    let mut state = State::from_bit_string_with_backend("|10110>", Cpu::new());
+    assert_eq!(state.measure(), 22);
+}
+
+#[test]
+fn hadamard() {
+    let mut state = State::with_backend(3, Cpu::new());
+    state.h(1);
+
+    let probabilities = state.get_probabilities();
+    assert!((probabilities[0] - 0.5).abs() < 1e-6);
+    assert!((probabilities[2] - 0.5).abs() < 1e-6);
+
+    state.apply_gate(1, h());
+    assert_eq!(state.measure(), 0);
+}
+
+#[test]
+fn apply_all() {
+    for i in 1..12 {
+        let mut state = State::with_backend(i, Cpu::new());
+        state.apply_all(x());
+
+        assert_eq!(state.measure(), 2_u64.pow(i) - 1);
+    }
+}
+
+#[test]
+fn controlled_not() {
+    let mut state = State::with_backend(2, Cpu::new());
+    state.h(0);
+    state.cx(0, 1);
+
+    let measurements = state.measure_many(1000);
+    assert!(!measurements.contains_key(&0b10) && !measurements.contains_key(&0b01));
+}
+
+#[test]
+fn toffoli() {
+    let mut state = State::from_bit_string_with_backend("|011>", Cpu::new());
+    state.toffoli(0, 1, 2);
+    assert_eq!(state.measure(), 7);
+
+    let mut state = State::from_bit_string_with_backend("|001>", Cpu::new());
+    state.toffoli(0, 1, 2);
+    assert_eq!(state.measure(), 1);
+}
+
+#[test]
+fn swap() {
+    let mut state = State::from_bit_string_with_backend("|0001>", Cpu::new());
+    state.swap(0, 2);
+    assert_eq!(state.measure(), 4);
+}
+
+#[test]
+fn pow_mod() {
+    // |a>|0> -> |a>|7^a mod 15>
+    let mut state = State::from_bit_string_with_backend("|0110000>", Cpu::new());
+    state.pow_mod(7, 15, 3, 4);
+    assert_eq!(state.measure(), (3 << 4) | 13);
+}
+
+#[test]
+fn scratch() {
+    let mut state = State::from_bit_string_with_backend("|11>", Cpu::new());
+    state.add_scratch(2);
+    assert_eq!(state.num_qubits, 4);
+    assert_eq!(state.measure(), 3);
+
+    state.measure_scratch(2);
+    assert_eq!(state.num_qubits, 2);
+    assert_eq!(state.measure(), 3);
+}
+
+#[test]
+fn thread_count() {
+    // Large enough that the state vector is split into several blocks
+    let mut single = State::with_backend(15, Cpu::with_threads(1).unwrap());
+    let mut multi = State::with_backend(15, Cpu::with_threads(4).unwrap());
+
+    for state in [&mut single, &mut multi].iter_mut() {
+        state.apply_all(h());
+        state.cx(14, 0);
+        state.toffoli(3, 13, 7);
+        state.swap(2, 14);
+        state.t(14);
+    }
+
+    assert_eq!(single.get_amplitudes(), multi.get_amplitudes());
+}
+
+#[test]
+fn swap_superposition() {
+    // A swap is equivilent to three controlled nots
+    let mut swapped = State::with_backend(14, Cpu::new());
+    let mut controlled = State::with_backend(14, Cpu::new());
+
+    for state in [&mut swapped, &mut controlled].iter_mut() {
+        state.h(1);
+        state.h(13);
+        state.t(13);
+        state.x(5);
+    }
+
+    swapped.swap(13, 1);
+    swapped.swap(5, 12);
+
+    controlled.cx(1, 13);
+    controlled.cx(13, 1);
+    controlled.cx(1, 13);
+    controlled.cx(5, 12);
+    controlled.cx(12, 5);
+    controlled.cx(5, 12);
+
+    let swapped = swapped.get_amplitudes();
+    let controlled = controlled.get_amplitudes();
+
+    for (a, b) in swapped.iter().zip(controlled.iter()) {
+        assert!((a - b).norm() < 1e-6);
+    }
+}
+
+#[test]
+fn high_target() {
+    let mut state = State::with_backend(15, Cpu::new());
+    state.h(14);
+    state.cx(14, 13);
+
+    let probabilities = state.get_probabilities();
+    assert!((probabilities[0] - 0.5).abs() < 1e-6);
+    assert!((probabilities[3 << 13] - 0.5).abs() < 1e-6);
+}
+
+#[test]
+fn measure_qubit() {
+    for _ in 0..20 {
+        let mut state = State::with_backend(3, Cpu::new());
+        state.h(0);
+        state.cx(0, 1);
+        state.cx(0, 2);
+
+        let outcome = state.measure_qubit(1);
+        let expected = if outcome { 7 } else { 0 };
+
+        // The other qubits collapse with it, and the state stays normalised
+        let probabilities = state.get_probabilities();
+        assert!((probabilities[expected] - 1.0).abs() < 1e-4);
+        assert_eq!(state.measure(), expected as u64);
+    }
+}
+
+#[test]
+fn measure_unlikely_outcome() {
+    // A generator whose every sample is 0, which is below any probability
+    struct Zero;
+    impl Rng for Zero {
+        fn next_u32(&mut self) -> u32 {
+            0
+        }
+    }
+
+    let mut state = State::with_backend(1, Cpu::new());
+    state.set_rng(Zero);
+    state.apply_gate(0, ry(1e-9));
+
+    // The probability of measuring 1 is only rounding error
+    assert!(!state.measure_qubit(0));
+    let amplitudes = state.get_amplitudes();
+    assert!((amplitudes[0].norm() - 1.0).abs() < 1e-4);
+    assert_eq!(amplitudes[1].norm(), 0.0);
+}
+
+#[test]
+fn measure_qubits() {
+    let mut state = State::from_bit_string_with_backend("|1010>", Cpu::new());
+    assert_eq!(state.measure_qubits(&[1, 3, 0]), 0b011);
+
+    let mut state = State::with_backend(4, Cpu::new());
+    state.h(2);
+    let outcome = state.measure_qubits(&[2, 0]);
+    assert!(outcome == 0b00 || outcome == 0b01);
+    assert_eq!(state.measure(), outcome << 2);
+}
+
+#[test]
+fn measure_scratch_collapses() {
+    for _ in 0..20 {
+        let mut state = State::with_backend(2, Cpu::new());
+        state.add_scratch(1);
+        state.h(0);
+        state.cx(0, 2);
+
+        state.measure_scratch(1);
+        assert_eq!(state.num_qubits, 2);
+
+        // Measuring the scratch qubit also determines qubit 0
+        let probabilities = state.get_probabilities();
+        assert!((probabilities.iter().sum::<Real>() - 1.0).abs() < 1e-4);
+        assert!(probabilities[0] > 0.99 || probabilities[1] > 0.99);
+    }
+}
+
+#[test]
+fn seeded() {
+    let run = |seed| {
+        let mut state = State::with_backend(6, Cpu::new()).with_seed(seed);
+        state.apply_all(h());
+
+        let shots = state.measure_many(100);
+        let qubits = state.measure_qubits(&[0, 2, 4]);
+        (shots, qubits, state.measure())
+    };
+
+    assert_eq!(run(7), run(7));
+    assert_ne!(run(7), run(8));
+}
+
+#[test]
+fn sampling() {
+    let mut state = State::with_backend(14, Cpu::new()).with_seed(3);
+    state.h(0);
+    state.x(13);
+
+    // Only two states are possible, sampled with equal probability
+    let measurements = state.measure_many(10000);
+    assert_eq!(measurements.len(), 2);
+    for &outcome in &[1 << 13, (1 << 13) | 1] {
+        let count = measurements[&outcome];
+        assert!(count > 4500 && count < 5500);
+    }
+
+    let first = state.measure_first(1, 1000);
+    assert_eq!(first.len(), 2);
+    assert_eq!(first[&0] + first[&1], 1000);
+}
+
+#[test]
+fn send() {
+    fn is_send<T: Send>() {}
+    is_send::<State>();
+
+    let mut state = State::with_backend(2, Cpu::new()).with_seed(5);
+    state.x(1);
+
+    let outcome = thread::spawn(move || state.measure()).join().unwrap();
+    assert_eq!(outcome, 2);
+}
@@ -0,0 +1,77 @@
+extern crate qcgpu;
+
+use qcgpu::State;
+use qcgpu::backends::{Cpu, OpenCL};
+use qcgpu::device::{list_devices, Device, DeviceType, Selection};
+
+fn device(name: &str, device_type: DeviceType, global_memory: u64) -> Device {
+    Device {
+        platform: 0,
+        index: 0,
+        platform_name: String::from("Test Platform"),
+        name: String::from(name),
+        vendor: String::from("Test Vendor"),
+        device_type,
+        global_memory,
+        max_allocation: global_memory / 4,
+        fp64: true,
+    }
+}
+
+fn devices() -> Vec<Device> {
+    vec![
+        device("Host Processor", DeviceType::Cpu, 1 << 34),
+        device("Integrated Graphics", DeviceType::Gpu, 1 << 30),
+        device("Discrete Graphics", DeviceType::Gpu, 1 << 33),
+    ]
+}
+
+#[test]
+fn first_gpu() {
+    let devices = devices();
+    let chosen = Selection::FirstGpu.choose(&devices).unwrap();
+
+    assert_eq!(chosen.name, "Integrated Graphics");
+    assert!(Selection::FirstGpu.choose(&devices[..1]).is_none());
+}
+
+#[test]
+fn most_memory() {
+    let devices = devices();
+    let chosen = Selection::MostMemory.choose(&devices).unwrap();
+
+    assert_eq!(chosen.name, "Host Processor");
+    assert!(Selection::MostMemory.choose(&[]).is_none());
+}
+
+#[test]
+fn by_name() {
+    let devices = devices();
+    let chosen = Selection::Name(String::from("discrete"))
+        .choose(&devices)
+        .unwrap();
+
+    assert_eq!(chosen.name, "Discrete Graphics");
+    assert!(Selection::Name(String::from("fpga")).choose(&devices).is_none());
+}
+
+#[test]
+fn cpu_backend_has_no_device() {
+    let state = State::with_backend(2, Cpu::new());
+
+    assert!(state.device().is_none());
+}
+
+#[test]
+fn missing_device_is_an_error() {
+    // Without any OpenCL platform the device list is empty or an error, and
+    // either way there is no device after the last one
+    let num_devices = list_devices().map(|devices| devices.len()).unwrap_or(0);
+
+    assert!(OpenCL::new(num_devices).is_err());
+    assert!(State::try_new(2, num_devices).is_err());
+
+    let mut missing = device("Missing Graphics", DeviceType::Gpu, 1 << 30);
+    missing.platform = usize::MAX;
+    assert!(OpenCL::with_device(&missing).is_err());
+}
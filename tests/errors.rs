@@ -0,0 +1,91 @@
+extern crate qcgpu;
+
+use qcgpu::{Error, State};
+use qcgpu::backends::Cpu;
+use qcgpu::gates::{h, x};
+
+#[test]
+fn invalid_qubit() {
+    let mut state = State::with_backend(3, Cpu::new());
+
+    match state.try_apply_gate(3, h()) {
+        Err(Error::InvalidQubit { qubit: 3, num_qubits: 3 }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    match state.try_apply_gate(-1, h()) {
+        Err(Error::InvalidQubit { qubit: -1, .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    assert!(state.try_apply_controlled_gate(0, 5, x()).is_err());
+    assert!(state.try_swap(0, 7).is_err());
+    assert!(state.try_pow_mod(2, 3, 2, 2).is_err());
+
+    // The register is unchanged by the failed operations
+    assert_eq!(state.try_measure().unwrap(), 0);
+}
+
+#[test]
+fn duplicate_qubit() {
+    let mut state = State::with_backend(3, Cpu::new());
+
+    match state.try_toffoli(0, 1, 0) {
+        Err(Error::DuplicateQubit(0)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    assert!(state.try_apply_controlled_gate(2, 2, x()).is_err());
+
+    match state.try_swap(1, 1) {
+        Err(Error::DuplicateQubit(1)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
+
+#[test]
+#[should_panic]
+fn invalid_qubit_panics() {
+    let mut state = State::with_backend(2, Cpu::new());
+    state.h(2);
+}
+
+#[test]
+fn invalid_bit_string() {
+    for bits in &["", "|>", "|012>", "abc"] {
+        match State::try_from_bit_string_with_backend(bits, Cpu::new()) {
+            Err(Error::InvalidBitString(_)) => {}
+            other => panic!("unexpected result {:?} for {:?}", other, bits),
+        }
+    }
+}
+
+#[test]
+fn width_overflow() {
+    match State::try_with_backend(200, Cpu::new()) {
+        Err(Error::WidthOverflow(200)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let bits: String = "1".repeat(65);
+    match State::try_from_bit_string_with_backend(&bits, Cpu::new()) {
+        Err(Error::WidthOverflow(65)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    assert!(State::memory_required(200).is_err());
+}
+
+#[test]
+fn scratch() {
+    let mut state = State::with_backend(2, Cpu::new());
+
+    match state.try_measure_scratch(3) {
+        Err(Error::NotEnoughQubits { requested: 3, num_qubits: 2 }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    state.try_add_scratch(3).unwrap();
+    state.try_measure_scratch(5).unwrap();
+    assert_eq!(state.num_qubits, 0);
+}
@@ -6,9 +6,9 @@ use qcgpu::gates::x;
 #[test]
 fn hadamard() {
     for i in 1..18 {
-        let mut state = State::new(i, 0);
+        let mut state = State::new(i, 0).with_seed(u64::from(i));
 
-        let mut h_state = State::new(i, 0);
+        let mut h_state = State::new(i, 0).with_seed(u64::from(i));
         h_state.h(0);
         h_state.h(0);
 
@@ -32,7 +32,7 @@ fn apply_all() {
         let mut state = State::new(i, 0);
         state.apply_all(x());
 
-        assert_eq!(state.measure(), 2_i32.pow(i) - 1);
+        assert_eq!(state.measure(), 2_u64.pow(i) - 1);
     }
 }
 
@@ -44,7 +44,7 @@ fn controlled_not() {
     state.cx(0, 1);
 
     let measurements = state.measure_many(1000);
-    assert!(!measurements.contains_key("10") && !measurements.contains_key("01"));
+    assert!(!measurements.contains_key(&0b10) && !measurements.contains_key(&0b01));
 }
 
 #[test]
@@ -57,8 +57,8 @@ fn toffoli() {
 
     let measurements = state.measure_many(1000);
     assert!(
-        !measurements.contains_key("100") && !measurements.contains_key("110")
-            && !measurements.contains_key("011") && !measurements.contains_key("101")
+        !measurements.contains_key(&0b100) && !measurements.contains_key(&0b110)
+            && !measurements.contains_key(&0b011) && !measurements.contains_key(&0b101)
     );
 }
 
@@ -69,5 +69,5 @@ fn swap() {
     state.swap(0, 1);
 
     let measurements = state.measure_many(1000);
-    assert!(!measurements.contains_key("01") && !measurements.contains_key("11"));
+    assert!(!measurements.contains_key(&0b01) && !measurements.contains_key(&0b11));
 }
@@ -0,0 +1,117 @@
+extern crate qcgpu;
+extern crate rand;
+
+use qcgpu::algorithms::grover::{
+    amplify, optimal_iterations, search, search_items, search_unknown,
+};
+use qcgpu::backends::Cpu;
+use qcgpu::gates::ry;
+use qcgpu::{Real, State};
+use rand::{Isaac64Rng, SeedableRng};
+
+fn rng(seed: u64) -> Isaac64Rng {
+    Isaac64Rng::from_seed(&[seed][..])
+}
+
+#[test]
+fn iterations() {
+    assert_eq!(optimal_iterations(2, 1), 1);
+    assert_eq!(optimal_iterations(3, 1), 2);
+    assert_eq!(optimal_iterations(8, 1), 12);
+    assert_eq!(optimal_iterations(8, 16), 3);
+    assert_eq!(optimal_iterations(20, 1), 804);
+
+    // Nothing can be gained without solutions, or when everything is a solution
+    assert_eq!(optimal_iterations(5, 0), 0);
+    assert_eq!(optimal_iterations(5, 32), 0);
+    assert_eq!(optimal_iterations(5, 100), 0);
+}
+
+#[test]
+fn single_solution() {
+    for num_qubits in 2..9 {
+        let target = (1 << num_qubits) * 3 / 5;
+        let found = search(
+            num_qubits,
+            1,
+            |x| x == target,
+            || Ok(Cpu::new()),
+            &mut rng(u64::from(num_qubits)),
+        )
+        .unwrap();
+
+        assert_eq!(found.solution, Some(target));
+        assert_eq!(found.measured, target);
+        assert!(found.success_probability > 0.9, "{:?}", found);
+        assert_eq!(
+            found.iterations,
+            u64::from(found.measurements * optimal_iterations(num_qubits, 1))
+        );
+    }
+}
+
+#[test]
+fn marked_items() {
+    let items = [3, 17, 42, 17, 1000];
+
+    for seed in 0..5 {
+        let found = search_items(6, &items, || Ok(Cpu::new()), &mut rng(seed)).unwrap();
+
+        let solution = found.solution.unwrap();
+        assert!(items[..4].contains(&solution), "{:?}", found);
+
+        // 1000 doesn't fit in the register, so there are 3 solutions
+        assert_eq!(found.iterations % u64::from(optimal_iterations(6, 3)), 0);
+        assert!(found.success_probability > 0.9, "{:?}", found);
+    }
+}
+
+#[test]
+fn unknown_number_of_solutions() {
+    for &num_solutions in &[1, 2, 5, 40, 200] {
+        for seed in 0..3 {
+            let f = |x: u64| (x * 37) % 256 < num_solutions;
+            let found = search_unknown(8, f, || Ok(Cpu::new()), &mut rng(seed)).unwrap();
+
+            let solution = found.solution.expect("a solution should be found");
+            assert!(f(solution));
+            assert!(found.measurements > 0);
+        }
+    }
+
+    // Without a solution, the search gives up
+    let found = search_unknown(6, |_| false, || Ok(Cpu::new()), &mut rng(0)).unwrap();
+    assert_eq!(found.solution, None);
+    assert_eq!(found.success_probability, 0.0);
+    assert!(found.iterations + u64::from(found.measurements) >= 18 * 8);
+
+    // With no qubits, the search still terminates
+    let found = search_unknown(0, |_| false, || Ok(Cpu::new()), &mut rng(0)).unwrap();
+    assert_eq!(found.solution, None);
+}
+
+#[test]
+fn amplitude_amplification() {
+    // Qubit 0 starts with a probability of sin^2(theta) of being 1
+    let theta: Real = 0.15;
+    let prepare = |state: &mut State| state.try_apply_gate(0, ry(2.0 * theta));
+    let unprepare = |state: &mut State| state.try_apply_gate(0, ry(-2.0 * theta));
+
+    for iterations in 0..6 {
+        let found = amplify(
+            3,
+            prepare,
+            unprepare,
+            |x| x & 1 == 1,
+            iterations,
+            &mut || Ok(Cpu::new()),
+            &mut rng(0),
+        )
+        .unwrap();
+
+        let expected = ((2 * iterations + 1) as Real * theta).sin().powi(2);
+        assert!((found.success_probability - expected).abs() < 1e-4);
+        assert_eq!(found.solution.is_some(), found.measured & 1 == 1);
+        assert_eq!(found.measured & 0b110, 0);
+    }
+}
@@ -0,0 +1,98 @@
+extern crate qcgpu;
+
+mod common;
+
+use qcgpu::backends::Cpu;
+use qcgpu::gates::{h, r, s, t, x, y, z, MatrixGate};
+use qcgpu::{Complex, Error, Real, State};
+use common::{assert_close, prepare};
+
+/// The Toffoli gate, with the last qubit as the target
+fn toffoli() -> MatrixGate {
+    let mut matrix = vec![Complex::new(0.0, 0.0); 64];
+    for i in 0..8 {
+        let j = if i & 0b011 == 0b011 { i ^ 0b100 } else { i };
+        matrix[j * 8 + i] = Complex::new(1.0, 0.0);
+    }
+
+    MatrixGate::new(matrix).unwrap()
+}
+
+#[test]
+fn single_qubit_gates() {
+    for gate in &[h(), x(), y(), z(), s(), t(), r(1.2)] {
+        for target in 0..4 {
+            let mut expected = prepare(4);
+            expected.apply_gate(target, *gate);
+
+            let mut state = prepare(4);
+            state.apply_matrix(&[target], &MatrixGate::from(*gate));
+
+            assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+        }
+    }
+}
+
+#[test]
+fn three_qubit_gate() {
+    for &(c1, c2, target) in &[(0, 1, 2), (3, 0, 1), (4, 2, 0), (1, 4, 3)] {
+        let mut expected = prepare(5);
+        expected.toffoli(c1, c2, target);
+
+        let mut state = prepare(5);
+        state.apply_matrix(&[c1, c2, target], &toffoli());
+
+        assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+    }
+}
+
+#[test]
+fn five_qubit_gate() {
+    // A Hadamard on each qubit, as one dense 32x32 matrix
+    let norm = (32.0 as Real).sqrt().recip();
+    let matrix = (0..32 * 32)
+        .map(|i: u32| {
+            let parity = ((i / 32) & (i % 32)).count_ones() & 1;
+            let sign = if parity == 0 { 1.0 } else { -1.0 };
+            Complex::new(sign * norm, 0.0)
+        })
+        .collect();
+    let gate = MatrixGate::new(matrix).unwrap();
+
+    let mut expected = prepare(6);
+    for &qubit in &[5, 0, 3, 1, 4] {
+        expected.h(qubit);
+    }
+
+    let mut state = prepare(6);
+    state.apply_matrix(&[5, 0, 3, 1, 4], &gate);
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn validation() {
+    let one = Complex::new(1.0, 0.0);
+
+    match MatrixGate::new(vec![one; 8]) {
+        Err(Error::InvalidMatrix(8)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    match MatrixGate::new(vec![one; 4]) {
+        Err(Error::NotUnitary) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let mut state = State::with_backend(3, Cpu::new());
+    match state.try_apply_matrix(&[0, 1], &toffoli()) {
+        Err(Error::WrongNumberOfQubits {
+            expected: 3,
+            found: 2,
+        }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    assert!(state.try_apply_matrix(&[0, 1, 1], &toffoli()).is_err());
+    assert!(state.try_apply_matrix(&[0, 1, 3], &toffoli()).is_err());
+}
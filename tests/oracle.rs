@@ -0,0 +1,157 @@
+extern crate qcgpu;
+
+mod common;
+
+use qcgpu::backends::Cpu;
+use qcgpu::gates::h;
+use qcgpu::oracle::TruthTable;
+use qcgpu::{Backend, Complex, Error, State};
+use common::{assert_close, prepare};
+
+/// The value of the qubits of a state, with the first qubit the lowest bit
+fn value(state: usize, qubits: &[i32]) -> u64 {
+    qubits.iter().enumerate().fold(0, |x, (bit, &qubit)| {
+        x | (((state >> qubit) as u64 & 1) << bit)
+    })
+}
+
+#[test]
+fn truth_table() {
+    let table = TruthTable::new(7, |x| x % 3 == 1);
+    assert_eq!(table.num_inputs(), 7);
+    for x in 0..128 {
+        assert_eq!(table.get(x), x % 3 == 1);
+    }
+
+    let table = TruthTable::new(0, |_| true);
+    assert!(table.get(0));
+}
+
+#[test]
+fn phase_oracle() {
+    let f = |x: u64| x == 2 || x == 5 || x == 6;
+
+    for qubits in &[vec![0, 1, 2], vec![3, 0, 4], vec![4, 2, 1]] {
+        let mut state = prepare(5);
+        let before = state.get_amplitudes();
+
+        state.apply_phase_oracle(qubits, f);
+
+        let expected: Vec<Complex> = before
+            .iter()
+            .enumerate()
+            .map(|(s, &amp)| if f(value(s, qubits)) { -amp } else { amp })
+            .collect();
+        assert_close(&state.get_amplitudes(), &expected);
+    }
+}
+
+#[test]
+fn bit_oracle() {
+    let f = |x: u64| x.count_ones() == 2;
+
+    for &(ref inputs, output) in &[(0..3, 3), (2..5, 0), (1..4, 4), (0..0, 2)] {
+        let mut state = prepare(5);
+        let before = state.get_amplitudes();
+
+        state.apply_bit_oracle(inputs.clone(), output, f);
+
+        let qubits: Vec<i32> = inputs.clone().collect();
+        let expected: Vec<Complex> = (0..before.len())
+            .map(|s| {
+                if f(value(s, &qubits)) {
+                    before[s ^ (1 << output)]
+                } else {
+                    before[s]
+                }
+            })
+            .collect();
+        assert_close(&state.get_amplitudes(), &expected);
+    }
+}
+
+#[test]
+fn phase_kickback() {
+    // With the output in |->, a bit oracle acts as the phase oracle
+    let f = |x: u64| x % 3 == 0;
+
+    let mut expected = prepare(4);
+    expected.apply_phase_oracle(&[0, 1, 2, 3], f);
+
+    let mut state = prepare(4);
+    state.add_scratch(1);
+    state.x(4);
+    state.apply_gate(4, h());
+    state.apply_bit_oracle(0..4, 4, f);
+    state.apply_gate(4, h());
+
+    let amplitudes = state.get_amplitudes();
+    assert_close(&amplitudes[16..], &expected.get_amplitudes());
+}
+
+#[test]
+fn deutsch_jozsa() {
+    let balanced = |x: u64| (x ^ (x >> 3)) & 1 == 1;
+    let constant = |_| true;
+
+    for &(f, result) in &[
+        (&balanced as &dyn Fn(u64) -> bool, false),
+        (&constant, true),
+    ] {
+        let mut state = State::with_backend(5, Cpu::new());
+        state.apply_all(h());
+        state.apply_phase_oracle(&[0, 1, 2, 3, 4], f);
+        state.apply_all(h());
+
+        assert_eq!(state.measure() == 0, result);
+    }
+}
+
+#[test]
+fn invalid_qubits() {
+    let mut state = State::with_backend(4, Cpu::new());
+
+    match state.try_apply_phase_oracle(&[0, 4], |_| true) {
+        Err(Error::InvalidQubit {
+            qubit: 4,
+            num_qubits: 4,
+        }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    match state.try_apply_phase_oracle(&[1, 2, 1], |_| true) {
+        Err(Error::DuplicateQubit(1)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    match state.try_apply_bit_oracle(0..3, 2, |_| true) {
+        Err(Error::DuplicateQubit(2)) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    assert!(state.try_apply_bit_oracle(0..3, -1, |_| true).is_err());
+}
+
+#[test]
+fn wrong_table_size() {
+    let mut backend = Cpu::new();
+    backend.allocate(3, 0).unwrap();
+
+    // A table for two inputs has no value for most of the values of three qubits
+    let table = TruthTable::new(2, |_| true);
+    match backend.apply_phase_oracle(&[0, 1, 2], &table) {
+        Err(Error::WrongNumberOfQubits {
+            expected: 2,
+            found: 3,
+        }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    assert!(backend.apply_bit_oracle(&[0], 2, &table).is_err());
+}
+
+#[test]
+#[should_panic]
+fn too_many_inputs() {
+    let _ = TruthTable::new(64, |_| true);
+}
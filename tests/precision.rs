@@ -0,0 +1,33 @@
+extern crate qcgpu;
+
+use qcgpu::{Real, State};
+use qcgpu::backends::Cpu;
+use qcgpu::gates::h;
+
+/// Rounding errors accumulate over deep circuits, but the register
+/// should stay normalized to within the precision it is simulated at.
+#[test]
+fn deep_circuit_normalization() {
+    let mut state = State::with_backend(10, Cpu::new());
+
+    for _ in 0..200 {
+        state.apply_all(h());
+        state.t(3);
+        state.cx(3, 7);
+    }
+
+    let norm: Real = state.get_probabilities().iter().sum();
+    assert!((norm - 1.0).abs() < 1e4 * Real::EPSILON, "norm = {}", norm);
+}
+
+#[test]
+fn hadamard_inverse() {
+    let mut state = State::with_backend(4, Cpu::new());
+
+    for _ in 0..1000 {
+        state.h(2);
+    }
+
+    let amplitudes = state.get_amplitudes();
+    assert!((amplitudes[0].re - 1.0).abs() < 1e3 * Real::EPSILON);
+}
@@ -0,0 +1,428 @@
+extern crate qcgpu;
+
+mod common;
+
+use qcgpu::arithmetic::Arithmetic;
+use qcgpu::backends::Cpu;
+use qcgpu::circuit::Operation;
+use qcgpu::gates::{cz, fsim, iswap, Control, Gate, MatrixGate};
+use qcgpu::gates::{h, r, rx, ry, rz, sqrt_x, u3, x, xx, y, z, zz};
+use qcgpu::qasm::{self, Unsupported};
+use qcgpu::{Circuit, Complex, Error, QftOptions, Real, State};
+use common::{assert_close, prepare};
+
+/// Run a program on a prepared register, returning the amplitudes
+fn run(num_qubits: u32, source: &str) -> Vec<Complex> {
+    let circuit = qasm::parse(source).unwrap();
+    let mut state = prepare(num_qubits);
+    let _ = state.run(&circuit);
+    state.get_amplitudes()
+}
+
+#[test]
+fn bell() {
+    let circuit = qasm::parse(
+        r#"
+        OPENQASM 2.0;
+        include "qelib1.inc";
+
+        // A Bell pair
+        qreg q[2];
+        creg c[2];
+        h q[0];
+        cx q[0], q[1];
+        barrier q;
+        measure q -> c;
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(circuit.num_qubits(), 2);
+    assert_eq!(circuit.num_bits(), 2);
+    assert_eq!(
+        circuit.operations()[..2],
+        [
+            Operation::Gate {
+                target: 0,
+                gate: h(),
+            },
+            Operation::ControlledGate {
+                control: 0,
+                target: 1,
+                gate: x(),
+            },
+        ]
+    );
+
+    for _ in 0..10 {
+        let (_, bits) = circuit.execute(Cpu::new()).unwrap();
+        assert_eq!(bits[0], bits[1]);
+    }
+}
+
+#[test]
+fn standard_gates() {
+    let amplitudes = run(
+        3,
+        r#"
+        OPENQASM 2.0;
+        include "qelib1.inc";
+        qreg q[3];
+        U(0.1, 0.2, 0.3) q[0];
+        CX q[0], q[1];
+        u3(0.4, 0.5, 0.6) q[1];
+        u2(0.7, 0.8) q[2];
+        u1(0.9) q[0];
+        p(1.0) q[1];
+        id q[2];
+        x q[0]; y q[1]; z q[2];
+        h q[0]; s q[1]; sdg q[2];
+        t q[0]; tdg q[1];
+        sx q[2]; sxdg q[0];
+        rx(1.1) q[1]; ry(1.2) q[2]; rz(1.3) q[0];
+        cz q[0], q[1];
+        cy q[1], q[2];
+        ch q[2], q[0];
+        csx q[0], q[2];
+        crx(1.4) q[1], q[0];
+        cry(1.5) q[2], q[1];
+        crz(1.6) q[0], q[2];
+        cu1(1.7) q[1], q[2];
+        cp(1.8) q[2], q[0];
+        cu3(1.9, 2.0, 2.1) q[0], q[1];
+        swap q[0], q[2];
+        rxx(2.2) q[1], q[2];
+        rzz(2.3) q[0], q[1];
+        ccx q[0], q[1], q[2];
+        cswap q[2], q[0], q[1];
+        "#,
+    );
+
+    let mut state = prepare(3);
+    state.apply_gate(0, u3(0.1, 0.2, 0.3));
+    state.cx(0, 1);
+    state.apply_gate(1, u3(0.4, 0.5, 0.6));
+    state.apply_gate(2, u3(std::f64::consts::FRAC_PI_2 as Real, 0.7, 0.8));
+    state.apply_gate(0, r(0.9));
+    state.apply_gate(1, r(1.0));
+    state.x(0);
+    state.y(1);
+    state.z(2);
+    state.h(0);
+    state.s(1);
+    state.sdg(2);
+    state.t(0);
+    state.tdg(1);
+    state.apply_gate(2, sqrt_x());
+    state.apply_gate(0, sqrt_x().adjoint());
+    state.apply_gate(1, rx(1.1));
+    state.apply_gate(2, ry(1.2));
+    state.apply_gate(0, rz(1.3));
+    state.apply_controlled_gate(0, 1, z());
+    state.apply_controlled_gate(1, 2, y());
+    state.apply_controlled_gate(2, 0, h());
+    state.apply_controlled_gate(0, 2, sqrt_x());
+    state.apply_controlled_gate(1, 0, rx(1.4));
+    state.apply_controlled_gate(2, 1, ry(1.5));
+    state.apply_controlled_gate(0, 2, rz(1.6));
+    state.apply_controlled_gate(1, 2, r(1.7));
+    state.apply_controlled_gate(2, 0, r(1.8));
+    state.apply_controlled_gate(0, 1, u3(1.9, 2.0, 2.1));
+    state.swap(0, 2);
+    state.apply_two_qubit_gate(1, 2, xx(2.2));
+    state.apply_two_qubit_gate(0, 1, zz(2.3));
+    state.toffoli(0, 1, 2);
+    state.cx(1, 0);
+    state.toffoli(2, 0, 1);
+    state.cx(1, 0);
+
+    assert_close(&amplitudes, &state.get_amplitudes());
+}
+
+#[test]
+fn gate_definitions() {
+    let amplitudes = run(
+        3,
+        r#"
+        OPENQASM 2.0;
+        include "qelib1.inc";
+
+        gate rotate(a, b) p, q {
+            rx(a / 2) p;
+            cx p, q;
+            ry(-b * 2 + pi) q;
+        }
+        gate twice(a) p, q, r {
+            rotate(a, sin(a) ^ 2) p, q;
+            barrier p, r;
+            rotate(sqrt(a), cos(a)) r, p;
+        }
+
+        qreg q[3];
+        twice(0.5) q[1], q[0], q[2];
+        "#,
+    );
+
+    let rotate = |state: &mut State, a: Real, b: Real, p: i32, q: i32| {
+        state.apply_gate(p, rx(a / 2.0));
+        state.cx(p, q);
+        state.apply_gate(q, ry(-b * 2.0 + std::f64::consts::PI as Real));
+    };
+    let mut state = prepare(3);
+    let a: Real = 0.5;
+    rotate(&mut state, a, a.sin().powf(2.0), 1, 0);
+    rotate(&mut state, a.sqrt(), a.cos(), 2, 1);
+
+    assert_close(&amplitudes, &state.get_amplitudes());
+}
+
+#[test]
+fn broadcasting() {
+    let amplitudes = run(
+        5,
+        r#"
+        OPENQASM 2.0;
+        include "qelib1.inc";
+        qreg a[2];
+        qreg b[2];
+        qreg c[1];
+        h a;
+        cx a, b;
+        ccx a, b[1], c;
+        "#,
+    );
+
+    let mut state = prepare(5);
+    state.h(0);
+    state.h(1);
+    state.cx(0, 2);
+    state.cx(1, 3);
+    state.toffoli(0, 3, 4);
+    state.toffoli(1, 3, 4);
+
+    assert_close(&amplitudes, &state.get_amplitudes());
+}
+
+#[test]
+fn conditionals() {
+    let circuit = qasm::parse(
+        r#"
+        OPENQASM 2.0;
+        include "qelib1.inc";
+        qreg q[3];
+        creg c[2];
+        creg d[1];
+        x q[1];
+        measure q[0] -> c[0];
+        measure q[1] -> c[1];
+        if (c == 2) x q[2];
+        if (c == 1) x q[0];
+        if (c == 2) measure q[2] -> d[0];
+        "#,
+    )
+    .unwrap();
+
+    let (mut state, bits) = circuit.execute(Cpu::new()).unwrap();
+    assert_eq!(bits, [false, true, true]);
+    assert_eq!(state.measure(), 0b110);
+}
+
+#[test]
+fn errors() {
+    fn assert_error(source: &str, line: usize, column: usize) {
+        match qasm::parse(source) {
+            Err(Error::Parse {
+                line: l, column: c, ..
+            }) => assert_eq!((l, c), (line, column), "{}", source),
+            other => panic!("unexpected result {:?} for {}", other, source),
+        }
+    }
+
+    assert_error("OPENQASM 3.0;", 1, 10);
+    assert_error("qreg q[1];", 1, 1);
+    assert_error("OPENQASM 2.0;\ninclude \"other.inc\";", 2, 9);
+    assert_error("OPENQASM 2.0;\nqreg q[1];\nh q[0];", 3, 1);
+
+    // Statements after a header declaring q[2] and c[2]
+    let header = "OPENQASM 2.0;\ninclude \"qelib1.inc\";\nqreg q[2];\ncreg c[2];\n";
+    let statements = [
+        ("bogus q[0];", 5, 1),
+        ("h q[2];", 5, 5),
+        ("h r;", 5, 3),
+        ("cx q[0], q[0];", 5, 1),
+        ("cx q[0];", 5, 1),
+        ("rx q[0];", 5, 1),
+        ("h q[0]", 5, 7),
+        ("h c;", 5, 3),
+        ("reset q[0];", 5, 1),
+        ("measure q -> c[0];", 5, 9),
+        ("qreg q[1];", 5, 6),
+        ("if (q == 1) x q[0];", 5, 5),
+        ("opaque magic a;\nmagic q[0];", 6, 1),
+        ("gate g a { h b; }", 5, 12),
+        ("gate g a { g a; }", 5, 12),
+        ("rx(theta) q[0];", 5, 4),
+        ("h q[0]; $", 5, 9),
+    ];
+
+    for &(statement, line, column) in &statements {
+        assert_error(&format!("{}{}", header, statement), line, column);
+    }
+}
+
+/// Export a circuit, import it again, and check both have the same effect
+fn assert_round_trip(circuit: &Circuit) {
+    let source = qasm::export(circuit).unwrap();
+    let imported = qasm::parse(&source).unwrap();
+
+    let mut expected = prepare(circuit.num_qubits());
+    let _ = expected.run(circuit);
+    let mut state = prepare(circuit.num_qubits());
+    let _ = state.run(&imported);
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn export_gates() {
+    let custom = u3(0.3, -1.2, 2.5) * r(0.8);
+    let phased = Gate {
+        a: Complex::new(0.0, 1.0),
+        b: Complex::new(0.0, 0.0),
+        c: Complex::new(0.0, 0.0),
+        d: Complex::new(0.0, 1.0),
+    } * rx(0.4);
+    let controls = [
+        Control::Positive(0),
+        Control::Negative(2),
+        Control::Positive(3),
+    ];
+
+    let mut circuit = Circuit::new(4);
+    circuit
+        .h(0)
+        .t(1)
+        .sdg(2)
+        .sqrt_x(3)
+        .apply_gate(0, custom)
+        .apply_gate(1, r(0.6))
+        .cx(0, 1)
+        .apply_controlled_gate(2, 3, phased)
+        .apply_controlled_gate(1, 0, r(1.1))
+        .apply_controlled_gate(3, 2, h())
+        .apply_multi_controlled_gate(&controls[..2], 1, x())
+        .apply_multi_controlled_gate(&controls, 1, custom)
+        .toffoli(3, 1, 0)
+        .swap(0, 2);
+    assert_round_trip(&circuit);
+}
+
+#[test]
+fn export_matrices() {
+    // The Fourier transform on 3 qubits, whose entries are all non-zero
+    let root = Complex::from_polar(&1.0, &(std::f64::consts::PI as Real / 4.0));
+    let fourier = MatrixGate::new(
+        (0..64)
+            .map(|i| root.powf(((i / 8) * (i % 8)) as Real) / (8.0 as Real).sqrt())
+            .collect(),
+    )
+    .unwrap();
+    let options = QftOptions {
+        swaps: false,
+        cutoff: Some(1),
+    };
+
+    let mut circuit = Circuit::new(4);
+    circuit
+        .apply_two_qubit_gate(0, 2, fsim(0.3, 0.7))
+        .apply_two_qubit_gate(3, 1, iswap())
+        .apply_two_qubit_gate(1, 2, cz())
+        .apply_matrix(&[3, 0, 2], &fourier)
+        .apply_matrix(&[1], &MatrixGate::from(ry(0.2)))
+        .qft(0..4)
+        .inverse_qft_with(1..4, options);
+    assert_round_trip(&circuit);
+}
+
+#[test]
+fn export_classical() {
+    let mut circuit = Circuit::new(3);
+    circuit.x(0).measure(0, 1).measure(1, 2);
+    let _ = circuit
+        .push(Operation::Conditional {
+            bits: 1..3,
+            value: 1,
+            operations: vec![
+                Operation::Gate {
+                    target: 2,
+                    gate: x(),
+                },
+                Operation::Swap {
+                    first: 0,
+                    second: 1,
+                },
+            ],
+        })
+        .push(Operation::Conditional {
+            bits: 0..1,
+            value: 0,
+            operations: vec![Operation::Measure { qubit: 1, bit: 0 }],
+        });
+
+    let source = qasm::export(&circuit).unwrap();
+    assert!(source.contains("creg c0[1];\ncreg c1[2];\n"), "{}", source);
+    assert!(
+        source.contains("if (c1 == 1) swap q[0], q[1];"),
+        "{}",
+        source
+    );
+
+    let (mut state, bits) = qasm::parse(&source).unwrap().execute(Cpu::new()).unwrap();
+    assert_eq!(bits, [true, true, false]);
+    assert_eq!(state.measure(), 0b110);
+}
+
+#[test]
+fn export_unsupported() {
+    let mut circuit = Circuit::new(6);
+    circuit.pow_mod(2, 15, 3, 3).apply_controlled_arithmetic(
+        &[Control::Negative(5)],
+        &Arithmetic::AddConstant {
+            target: 0..3,
+            constant: 5,
+        },
+    );
+
+    match qasm::export(&circuit) {
+        Err(Error::Unsupported { .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let source = qasm::export_with(&circuit, Unsupported::Opaque).unwrap();
+    assert!(source.contains("opaque cadd_constant_4(constant) a0, a1, a2, a3;"));
+    assert!(source.contains("x q[5];\ncadd_constant_4(5) q[5], q[0], q[1], q[2];\nx q[5];"));
+    match qasm::parse(&source) {
+        Err(Error::Parse { line: 6, .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    // Conditions on overlapping bits can't each be a register
+    let conditional = |bits| Operation::Conditional {
+        bits,
+        value: 1,
+        operations: vec![],
+    };
+    let mut circuit = Circuit::new(1);
+    let _ = circuit.push(conditional(0..2)).push(conditional(1..3));
+    match qasm::export(&circuit) {
+        Err(Error::Unsupported { .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let mut circuit = Circuit::new(1);
+    let _ = circuit.cx(0, 1);
+    match qasm::export(&circuit) {
+        Err(Error::InvalidQubit { qubit: 1, .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
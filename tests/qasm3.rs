@@ -0,0 +1,226 @@
+extern crate qcgpu;
+
+mod common;
+
+use qcgpu::backends::Cpu;
+use qcgpu::gates::{global_phase, r, rx, ry, u3};
+use qcgpu::qasm3::Program;
+use qcgpu::{Error, Real, State};
+use common::{assert_close, prepare};
+
+#[test]
+fn gates() {
+    let program = Program::parse(
+        r#"
+        OPENQASM 3.0;
+        include "stdgates.inc";
+
+        gate rotate(a, b) p, q {
+            rx(a / 2) p;
+            cx p, q;
+            ry(-b * 2 + pi) q;
+        }
+
+        qubit[2] q;
+        qubit r;
+        h q;
+        cu(0.1, 0.2, 0.3, 0.4) q[0], r;
+        cphase(0.5) r, q[1];
+        rotate(0.6, tau / 8) q[1], r;
+        U(0.7, 0.8, 0.9) q[0];
+        gphase(1.0);
+        "#,
+    )
+    .unwrap();
+    assert_eq!(program.num_qubits(), 3);
+
+    let mut state = prepare(3);
+    let _ = program.run(&mut state).unwrap();
+
+    let pi = std::f64::consts::PI as Real;
+    let cu = u3(0.1, 0.2, 0.3) * global_phase(0.4);
+    let mut expected = prepare(3);
+    expected.h(0);
+    expected.h(1);
+    expected.apply_controlled_gate(0, 2, cu);
+    expected.apply_controlled_gate(2, 1, r(0.5));
+    expected.apply_gate(1, rx(0.3));
+    expected.cx(1, 2);
+    expected.apply_gate(2, ry(pi / 2.0));
+    expected.apply_gate(0, u3(0.7, 0.8, 0.9));
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn loops() {
+    let program = Program::parse(
+        r#"
+        OPENQASM 3;
+        include "stdgates.inc";
+        qubit[4] q;
+        bit[4] c;
+
+        for int i in [0:3] {
+            rx(pi * i / 4) q[i];
+        }
+        for uint[8] i in [3:-2:0] {
+            cx q[i], q[i - 1];
+        }
+        for i in {0, 2} {
+            x q[i];
+        }
+        c = 5;
+        c[3] = c[0] && !c[1];
+        "#,
+    )
+    .unwrap();
+
+    let mut state = prepare(4);
+    let bits = program.run(&mut state).unwrap();
+    assert_eq!(bits["c"], vec![true, false, true, true]);
+
+    let pi = std::f64::consts::PI as Real;
+    let mut expected = prepare(4);
+    for i in 0..4 {
+        expected.apply_gate(i, rx(pi * i as Real / 4.0));
+    }
+    expected.cx(3, 2);
+    expected.cx(1, 0);
+    expected.x(0);
+    expected.x(2);
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn repeat_until_success() {
+    // Apply a Hadamard and measure until the outcome is 1, counting the attempts
+    let program = Program::parse(
+        r#"
+        OPENQASM 3;
+        include "stdgates.inc";
+        qubit q;
+        qubit[2] ancilla;
+        bit done;
+        bit[4] attempts;
+
+        while (!done && attempts < 15) {
+            reset q;
+            h q;
+            done = measure q;
+            attempts = attempts + 1;
+        }
+
+        // Mid-circuit measurements drive later gates
+        if (done) x ancilla[0]; else x ancilla[1];
+        bit[2] result = measure ancilla;
+        "#,
+    )
+    .unwrap();
+
+    for _ in 0..10 {
+        let (mut state, bits) = program.execute(Cpu::new()).unwrap();
+        let attempts = bits["attempts"]
+            .iter()
+            .rev()
+            .fold(0, |value, &bit| (value << 1) | bit as u32);
+
+        assert!((1..=15).contains(&attempts));
+        if bits["done"][0] {
+            assert_eq!(bits["result"], vec![true, false]);
+            assert_eq!(state.measure(), 0b011);
+        } else {
+            assert_eq!(attempts, 15);
+            assert_eq!(bits["result"], vec![false, true]);
+        }
+    }
+}
+
+#[test]
+fn reset() {
+    let program = Program::parse(
+        r#"
+        OPENQASM 3;
+        qreg q[3];
+        creg c[3];
+        U(pi, 0, pi) q[0];
+        U(pi / 2, 0, pi) q[1];
+        reset q;
+        measure q -> c;
+        "#,
+    )
+    .unwrap();
+
+    let mut state = prepare(4);
+    let bits = program.run(&mut state).unwrap();
+    assert_eq!(bits["c"], vec![false, false, false]);
+
+    // Qubits after those the program declares are left alone
+    assert!(state.get_probabilities()[0b1000] > 0.4);
+}
+
+#[test]
+fn errors() {
+    fn assert_error(source: &str, line: usize, column: usize) {
+        match Program::parse(source) {
+            Err(Error::Parse {
+                line: l, column: c, ..
+            }) => assert_eq!((l, c), (line, column), "{}", source),
+            other => panic!("unexpected result {:?} for {}", other, source),
+        }
+    }
+
+    assert_error("OPENQASM 2.0;", 1, 10);
+    assert_error("OPENQASM 3;\nqubit q;\nh q;", 3, 1);
+
+    // Statements after a header declaring q[2] and c[2]
+    let header = "OPENQASM 3;\ninclude \"stdgates.inc\";\nqubit[2] q;\nbit[2] c;\n";
+    let statements = [
+        ("int i = 0;", 5, 1),
+        ("if (c == 1) { qubit r; }", 5, 15),
+        ("for i in [0:1] { h q[i + j]; }", 5, 26),
+        ("h q[2];", 5, 5),
+        ("c = measure q[0];", 5, 13),
+        ("x c;", 5, 3),
+        ("cx q;", 5, 1),
+        ("rx(q) q[0];", 5, 4),
+        ("gate g a { rx(c) a; }", 5, 15),
+        ("ctrl @ x q[0], q[1];", 5, 6),
+        ("while (c) { h q }", 5, 17),
+    ];
+
+    for &(statement, line, column) in &statements {
+        assert_error(&format!("{}{}", header, statement), line, column);
+    }
+
+    // Problems which depend on values computed while running
+    let program = Program::parse(&format!("{}{}", header, "c = 3; h q[c];")).unwrap();
+    match program.execute(Cpu::new()) {
+        Err(Error::Parse { line: 5, .. }) => {}
+        other => panic!("unexpected result {:?}", other.map(|(_, bits)| bits)),
+    }
+
+    let program = Program::parse(&format!("{}{}", header, "cx q[c[0]], q[0];")).unwrap();
+    match program.execute(Cpu::new()) {
+        Err(Error::Parse { line: 5, .. }) => {}
+        other => panic!("unexpected result {:?}", other.map(|(_, bits)| bits)),
+    }
+
+    // The values of a loop must be integers
+    let program = Program::parse(&format!("{}{}", header, "for i in {1.5, 2} { x q[0]; }")).unwrap();
+    match program.execute(Cpu::new()) {
+        Err(Error::Parse {
+            line: 5,
+            column: 10,
+            ..
+        }) => {}
+        other => panic!("unexpected result {:?}", other.map(|(_, bits)| bits)),
+    }
+
+    let mut state = State::with_backend(1, Cpu::new());
+    match Program::parse(header).unwrap().run(&mut state) {
+        Err(Error::NotEnoughQubits { .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
@@ -0,0 +1,192 @@
+extern crate qcgpu;
+
+mod common;
+
+use qcgpu::backends::Cpu;
+use qcgpu::gates::r;
+use qcgpu::{Complex, Error, QftOptions, Real, State};
+use common::{assert_close, prepare};
+
+/// The classical discrete Fourier transform of the value of the qubits
+/// `low..high`, for each value of the other qubits. `sign` is 1 for the
+/// transform and -1 for its inverse.
+fn dft(amplitudes: &[Complex], low: u32, high: u32, sign: Real) -> Vec<Complex> {
+    let size = 1 << (high - low);
+    let mask = (size - 1) << low;
+    let norm = (size as Real).sqrt().recip();
+    let turn = 4.0 * Real::acos(0.0);
+
+    (0..amplitudes.len())
+        .map(|state| {
+            let y = (state & mask) >> low;
+            (0..size)
+                .map(|x| {
+                    let angle = sign * turn * (x * y) as Real / size as Real;
+                    amplitudes[(state & !mask) | (x << low)] * Complex::from_polar(&norm, &angle)
+                })
+                .fold(Complex::new(0.0, 0.0), |sum, term| sum + term)
+        })
+        .collect()
+}
+
+/// The textbook circuit for the transform, built from controlled rotations
+fn circuit(state: &mut State, low: i32, high: i32, cutoff: i32) {
+    let half_turn = 2.0 * Real::acos(0.0);
+
+    for target in (low..high).rev() {
+        state.h(target);
+        for control in (low.max(target - cutoff)..target).rev() {
+            let angle = half_turn / (1 << (target - control)) as Real;
+            state.apply_controlled_gate(control, target, r(angle));
+        }
+    }
+
+    for i in 0..(high - low) / 2 {
+        state.swap(low + i, high - 1 - i);
+    }
+}
+
+#[test]
+fn discrete_fourier_transform() {
+    for &(low, high) in &[(0, 5), (1, 4), (2, 5), (3, 4)] {
+        let mut state = prepare(5);
+        let expected = dft(&state.get_amplitudes(), low, high, 1.0);
+
+        state.qft(low as i32..high as i32);
+        assert_close(&state.get_amplitudes(), &expected);
+
+        let expected = dft(&state.get_amplitudes(), low, high, -1.0);
+
+        state.inverse_qft(low as i32..high as i32);
+        assert_close(&state.get_amplitudes(), &expected);
+        assert_close(&state.get_amplitudes(), &prepare(5).get_amplitudes());
+    }
+}
+
+#[test]
+fn gates() {
+    for &(low, high) in &[(0, 5), (1, 4), (2, 5), (3, 4)] {
+        let range = low as i32..high as i32;
+        let mut state = prepare(5);
+        let expected = dft(&state.get_amplitudes(), low, high, 1.0);
+
+        state.qft_gates(range.clone(), QftOptions::default(), false);
+        assert_close(&state.get_amplitudes(), &expected);
+
+        let expected = dft(&state.get_amplitudes(), low, high, -1.0);
+
+        state.qft_gates(range, QftOptions::default(), true);
+        assert_close(&state.get_amplitudes(), &expected);
+    }
+
+    // The gates match the kernels with every option
+    for &swaps in &[true, false] {
+        for &cutoff in &[None, Some(1), Some(2)] {
+            let options = QftOptions { swaps, cutoff };
+
+            let mut expected = prepare(6);
+            expected.qft_with(1..6, options);
+
+            let mut state = prepare(6);
+            state.qft_gates(1..6, options, false);
+            assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+
+            expected.inverse_qft_with(0..5, options);
+            state.qft_gates(0..5, options, true);
+            assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+        }
+    }
+
+    let mut state = State::with_backend(3, Cpu::new());
+    assert!(state.try_qft_gates(1..4, QftOptions::default(), false).is_err());
+}
+
+#[test]
+fn controlled_rotations() {
+    let mut expected = prepare(6);
+    circuit(&mut expected, 1, 6, 6);
+
+    let mut state = prepare(6);
+    state.qft(1..6);
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn swap_omission() {
+    let options = QftOptions {
+        swaps: false,
+        ..QftOptions::default()
+    };
+
+    let mut expected = prepare(5);
+    expected.qft(0..4);
+
+    let mut state = prepare(5);
+    state.qft_with(0..4, options);
+    state.swap(0, 3);
+    state.swap(1, 2);
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+
+    state.swap(0, 3);
+    state.swap(1, 2);
+    state.inverse_qft_with(0..4, options);
+
+    assert_close(&state.get_amplitudes(), &prepare(5).get_amplitudes());
+}
+
+#[test]
+fn approximate() {
+    for cutoff in 0..4 {
+        let options = QftOptions {
+            cutoff: Some(cutoff),
+            ..QftOptions::default()
+        };
+
+        let mut expected = prepare(6);
+        circuit(&mut expected, 0, 6, cutoff as i32);
+
+        let mut state = prepare(6);
+        state.qft_with(0..6, options);
+
+        assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+
+        state.inverse_qft_with(0..6, options);
+        assert_close(&state.get_amplitudes(), &prepare(6).get_amplitudes());
+    }
+
+    // A cutoff at least as wide as the range is the exact transform
+    let mut expected = prepare(4);
+    expected.qft(0..4);
+
+    let mut state = prepare(4);
+    state.qft_with(
+        0..4,
+        QftOptions {
+            cutoff: Some(100),
+            ..QftOptions::default()
+        },
+    );
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn invalid_range() {
+    let mut state = State::with_backend(3, Cpu::new());
+
+    match state.try_qft(1..4) {
+        Err(Error::InvalidQubit {
+            qubit: 3,
+            num_qubits: 3,
+        }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    assert!(state.try_inverse_qft(-1..2).is_err());
+
+    // An empty range leaves the register unchanged
+    state.qft(2..2);
+    assert_eq!(state.measure(), 0);
+}
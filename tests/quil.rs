@@ -0,0 +1,467 @@
+extern crate qcgpu;
+
+mod common;
+
+use qcgpu::arithmetic::Arithmetic;
+use qcgpu::backends::Cpu;
+use qcgpu::circuit::Operation;
+use qcgpu::gates::{fsim, iswap, Control, Gate, MatrixGate, TwoQubitGate};
+use qcgpu::gates::{h, r, rx, ry, rz, sqrt_x, u3, x, y, z};
+use qcgpu::quil;
+use qcgpu::{Circuit, Complex, Error, QftOptions, Real};
+use common::{assert_close, prepare};
+
+/// Run a program on a prepared register, returning the amplitudes
+fn run(num_qubits: u32, source: &str) -> Vec<Complex> {
+    let circuit = quil::parse(source).unwrap();
+    let mut state = prepare(num_qubits);
+    let _ = state.run(&circuit);
+    state.get_amplitudes()
+}
+
+#[test]
+fn bell() {
+    let circuit = quil::parse(
+        "
+        # A Bell pair
+        DECLARE ro BIT[2]
+        H 0
+        CNOT 0 1
+        MEASURE 0 ro[0]
+        MEASURE 1 ro[1]
+        ",
+    )
+    .unwrap();
+
+    assert_eq!(circuit.num_qubits(), 2);
+    assert_eq!(circuit.num_bits(), 2);
+    assert_eq!(
+        circuit.operations()[..2],
+        [
+            Operation::Gate {
+                target: 0,
+                gate: h(),
+            },
+            Operation::ControlledGate {
+                control: 0,
+                target: 1,
+                gate: x(),
+            },
+        ]
+    );
+
+    for _ in 0..10 {
+        let (_, bits) = circuit.execute(Cpu::new()).unwrap();
+        assert_eq!(bits[0], bits[1]);
+    }
+}
+
+#[test]
+fn standard_gates() {
+    let amplitudes = run(
+        3,
+        "
+        I 0; X 1; Y 2; Z 0
+        H 1; S 2; T 0
+        PHASE(0.1) 1
+        RX(0.2) 2; RY(-0.3) 0; RZ(pi/5) 1
+        CZ 0 1
+        CNOT 2 0
+        CCNOT 0 1 2
+        CPHASE00(0.4) 0 1
+        CPHASE01(0.5) 1 2
+        CPHASE10(0.6) 2 0
+        CPHASE(0.7) 0 2
+        SWAP 0 1
+        CSWAP 2 0 1
+        ISWAP 1 2
+        PSWAP(0.8) 2 0
+        DAGGER T 1
+        CONTROLLED RX(0.9) 2 1
+        CONTROLLED CONTROLLED H 0 1 2
+        DAGGER CONTROLLED S 1 0
+        CONTROLLED SWAP 1 2 0
+        ",
+    );
+
+    let pi = std::f64::consts::PI as Real;
+    let flipped = |gate: Gate| x() * gate * x();
+    let zero = Complex::new(0.0, 0.0);
+    let one = Complex::new(1.0, 0.0);
+    let phase = Complex::new(0.0, 0.8).exp();
+    let pswap = TwoQubitGate {
+        matrix: [
+            [one, zero, zero, zero],
+            [zero, zero, phase, zero],
+            [zero, phase, zero, zero],
+            [zero, zero, zero, one],
+        ],
+    };
+
+    let mut state = prepare(3);
+    state.x(1);
+    state.y(2);
+    state.z(0);
+    state.h(1);
+    state.s(2);
+    state.t(0);
+    state.apply_gate(1, r(0.1));
+    state.apply_gate(2, rx(0.2));
+    state.apply_gate(0, ry(-0.3));
+    state.apply_gate(1, rz(pi / 5.0));
+    state.apply_controlled_gate(0, 1, z());
+    state.cx(2, 0);
+    state.toffoli(0, 1, 2);
+    state.apply_multi_controlled_gate(&[Control::Negative(0)], 1, flipped(r(0.4)));
+    state.apply_multi_controlled_gate(&[Control::Negative(1)], 2, r(0.5));
+    state.apply_controlled_gate(2, 0, flipped(r(0.6)));
+    state.apply_controlled_gate(0, 2, r(0.7));
+    state.swap(0, 1);
+    state.toffoli(2, 1, 0);
+    state.toffoli(2, 0, 1);
+    state.toffoli(2, 1, 0);
+    state.apply_two_qubit_gate(1, 2, iswap());
+    state.apply_two_qubit_gate(2, 0, pswap);
+    state.tdg(1);
+    state.apply_controlled_gate(2, 1, rx(0.9));
+    state.apply_multi_controlled_gate(&[Control::Positive(0), Control::Positive(1)], 2, h());
+    state.apply_controlled_gate(1, 0, r(-pi / 2.0));
+    state.toffoli(1, 0, 2);
+    state.toffoli(1, 2, 0);
+    state.toffoli(1, 0, 2);
+
+    assert_close(&amplitudes, &state.get_amplitudes());
+}
+
+#[test]
+fn gate_definitions() {
+    let amplitudes = run(
+        3,
+        "
+        DEFGATE HADAMARD:
+            1/sqrt(2), 1/sqrt(2)
+            1/sqrt(2), -1/sqrt(2)
+
+        DEFGATE ROTATE(%theta, %phi) AS MATRIX:
+            cos(%theta/2), -i*sin(%theta/2)*cis(-%phi)
+            -1.0i*sin(%theta/2)*exp(i*%phi), cos(%theta/2)
+
+        # The first qubit is the control
+        DEFGATE MY-CNOT:
+            1, 0, 0, 0
+            0, 1, 0, 0
+            0, 0, 0, 1
+            0, 0, 1, 0
+
+        HADAMARD 0
+        ROTATE(0.5, 2^-1) 1
+        MY-CNOT 2 0
+        DAGGER ROTATE(1.5, 0.25) 2
+        CONTROLLED HADAMARD 1 2
+        CONTROLLED MY-CNOT 0 1 2
+        ",
+    );
+
+    // rx(theta) with its axis rotated by phi about z
+    let rotate = |theta: Real, phi: Real| rz(phi) * rx(theta) * rz(-phi);
+
+    let mut state = prepare(3);
+    state.h(0);
+    state.apply_gate(1, rotate(0.5, 0.5));
+    state.cx(2, 0);
+    state.apply_gate(2, rotate(1.5, 0.25).adjoint());
+    state.apply_controlled_gate(1, 2, h());
+    state.toffoli(0, 1, 2);
+
+    assert_close(&amplitudes, &state.get_amplitudes());
+}
+
+#[test]
+fn conditionals() {
+    let circuit = quil::parse(
+        "
+        DECLARE ro BIT[3]
+        DECLARE flag BIT
+        X 0
+        MEASURE 0 ro[1]
+
+        JUMP-WHEN @skip ro[1]
+        X 1
+        LABEL @skip
+
+        # An if and else
+        JUMP-UNLESS @else flag
+        H 0
+        JUMP @end
+        LABEL @else
+        X 2
+        MEASURE 2 flag
+        LABEL @end
+
+        # Several jumps to the same label test several bits
+        JUMP-WHEN @done ro[1]
+        JUMP-UNLESS @done ro[2]
+        Y 1
+        LABEL @done
+        HALT
+        ",
+    )
+    .unwrap();
+
+    assert_eq!(circuit.num_bits(), 4);
+    assert_eq!(
+        circuit.operations()[2..],
+        [
+            Operation::Conditional {
+                bits: 1..2,
+                value: 0,
+                operations: vec![Operation::Gate {
+                    target: 1,
+                    gate: x(),
+                }],
+            },
+            Operation::Conditional {
+                bits: 3..4,
+                value: 1,
+                operations: vec![Operation::Gate {
+                    target: 0,
+                    gate: h(),
+                }],
+            },
+            Operation::Conditional {
+                bits: 3..4,
+                value: 0,
+                operations: vec![
+                    Operation::Gate {
+                        target: 2,
+                        gate: x(),
+                    },
+                    Operation::Measure { qubit: 2, bit: 3 },
+                ],
+            },
+            Operation::Conditional {
+                bits: 1..3,
+                value: 0b10,
+                operations: vec![Operation::Gate {
+                    target: 1,
+                    gate: y(),
+                }],
+            },
+        ]
+    );
+
+    let (mut state, bits) = circuit.execute(Cpu::new()).unwrap();
+    assert_eq!(bits, [false, true, false, true]);
+    assert_eq!(state.measure(), 0b101);
+}
+
+#[test]
+fn errors() {
+    fn assert_error(source: &str, line: usize, column: usize) {
+        match quil::parse(source) {
+            Err(Error::Parse {
+                line: l, column: c, ..
+            }) => assert_eq!((l, c), (line, column), "{}", source),
+            other => panic!("unexpected result {:?} for {}", other, source),
+        }
+    }
+
+    // Instructions after declaring ro[2]
+    let header = "DECLARE ro BIT[2]\n";
+    let instructions = [
+        ("RESET 0", 2, 1),
+        ("DECLARE theta REAL", 2, 15),
+        ("DECLARE ro BIT", 2, 9),
+        ("FOO 0", 2, 1),
+        ("CNOT 0", 2, 1),
+        ("CNOT 0 0", 2, 8),
+        ("RX 0", 2, 1),
+        ("RX(i) 0", 2, 4),
+        ("H q", 2, 3),
+        ("H 0 ro[0]", 2, 5),
+        ("MEASURE 0", 2, 1),
+        ("MEASURE 0 ro[2]", 2, 14),
+        ("MEASURE 0 c[0]", 2, 11),
+        ("LABEL @a\nX 0\nJUMP-WHEN @a ro[0]", 4, 1),
+        ("JUMP-WHEN @a ro[0]", 2, 1),
+        ("LABEL @a\nLABEL @a", 3, 1),
+        ("JUMP @a\nX 0\nLABEL @a", 2, 1),
+        (
+            "JUMP-WHEN @a ro[0]\nJUMP-WHEN @b ro[1]\nLABEL @a\nLABEL @b",
+            4,
+            1,
+        ),
+        ("HALT\nX 0", 2, 1),
+        ("DEFGATE G:\n    1, 0\n    0, 2", 2, 9),
+        ("DEFGATE G:\n    1, 0, 0\n", 2, 9),
+        ("DEFGATE G:\n    1, 0\n    0", 4, 5),
+        ("DEFGATE G(%a):\n    %b, 0\n    0, 1", 3, 6),
+        ("DEFGATE G AS PERMUTATION:\n    0, 1", 2, 14),
+        ("DEFGATE H:\n    1, 0\n    0, 1", 2, 9),
+    ];
+
+    for &(instruction, line, column) in &instructions {
+        assert_error(&format!("{}{}", header, instruction), line, column);
+    }
+}
+
+/// Export a circuit, import it again, and check both have the same effect
+fn assert_round_trip(circuit: &Circuit) {
+    let source = quil::export(circuit).unwrap();
+    let imported = quil::parse(&source).unwrap();
+
+    let mut expected = prepare(circuit.num_qubits());
+    let _ = expected.run(circuit);
+    let mut state = prepare(circuit.num_qubits());
+    let _ = state.run(&imported);
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn export_gates() {
+    let custom = u3(0.3, -1.2, 2.5) * r(0.8);
+    let controls = [
+        Control::Positive(0),
+        Control::Negative(2),
+        Control::Positive(3),
+    ];
+
+    let mut circuit = Circuit::new(4);
+    circuit
+        .h(0)
+        .t(1)
+        .sdg(2)
+        .sqrt_x(3)
+        .apply_gate(0, custom)
+        .apply_gate(1, r(0.6))
+        .rx(2, -0.7)
+        .cx(0, 1)
+        .apply_controlled_gate(1, 0, r(1.1))
+        .apply_controlled_gate(3, 2, h())
+        .apply_controlled_gate(2, 3, sqrt_x() * r(0.2))
+        .apply_multi_controlled_gate(&controls[..2], 1, x())
+        .apply_multi_controlled_gate(&controls, 1, custom)
+        .toffoli(3, 1, 0)
+        .swap(0, 2);
+    assert_round_trip(&circuit);
+
+    let source = quil::export(&circuit).unwrap();
+    assert!(source.contains("\nDAGGER S 2\n"), "{}", source);
+    assert!(source.contains("\nCONTROLLED H 3 2\n"), "{}", source);
+    assert!(source.contains("\nX 2\nCCNOT 0 2 1\nX 2\n"), "{}", source);
+    assert!(source.contains("\nG0 3\nG1 0\n"), "{}", source);
+    assert!(
+        source.contains("\nCONTROLLED CONTROLLED CONTROLLED G1 0 2 3 1\n"),
+        "{}",
+        source
+    );
+}
+
+#[test]
+fn export_matrices() {
+    // The Fourier transform on 3 qubits, whose entries are all non-zero
+    let root = Complex::from_polar(&1.0, &(std::f64::consts::PI as Real / 4.0));
+    let fourier = MatrixGate::new(
+        (0..64)
+            .map(|i| root.powf(((i / 8) * (i % 8)) as Real) / (8.0 as Real).sqrt())
+            .collect(),
+    )
+    .unwrap();
+    let options = QftOptions {
+        swaps: false,
+        cutoff: Some(1),
+    };
+
+    let mut circuit = Circuit::new(4);
+    circuit
+        .apply_two_qubit_gate(0, 2, fsim(0.3, 0.7))
+        .apply_two_qubit_gate(3, 1, iswap())
+        .apply_matrix(&[3, 0, 2], &fourier)
+        .apply_matrix(&[1], &MatrixGate::from(ry(0.2)))
+        .qft(0..4)
+        .inverse_qft_with(1..4, options);
+    assert_round_trip(&circuit);
+
+    // The first qubit in Quil is the highest bit of the matrix
+    let source = quil::export(&circuit).unwrap();
+    assert!(source.contains("\nG0 2 0\n"), "{}", source);
+    assert!(source.contains("\nG2 2 0 3\n"), "{}", source);
+}
+
+#[test]
+fn export_classical() {
+    let mut circuit = Circuit::new(3);
+    circuit.x(0).measure(0, 1).measure(1, 2);
+    let _ = circuit
+        .push(Operation::Conditional {
+            bits: 1..3,
+            value: 1,
+            operations: vec![
+                Operation::Gate {
+                    target: 2,
+                    gate: x(),
+                },
+                Operation::Conditional {
+                    bits: 0..1,
+                    value: 0,
+                    operations: vec![Operation::Measure { qubit: 2, bit: 1 }],
+                },
+                Operation::Swap {
+                    first: 0,
+                    second: 1,
+                },
+            ],
+        })
+        .push(Operation::Conditional {
+            bits: 0..0,
+            value: 1,
+            operations: vec![Operation::Gate {
+                target: 0,
+                gate: x(),
+            }],
+        });
+
+    let source = quil::export(&circuit).unwrap();
+    assert!(source.starts_with("DECLARE ro BIT[3]\n"), "{}", source);
+    assert!(
+        source.contains("JUMP-UNLESS @skip0 ro[1]\nJUMP-WHEN @skip0 ro[2]\n"),
+        "{}",
+        source
+    );
+
+    // Importing gives the same circuit, other than the condition which can't hold
+    let imported = quil::parse(&source).unwrap();
+    assert_eq!(imported.operations(), &circuit.operations()[..4]);
+
+    let (mut state, bits) = imported.execute(Cpu::new()).unwrap();
+    assert_eq!(bits, [false, true, false]);
+    assert_eq!(state.measure(), 0b110);
+}
+
+#[test]
+fn export_unsupported() {
+    let mut circuit = Circuit::new(6);
+    circuit.pow_mod(2, 15, 3, 3);
+    match quil::export(&circuit) {
+        Err(Error::Unsupported { .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let mut circuit = Circuit::new(3);
+    circuit.apply_arithmetic(&Arithmetic::AddConstant {
+        target: 0..3,
+        constant: 5,
+    });
+    match quil::export(&circuit) {
+        Err(Error::Unsupported { .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+
+    let mut circuit = Circuit::new(2);
+    circuit.cx(0, 2);
+    match quil::export(&circuit) {
+        Err(Error::InvalidQubit { qubit: 2, .. }) => {}
+        other => panic!("unexpected result {:?}", other),
+    }
+}
@@ -0,0 +1,144 @@
+extern crate qcgpu;
+
+mod common;
+
+use qcgpu::gates::{global_phase, h, r, rx, ry, rz, s, sqrt_x, t, u3, x, y, z};
+use qcgpu::{Complex, Gate, Real};
+use common::{assert_close, prepare};
+
+fn entries(gate: Gate) -> [Complex; 4] {
+    [gate.a, gate.b, gate.c, gate.d]
+}
+
+/// Multiply every entry of a gate by a phase
+fn phased(gate: Gate, angle: Real) -> Gate {
+    let phase = Complex::from_polar(&1.0, &angle);
+
+    Gate {
+        a: gate.a * phase,
+        b: gate.b * phase,
+        c: gate.c * phase,
+        d: gate.d * phase,
+    }
+}
+
+#[test]
+fn inverses() {
+    let mut state = prepare(2);
+    state.s(0);
+    state.sdg(0);
+    state.t(1);
+    state.tdg(1);
+
+    assert_close(&state.get_amplitudes(), &prepare(2).get_amplitudes());
+}
+
+#[test]
+fn square_root_of_not() {
+    let mut expected = prepare(3);
+    expected.x(1);
+
+    let mut state = prepare(3);
+    state.sqrt_x(1);
+    state.sqrt_x(1);
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn rotations() {
+    let half_turn = Real::acos(-1.0);
+
+    // Rotations by pi are the Pauli gates, up to a phase of -i
+    assert_close(
+        &entries(rx(half_turn)),
+        &entries(phased(x(), -half_turn / 2.0)),
+    );
+    assert_close(
+        &entries(ry(half_turn)),
+        &entries(phased(y(), -half_turn / 2.0)),
+    );
+    assert_close(
+        &entries(rz(half_turn)),
+        &entries(phased(z(), -half_turn / 2.0)),
+    );
+
+    // Rz is the phase shift gate, up to a global phase
+    let angle = 0.8;
+    assert_close(
+        &entries(rz(angle)),
+        &entries(phased(r(angle), -angle / 2.0)),
+    );
+
+    // Rotations about X are rotations about Z in the Hadamard basis
+    let mut expected = prepare(2);
+    expected.h(0);
+    expected.rz(0, angle);
+    expected.h(0);
+
+    let mut state = prepare(2);
+    state.rx(0, angle);
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn universal_gate() {
+    let quarter_turn = Real::acos(0.0);
+    let (theta, phi, lambda) = (0.4, 1.1, -0.6);
+
+    assert_close(&entries(u3(theta, 0.0, 0.0)), &entries(ry(theta)));
+    assert_close(
+        &entries(u3(theta, -quarter_turn, quarter_turn)),
+        &entries(rx(theta)),
+    );
+
+    // U3 is Rz(phi) Ry(theta) Rz(lambda), up to a global phase
+    let mut expected = prepare(1);
+    expected.rz(0, lambda);
+    expected.ry(0, theta);
+    expected.rz(0, phi);
+
+    let mut state = prepare(1);
+    state.u3(0, theta, phi, lambda);
+    state.apply_gate(0, global_phase(-(phi + lambda) / 2.0));
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn euler_angles() {
+    let gates = [
+        h(),
+        x(),
+        y(),
+        z(),
+        s(),
+        t(),
+        sqrt_x(),
+        rx(0.3),
+        u3(2.1, -0.4, 0.9),
+        phased(u3(0.7, 1.3, 2.8), 0.5),
+    ];
+
+    for &gate in &gates {
+        let (alpha, beta, gamma, delta) = gate.to_euler_zyz();
+
+        assert_close(
+            &entries(Gate::from_euler_zyz(alpha, beta, gamma, delta)),
+            &entries(gate),
+        );
+
+        // Applying the rotations one at a time gives the same state
+        let mut expected = prepare(1);
+        expected.apply_gate(0, gate);
+
+        let mut state = prepare(1);
+        state.rz(0, delta);
+        state.ry(0, gamma);
+        state.rz(0, beta);
+        state.apply_gate(0, global_phase(alpha));
+
+        assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+    }
+}
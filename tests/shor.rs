@@ -0,0 +1,62 @@
+extern crate qcgpu;
+extern crate rand;
+
+use qcgpu::algorithms::shor::{factor, find_order};
+use qcgpu::backends::Cpu;
+use rand::{Isaac64Rng, SeedableRng};
+
+fn rng(seed: u64) -> Isaac64Rng {
+    Isaac64Rng::from_seed(&[seed][..])
+}
+
+#[test]
+fn order_finding() {
+    for &(a, n, order) in &[
+        (2, 15, 4),
+        (4, 15, 2),
+        (7, 15, 4),
+        (2, 21, 6),
+        (5, 21, 6),
+        (2, 35, 12),
+    ] {
+        let found = find_order(a, n, &mut || Ok(Cpu::new()), &mut rng(a * n)).unwrap();
+        assert_eq!(found, Some(order), "order of {} mod {}", a, n);
+    }
+
+    // a must be coprime to n
+    assert_eq!(
+        find_order(3, 15, &mut || Ok(Cpu::new()), &mut rng(0)).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn factoring() {
+    for &(n, factors) in &[(15, (3, 5)), (21, (3, 7)), (33, (3, 11)), (35, (5, 7))] {
+        for seed in 0..3 {
+            let found = factor(n, || Ok(Cpu::new()), &mut rng(seed)).unwrap();
+            assert_eq!(found, Some(factors), "factors of {}", n);
+        }
+    }
+}
+
+#[test]
+fn classical_cases() {
+    let mut rng = rng(0);
+
+    // These are factored without creating a register
+    let no_backend = || -> qcgpu::Result<Cpu> { panic!("no register should be needed") };
+
+    assert_eq!(factor(22, no_backend, &mut rng).unwrap(), Some((2, 11)));
+    assert_eq!(factor(27, no_backend, &mut rng).unwrap(), Some((3, 9)));
+    assert_eq!(factor(49, no_backend, &mut rng).unwrap(), Some((7, 7)));
+    assert_eq!(
+        factor(1 << 40, no_backend, &mut rng).unwrap(),
+        Some((2, 1 << 39))
+    );
+
+    for &prime in &[2, 3, 13, 31, 1_000_000_007] {
+        assert_eq!(factor(prime, no_backend, &mut rng).unwrap(), None);
+    }
+    assert_eq!(factor(1, no_backend, &mut rng).unwrap(), None);
+}
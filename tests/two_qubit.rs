@@ -0,0 +1,160 @@
+extern crate qcgpu;
+
+mod common;
+
+use qcgpu::backends::Cpu;
+use qcgpu::gates::{cz, fsim, h, iswap, r, sqrt_iswap, xx, yy, zz};
+use qcgpu::{Complex, Gate, Real, State, TwoQubitGate};
+use common::{assert_close, prepare};
+
+/// The CNOT gate with qubit 0 as the control
+fn cnot() -> TwoQubitGate {
+    let zero = Complex::new(0.0, 0.0);
+    let one = Complex::new(1.0, 0.0);
+
+    TwoQubitGate {
+        matrix: [
+            [one, zero, zero, zero],
+            [zero, zero, zero, one],
+            [zero, zero, one, zero],
+            [zero, one, zero, zero],
+        ],
+    }
+}
+
+#[test]
+fn qubit_order() {
+    for &(control, target) in &[(0, 1), (1, 0), (3, 1), (0, 4)] {
+        let mut expected = prepare(5);
+        expected.cx(control, target);
+
+        let mut state = prepare(5);
+        state.apply_two_qubit_gate(control, target, cnot());
+
+        assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+    }
+}
+
+#[test]
+fn controlled_z() {
+    let mut state = State::from_bit_string_with_backend("|11>", Cpu::new());
+    state.apply_two_qubit_gate(0, 1, cz());
+
+    let amplitudes = state.get_amplitudes();
+    assert_close(&amplitudes[3..], &[Complex::new(-1.0, 0.0)]);
+
+    // Equivalent to a CNOT conjugated by Hadamards on the target
+    let mut expected = prepare(3);
+    expected.h(2);
+    expected.cx(0, 2);
+    expected.h(2);
+
+    let mut state = prepare(3);
+    state.apply_two_qubit_gate(2, 0, cz());
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn iswap_gates() {
+    let mut state = State::from_bit_string_with_backend("|01>", Cpu::new());
+    state.apply_two_qubit_gate(0, 1, iswap());
+
+    let zero = Complex::new(0.0, 0.0);
+    assert_close(
+        &state.get_amplitudes(),
+        &[zero, zero, Complex::new(0.0, 1.0), zero],
+    );
+
+    let mut expected = prepare(3);
+    expected.apply_two_qubit_gate(1, 2, iswap());
+
+    let mut state = prepare(3);
+    state.apply_two_qubit_gate(1, 2, sqrt_iswap());
+    state.apply_two_qubit_gate(1, 2, sqrt_iswap());
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn fsim_gates() {
+    let quarter = Real::acos(0.0);
+
+    // fSim with a swap angle of -pi/2 and no phase is iSWAP
+    let mut expected = prepare(3);
+    expected.apply_two_qubit_gate(0, 2, iswap());
+
+    let mut state = prepare(3);
+    state.apply_two_qubit_gate(0, 2, fsim(-quarter, 0.0));
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+
+    // fSim with no swap angle is a controlled phase
+    let mut expected = prepare(2);
+    expected.apply_controlled_gate(0, 1, r(-0.7));
+
+    let mut state = prepare(2);
+    state.apply_two_qubit_gate(0, 1, fsim(0.0, 0.7));
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}
+
+#[test]
+fn ising_gates() {
+    let angle = 0.9;
+
+    // ZZ(angle) is CNOT, Rz(angle) on the target, CNOT, up to a global phase
+    let mut state = prepare(2);
+    state.apply_two_qubit_gate(0, 1, zz(angle));
+
+    let mut expected = prepare(2);
+    expected.cx(0, 1);
+    expected.apply_gate(1, r(angle));
+    expected.cx(0, 1);
+
+    let phase = Complex::from_polar(&1.0, &(-angle / 2.0));
+    let expected: Vec<Complex> = expected
+        .get_amplitudes()
+        .iter()
+        .map(|amp| amp * phase)
+        .collect();
+
+    assert_close(&state.get_amplitudes(), &expected);
+
+    // XX and YY are ZZ in a rotated basis
+    let mut state = prepare(2);
+    state.apply_two_qubit_gate(0, 1, xx(angle));
+
+    let mut expected = prepare(2);
+    expected.h(0);
+    expected.h(1);
+    expected.apply_two_qubit_gate(0, 1, zz(angle));
+    expected.h(0);
+    expected.h(1);
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+
+    // Y = S H Z H S†
+    let s_dagger = Gate {
+        a: Complex::new(1.0, 0.0),
+        b: Complex::new(0.0, 0.0),
+        c: Complex::new(0.0, 0.0),
+        d: Complex::new(0.0, -1.0),
+    };
+
+    let mut state = prepare(2);
+    state.apply_two_qubit_gate(0, 1, yy(angle));
+
+    let mut expected = prepare(2);
+    for &qubit in &[0, 1] {
+        expected.apply_gate(qubit, s_dagger);
+        expected.apply_gate(qubit, h());
+    }
+    expected.apply_two_qubit_gate(0, 1, zz(angle));
+    for &qubit in &[0, 1] {
+        expected.apply_gate(qubit, h());
+        expected.s(qubit);
+    }
+
+    assert_close(&state.get_amplitudes(), &expected.get_amplitudes());
+}